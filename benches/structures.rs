@@ -0,0 +1,97 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use spatial::bench_utils::SyntheticWorld;
+use spatial::{Geometry, HashGrid, InterestManager, LinearIndex};
+
+const WORLD_SIZE: f32 = 10_000.0;
+const CELLS: [u32; 2] = [100, 100];
+const SEED: u64 = 42;
+
+fn insert_throughput(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert");
+
+    for count in [1_000, 10_000, 100_000] {
+        let world = SyntheticWorld::uniform(count, WORLD_SIZE, SEED);
+        group.throughput(Throughput::Elements(count as u64));
+
+        group.bench_with_input(BenchmarkId::new("hashgrid", count), &world, |b, world| {
+            b.iter(|| {
+                let mut grid = HashGrid::<f32, _>::new(CELLS, 0, &world.bounds, false);
+                grid.update(&world.entities);
+                black_box(grid);
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("linear", count), &world, |b, world| {
+            b.iter(|| {
+                let mut index = LinearIndex::<f32, _>::new();
+                world.entities.iter().for_each(|e| index.insert(e));
+                black_box(index);
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn query_latency(c: &mut Criterion) {
+    let mut group = c.benchmark_group("query_radius");
+
+    for count in [1_000, 10_000, 100_000] {
+        let world = SyntheticWorld::clustered(count, WORLD_SIZE, 16, 500.0, SEED);
+        let centres = world.sample_points(64, SEED);
+
+        let mut grid = HashGrid::<f32, _>::new(CELLS, 0, &world.bounds, false);
+        grid.update(&world.entities);
+
+        let mut index = LinearIndex::<f32, _>::new();
+        world.entities.iter().for_each(|e| index.insert(e));
+
+        group.bench_function(BenchmarkId::new("hashgrid", count), |b| {
+            let mut results = Vec::new();
+            b.iter(|| {
+                for &centre in &centres {
+                    grid.query_into(&Geometry::radius(centre, 250.0), &mut results);
+                    black_box(&results);
+                }
+            })
+        });
+
+        group.bench_function(BenchmarkId::new("linear", count), |b| {
+            let mut results = Vec::new();
+            b.iter(|| {
+                for &centre in &centres {
+                    index.query_into(&Geometry::radius(centre, 250.0), &mut results);
+                    black_box(&results);
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn rebuild_cost(c: &mut Criterion) {
+    let mut group = c.benchmark_group("interest_update");
+
+    for count in [1_000, 10_000] {
+        let world = SyntheticWorld::clustered(count, WORLD_SIZE, 16, 500.0, SEED);
+
+        let mut manager = InterestManager::<f32, u32>::new(CELLS, 0, &world.bounds, false);
+        for (id, centre) in world.sample_points(64, SEED).into_iter().enumerate() {
+            manager.subscribe(id as u32, centre, 250.0);
+        }
+
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_function(BenchmarkId::new("hashgrid", count), |b| {
+            b.iter(|| {
+                manager.update(&world.entities);
+                black_box(manager.poll_events().count());
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, insert_throughput, query_latency, rebuild_cost);
+criterion_main!(benches);
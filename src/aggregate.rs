@@ -0,0 +1,108 @@
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use num_traits::Float;
+
+use crate::geometry::Geometry;
+use crate::hashgrid::Coordinate;
+use crate::math::distance;
+
+/// `Entities` computes aggregate shapes over a set of entities, typically the results of a
+/// query, such as the area covered by a formation or the ring of units encircling a target.
+///
+/// It is implemented for slices of references, which covers both the vectors returned by
+/// the geometry queries and [`QueryResult::data`](crate::hashgrid::QueryResult::data).
+pub trait Entities<'a, T: Coordinate> {
+    /// Returns the entities on the convex hull of the set projected onto the x/y plane,
+    /// in counter-clockwise order. Entities lying on the edges of the hull are left out.
+    ///
+    /// Sets of less than three entities are returned as they are, without duplicates.
+    fn convex_hull(&self) -> Vec<&'a T>;
+
+    /// Returns the smallest axis aligned [`Geometry::Rect`] enclosing every entity, or `None`
+    /// if the set is empty
+    fn bounding_rect(&self) -> Option<Geometry<T::Item>>;
+
+    /// Returns a [`Geometry::Radius`] enclosing every entity, centred on the centre of their
+    /// bounding rect, or `None` if the set is empty.
+    ///
+    /// The circle is cheap to compute but not the smallest one enclosing the entities.
+    fn bounding_circle(&self) -> Option<Geometry<T::Item>>;
+}
+
+impl<'a, T, F> Entities<'a, T> for [&'a T]
+where
+    F: Float,
+    T: Coordinate<Item = F>,
+{
+    fn convex_hull(&self) -> Vec<&'a T> {
+        let mut points: Vec<&'a T> = self.to_vec();
+        points.sort_by(|a, b| {
+            (a.x(), a.y())
+                .partial_cmp(&(b.x(), b.y()))
+                .unwrap_or(Ordering::Equal)
+        });
+        points.dedup_by(|a, b| a.x() == b.x() && a.y() == b.y());
+
+        if points.len() < 3 {
+            return points;
+        }
+
+        // Andrew's monotone chain, building the lower then the upper half of the hull
+        let cross = |o: &T, a: &T, b: &T| {
+            (a.x() - o.x()) * (b.y() - o.y()) - (a.y() - o.y()) * (b.x() - o.x())
+        };
+
+        let mut hull: Vec<&'a T> = Vec::with_capacity(points.len() + 1);
+        for &point in points.iter() {
+            while hull.len() >= 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= F::zero()
+            {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+
+        // The upper half starts from the last point of the lower one, which is kept
+        let lower = hull.len() + 1;
+        for &point in points.iter().rev().skip(1) {
+            while hull.len() >= lower
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= F::zero()
+            {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+
+        // The last point closes the hull on the first one
+        hull.pop();
+        hull
+    }
+
+    fn bounding_rect(&self) -> Option<Geometry<F>> {
+        let first = self.first()?;
+        let start = (
+            (first.x(), first.y(), first.z()),
+            (first.x(), first.y(), first.z()),
+        );
+
+        let (min, max) = self.iter().fold(start, |(min, max), e| {
+            (
+                (min.0.min(e.x()), min.1.min(e.y()), min.2.min(e.z())),
+                (max.0.max(e.x()), max.1.max(e.y()), max.2.max(e.z())),
+            )
+        });
+
+        Some(Geometry::rect_from_corners(min, max))
+    }
+
+    fn bounding_circle(&self) -> Option<Geometry<F>> {
+        let centre = self.bounding_rect()?.centre();
+        let radius = self
+            .iter()
+            .map(|e| distance(centre, (e.x(), e.y(), e.z())))
+            .fold(F::zero(), F::max);
+
+        Some(Geometry::radius(centre, radius))
+    }
+}
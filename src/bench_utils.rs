@@ -0,0 +1,142 @@
+//! Synthetic worlds shared by the benchmarks of the crate, so performance work on the
+//! structures is measured against the same baseline.
+//!
+//! Worlds are generated from a seed with a small deterministic generator, the same seed
+//! always producing the same entities.
+
+use alloc::vec::Vec;
+
+use crate::hashgrid::{Coordinate, Entity, GridBoundary};
+
+/// Entity of a [`SyntheticWorld`], identified by its index in the world
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SyntheticEntity {
+    pub id: u32,
+    pub position: [f32; 3],
+}
+
+impl Entity for SyntheticEntity {
+    type ID = u32;
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+}
+
+impl Coordinate for SyntheticEntity {
+    type Item = f32;
+
+    fn x(&self) -> Self::Item {
+        self.position[0]
+    }
+
+    fn y(&self) -> Self::Item {
+        self.position[1]
+    }
+
+    fn z(&self) -> Self::Item {
+        self.position[2]
+    }
+}
+
+/// # Synthetic World
+///
+/// A flat square world centred on the origin, populated with entities either spread
+/// uniformly or gathered around a few hotspots, the latter being closer to the crowds
+/// of a real game world.
+#[derive(Debug)]
+pub struct SyntheticWorld {
+    pub bounds: GridBoundary<f32>,
+    pub entities: Vec<SyntheticEntity>,
+}
+
+impl SyntheticWorld {
+    /// Spreads `count` entities uniformly over a world of `size` world units on each side
+    pub fn uniform(count: usize, size: f32, seed: u64) -> Self {
+        let mut rng = SplitMix64(seed);
+        let half = size / 2.0;
+
+        let entities = (0..count)
+            .map(|id| SyntheticEntity {
+                id: id as u32,
+                position: [rng.range(-half, half), rng.range(-half, half), 0.0],
+            })
+            .collect();
+
+        Self::with_entities(size, entities)
+    }
+
+    /// Gathers `count` entities around `clusters` hotspots placed uniformly over a world of
+    /// `size` world units on each side.
+    ///
+    /// Entities lie within `spread` world units of their hotspot, clamped to the world.
+    pub fn clustered(count: usize, size: f32, clusters: usize, spread: f32, seed: u64) -> Self {
+        let mut rng = SplitMix64(seed);
+        let half = size / 2.0;
+
+        let hotspots = (0..clusters.max(1))
+            .map(|_| [rng.range(-half, half), rng.range(-half, half)])
+            .collect::<Vec<_>>();
+
+        let entities = (0..count)
+            .map(|id| {
+                let hotspot = hotspots[id % hotspots.len()];
+                let x = hotspot[0] + rng.range(-spread, spread);
+                let y = hotspot[1] + rng.range(-spread, spread);
+
+                SyntheticEntity {
+                    id: id as u32,
+                    position: [x.clamp(-half, half), y.clamp(-half, half), 0.0],
+                }
+            })
+            .collect();
+
+        Self::with_entities(size, entities)
+    }
+
+    fn with_entities(size: f32, entities: Vec<SyntheticEntity>) -> Self {
+        Self {
+            bounds: GridBoundary {
+                center: [0.0; 3],
+                size: [size, size, 0.0],
+            },
+            entities,
+        }
+    }
+
+    /// Deterministic sample of `count` entity positions, e.g. to use as query centres
+    pub fn sample_points(&self, count: usize, seed: u64) -> Vec<(f32, f32, f32)> {
+        if self.entities.is_empty() {
+            return Vec::new();
+        }
+
+        let mut rng = SplitMix64(seed);
+        (0..count)
+            .map(|_| {
+                let entity = &self.entities[rng.next() as usize % self.entities.len()];
+                (entity.x(), entity.y(), entity.z())
+            })
+            .collect()
+    }
+}
+
+/// SplitMix64 generator, small and good enough to scatter entities without a dependency
+pub(crate) struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64 {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in the range `[min, max)`
+    pub(crate) fn range(&mut self, min: f32, max: f32) -> f32 {
+        // The upper 24 bits fill the mantissa of an f32 exactly
+        let unit = (self.next() >> 40) as f32 / (1u64 << 24) as f32;
+        min + (max - min) * unit
+    }
+}
@@ -0,0 +1,162 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use num_traits::Float;
+
+use crate::geometry::Geometry;
+
+/// Corners of an axis aligned box
+type Aabb<F> = ((F, F, F), (F, F, F));
+
+/// # Geometry Group
+///
+/// Shape combining several [`Geometry`] with boolean operations, such as "in zone A or zone B
+/// but not C", built from [`Geometry::union`], [`Geometry::intersection`] and
+/// [`Geometry::difference`]. Queries such as [`HashGrid::query_group`](crate::HashGrid::query_group)
+/// test the whole group in a single traversal of the cells.
+///
+/// * `Shape:` Single geometry
+/// * `Union:` Points inside any of the groups
+/// * `Intersection:` Points inside every one of the groups
+/// * `Difference:` Points inside the first group but outside the second one
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GeometryGroup<F> {
+    Shape(Geometry<F>),
+    Union(Vec<GeometryGroup<F>>),
+    Intersection(Vec<GeometryGroup<F>>),
+    Difference(Box<GeometryGroup<F>>, Box<GeometryGroup<F>>),
+}
+
+impl<F: Float> From<Geometry<F>> for GeometryGroup<F> {
+    fn from(geometry: Geometry<F>) -> Self {
+        GeometryGroup::Shape(geometry)
+    }
+}
+
+impl<F: Float> Geometry<F> {
+    /// Combines the shape with the `other` one into a [`GeometryGroup`] containing the points
+    /// inside either of them
+    pub fn union(self, other: impl Into<GeometryGroup<F>>) -> GeometryGroup<F> {
+        GeometryGroup::from(self).union(other)
+    }
+
+    /// Combines the shape with the `other` one into a [`GeometryGroup`] containing the points
+    /// inside both of them
+    pub fn intersection(self, other: impl Into<GeometryGroup<F>>) -> GeometryGroup<F> {
+        GeometryGroup::from(self).intersection(other)
+    }
+
+    /// Combines the shape with the `other` one into a [`GeometryGroup`] containing the points
+    /// inside this shape but outside the `other` one
+    pub fn difference(self, other: impl Into<GeometryGroup<F>>) -> GeometryGroup<F> {
+        GeometryGroup::from(self).difference(other)
+    }
+}
+
+impl<F: Float> GeometryGroup<F> {
+    /// Extends the group to the points inside the `other` group as well
+    pub fn union(self, other: impl Into<GeometryGroup<F>>) -> Self {
+        match self {
+            GeometryGroup::Union(mut groups) => {
+                groups.push(other.into());
+                GeometryGroup::Union(groups)
+            }
+            group => GeometryGroup::Union(vec![group, other.into()]),
+        }
+    }
+
+    /// Narrows the group down to the points inside the `other` group as well
+    pub fn intersection(self, other: impl Into<GeometryGroup<F>>) -> Self {
+        match self {
+            GeometryGroup::Intersection(mut groups) => {
+                groups.push(other.into());
+                GeometryGroup::Intersection(groups)
+            }
+            group => GeometryGroup::Intersection(vec![group, other.into()]),
+        }
+    }
+
+    /// Takes the points inside the `other` group out of the group
+    pub fn difference(self, other: impl Into<GeometryGroup<F>>) -> Self {
+        GeometryGroup::Difference(Box::new(self), Box::new(other.into()))
+    }
+
+    /// Tests whether the `point` is inside the group. Empty unions and intersections contain
+    /// no point.
+    pub fn contains(&self, point: (F, F, F)) -> bool {
+        match self {
+            GeometryGroup::Shape(geometry) => geometry.contains(point),
+            GeometryGroup::Union(groups) => groups.iter().any(|g| g.contains(point)),
+            GeometryGroup::Intersection(groups) => {
+                !groups.is_empty() && groups.iter().all(|g| g.contains(point))
+            }
+            GeometryGroup::Difference(include, exclude) => {
+                include.contains(point) && !exclude.contains(point)
+            }
+        }
+    }
+
+    /// Tests whether every geometry of the group is valid, see [`Geometry::is_valid`]
+    pub fn is_valid(&self) -> bool {
+        match self {
+            GeometryGroup::Shape(geometry) => geometry.is_valid(),
+            GeometryGroup::Union(groups) | GeometryGroup::Intersection(groups) => {
+                groups.iter().all(|g| g.is_valid())
+            }
+            GeometryGroup::Difference(include, exclude) => include.is_valid() && exclude.is_valid(),
+        }
+    }
+
+    /// Returns the `min` and `max` corners of the axis aligned box enclosing the group, or
+    /// `None` if the group cannot contain any point.
+    pub fn bounding_box(&self) -> Option<Aabb<F>> {
+        self.boxes().into_iter().reduce(enclose)
+    }
+
+    /// Axis aligned boxes together covering the group, so that queries only visit the cells
+    /// around each part of a union rather than the box enclosing all of them
+    pub(crate) fn boxes(&self) -> Vec<Aabb<F>> {
+        match self {
+            GeometryGroup::Shape(geometry) => vec![geometry.bounding_box()],
+            GeometryGroup::Union(groups) => groups.iter().flat_map(|g| g.boxes()).collect(),
+            GeometryGroup::Intersection(groups) => {
+                let Some((first, others)) = groups.split_first() else {
+                    return Vec::new();
+                };
+
+                // Parts of the first group are clipped to the extent of every other group
+                let mut boxes = first.boxes();
+                for other in others {
+                    let Some(extent) = other.bounding_box() else {
+                        return Vec::new();
+                    };
+                    boxes = boxes
+                        .into_iter()
+                        .filter_map(|b| overlap(b, extent))
+                        .collect();
+                }
+
+                boxes
+            }
+            GeometryGroup::Difference(include, _) => include.boxes(),
+        }
+    }
+}
+
+/// Box enclosing both boxes
+fn enclose<F: Float>(a: Aabb<F>, b: Aabb<F>) -> Aabb<F> {
+    (
+        (a.0 .0.min(b.0 .0), a.0 .1.min(b.0 .1), a.0 .2.min(b.0 .2)),
+        (a.1 .0.max(b.1 .0), a.1 .1.max(b.1 .1), a.1 .2.max(b.1 .2)),
+    )
+}
+
+/// Overlap of both boxes, or `None` if they are disjoint
+fn overlap<F: Float>(a: Aabb<F>, b: Aabb<F>) -> Option<Aabb<F>> {
+    let min = (a.0 .0.max(b.0 .0), a.0 .1.max(b.0 .1), a.0 .2.max(b.0 .2));
+    let max = (a.1 .0.min(b.1 .0), a.1 .1.min(b.1 .1), a.1 .2.min(b.1 .2));
+
+    (min.0 <= max.0 && min.1 <= max.1 && min.2 <= max.2).then_some((min, max))
+}
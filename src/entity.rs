@@ -0,0 +1,133 @@
+use alloc::{boxed::Box, rc::Rc, sync::Arc};
+
+use num_traits::Float;
+
+use crate::geometry::Geometry;
+use crate::hashgrid::{Coordinate, DataIndex, Entity, ExtentEntity, WeightedEntity};
+
+/// Single trait describing an entity stored in the spatial structures, combining its unique
+/// id and its position.
+///
+/// Every type implementing `SpatialEntity` implements [`Entity`] and [`Coordinate`] through
+/// blanket adapters, so one impl serves every structure of the crate. Types already
+/// implementing those traits directly keep working as before.
+///
+/// * `Id:` Type of the unique id of the entity
+/// * `Scalar:` Float type of the coordinates, such as `f32` or `f64`
+///
+/// References, [`Box`], [`Rc`] and [`Arc`] forward the traits to the entity they point to,
+/// so shared handles can be stored without newtype wrappers.
+pub trait SpatialEntity {
+    type Id: DataIndex;
+    type Scalar: Float;
+
+    /// Mandatory method to return the unique id of the entity
+    fn id(&self) -> Self::Id;
+
+    /// Mandatory method to return the `x`, `y` and `z` coordinates of the entity, with a zero
+    /// `z` for 2D entities
+    fn position(&self) -> (Self::Scalar, Self::Scalar, Self::Scalar);
+}
+
+impl<E: SpatialEntity> Entity for E {
+    type ID = E::Id;
+
+    fn id(&self) -> Self::ID {
+        SpatialEntity::id(self)
+    }
+}
+
+impl<E: SpatialEntity> Coordinate for E {
+    type Item = E::Scalar;
+
+    fn x(&self) -> Self::Item {
+        self.position().0
+    }
+
+    fn y(&self) -> Self::Item {
+        self.position().1
+    }
+
+    fn z(&self) -> Self::Item {
+        self.position().2
+    }
+}
+
+// The blanket adapters above rule out forwarding `Entity` for references and boxes directly,
+// since downstream crates may implement `SpatialEntity` for those, so they forward through
+// `SpatialEntity` instead
+macro_rules! impl_spatial_handles(
+    ( $( $handle:ty ),* ) => {
+        $(
+            impl<T: Entity + Coordinate + ?Sized> SpatialEntity for $handle {
+                type Id = T::ID;
+                type Scalar = T::Item;
+
+                fn id(&self) -> Self::Id {
+                    Entity::id(&**self)
+                }
+
+                fn position(&self) -> (Self::Scalar, Self::Scalar, Self::Scalar) {
+                    ((**self).x(), (**self).y(), (**self).z())
+                }
+            }
+
+            impl<T: ExtentEntity + Entity + ?Sized> ExtentEntity for $handle {
+                fn extent(&self) -> Geometry<Self::Item> {
+                    (**self).extent()
+                }
+            }
+
+            impl<T: WeightedEntity + Entity + ?Sized> WeightedEntity for $handle {
+                fn weight(&self) -> Self::Item {
+                    (**self).weight()
+                }
+            }
+        )*
+    };
+);
+
+macro_rules! impl_shared_handles(
+    ( $( $handle:ty ),* ) => {
+        $(
+            impl<T: Entity + ?Sized> Entity for $handle {
+                type ID = T::ID;
+
+                fn id(&self) -> Self::ID {
+                    (**self).id()
+                }
+            }
+
+            impl<T: Coordinate + ?Sized> Coordinate for $handle {
+                type Item = T::Item;
+
+                fn x(&self) -> Self::Item {
+                    (**self).x()
+                }
+
+                fn y(&self) -> Self::Item {
+                    (**self).y()
+                }
+
+                fn z(&self) -> Self::Item {
+                    (**self).z()
+                }
+            }
+
+            impl<T: ExtentEntity + ?Sized> ExtentEntity for $handle {
+                fn extent(&self) -> Geometry<Self::Item> {
+                    (**self).extent()
+                }
+            }
+
+            impl<T: WeightedEntity + ?Sized> WeightedEntity for $handle {
+                fn weight(&self) -> Self::Item {
+                    (**self).weight()
+                }
+            }
+        )*
+    };
+);
+
+impl_spatial_handles!(&T, Box<T>);
+impl_shared_handles!(Rc<T>, Arc<T>);
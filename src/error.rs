@@ -0,0 +1,52 @@
+use core::fmt;
+
+/// Errors reported by the fallible `try_*` methods of the spatial structures, for callers
+/// which need to know why an operation was rejected instead of a plain `bool`.
+///
+/// Coordinates are reported as `f64` whatever the base float type of the structure. `Id` is
+/// the [`Entity::ID`](crate::hashgrid::Entity::ID) of the entities held by the structure,
+/// errors unrelated to any entity, such as [`Geometry::try_rect`](crate::Geometry::try_rect),
+/// keep the default.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpatialError<Id = u64> {
+    /// The coordinates have a NaN or infinite component
+    InvalidCoordinates([f64; 3]),
+    /// The coordinates lie outside the bounds of a structure which does not wrap them
+    OutOfBounds([f64; 3]),
+    /// No entity with the id is stored in the structure
+    UnknownEntity(Id),
+    /// An entity with the id is already stored in the structure
+    DuplicateId(Id),
+    /// A parameter has a different number of components than the structure expects
+    DimensionMismatch { expected: usize, found: usize },
+    /// The query geometry has a non-finite component or a negative extent
+    InvalidQuery,
+}
+
+impl<Id: fmt::Debug> fmt::Display for SpatialError<Id> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpatialError::InvalidCoordinates([x, y, z]) => {
+                write!(f, "invalid coordinates (x= {x}, y= {y}, z= {z})")
+            }
+            SpatialError::OutOfBounds([x, y, z]) => {
+                write!(
+                    f,
+                    "coordinates (x= {x}, y= {y}, z= {z}) are outside the bounds"
+                )
+            }
+            SpatialError::UnknownEntity(id) => write!(f, "unknown entity with id {id:?}"),
+            SpatialError::DuplicateId(id) => {
+                write!(f, "an entity with id {id:?} is already stored")
+            }
+            SpatialError::DimensionMismatch { expected, found } => {
+                write!(f, "expected {expected} components, found {found}")
+            }
+            SpatialError::InvalidQuery => {
+                write!(f, "query geometry is not finite or has a negative extent")
+            }
+        }
+    }
+}
+
+impl<Id: fmt::Debug> core::error::Error for SpatialError<Id> {}
@@ -0,0 +1,163 @@
+use alloc::{vec, vec::Vec};
+use core::hash::{BuildHasher, Hash};
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+
+use crate::hashgrid::{Boundary, HashGrid};
+
+/// # Scalar Field
+///
+/// Dense 2D grid of values laid over the world bounds, such as the influence maps or
+/// pheromone trails of game AI. Amounts are deposited into the cells with
+/// [`ScalarField::deposit`], fade away with [`ScalarField::decay`], and are read back
+/// smoothly with [`ScalarField::sample`] and [`ScalarField::gradient`].
+///
+/// The cells have the same size as the cells of a [`HashGrid`] with the same bounds and
+/// number of cells, see [`ScalarField::from_grid`]. They are counted from the minimum corner of
+/// the bounds, so they line up with the cells of the grid whenever the corner falls on a cell
+/// boundary of the grid, such as for bounds centred on the origin with an even number of cells.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScalarField<F> {
+    cells: [u32; 2],
+    min: [F; 2],
+    cell_size: [F; 2],
+    values: Vec<F>,
+}
+
+impl<F> ScalarField<F>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+{
+    /// Creates a field of zeros with the number of `cells` along the x and y axis, covering
+    /// the `bounds`. At least one cell is created along each axis.
+    pub fn new<B>(cells: [u32; 2], bounds: &B) -> Self
+    where
+        B: Boundary<Item = F>,
+    {
+        let cells = cells.map(|c| c.max(1));
+        let (min, size) = (bounds.min(), bounds.size());
+
+        Self {
+            cells,
+            min: [min[0], min[1]],
+            cell_size: [
+                size[0] / F::from_u32(cells[0]).unwrap(),
+                size[1] / F::from_u32(cells[1]).unwrap(),
+            ],
+            values: vec![F::zero(); cells[0] as usize * cells[1] as usize],
+        }
+    }
+
+    /// Creates a field of zeros matching the bounds and the cells of the `floor` of the grid
+    pub fn from_grid<T, Hx, S>(grid: &HashGrid<'_, F, T, Hx, S>, floor: usize) -> Self
+    where
+        Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+        S: BuildHasher,
+    {
+        Self::new(grid.floor_cells(floor), &grid.bounds)
+    }
+
+    /// Returns the number of cells along the x and y axis
+    pub fn cells(&self) -> [u32; 2] {
+        self.cells
+    }
+
+    /// Returns the cell containing the `point`, or `None` if it lies outside the bounds
+    pub fn cell_of(&self, point: (F, F)) -> Option<[u32; 2]> {
+        let cell = |v: F, axis: usize| {
+            let offset = (v - self.min[axis]) / self.cell_size[axis];
+            let cells = F::from_u32(self.cells[axis]).unwrap();
+
+            // The far edge of the bounds belongs to the last cell
+            match offset >= F::zero() && offset <= cells {
+                true => offset.floor().to_u32().map(|c| c.min(self.cells[axis] - 1)),
+                false => None,
+            }
+        };
+
+        Some([cell(point.0, 0)?, cell(point.1, 1)?])
+    }
+
+    /// Returns the value of the `cell`, or `None` if it is not a cell of the field
+    pub fn value(&self, cell: [u32; 2]) -> Option<F> {
+        (cell[0] < self.cells[0] && cell[1] < self.cells[1]).then(|| self.values[self.index(cell)])
+    }
+
+    /// Adds the `amount` to the cell containing the `point`.
+    ///
+    /// Returns `false` if the point lies outside the bounds, nothing being deposited.
+    pub fn deposit(&mut self, point: (F, F), amount: F) -> bool {
+        match self.cell_of(point) {
+            Some(cell) => {
+                let index = self.index(cell);
+                self.values[index] = self.values[index] + amount;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Decays every value exponentially by the `rate` over one tick, each value being
+    /// multiplied by `e^-rate`
+    pub fn decay(&mut self, rate: F) {
+        let factor = (-rate).exp();
+        for value in self.values.iter_mut() {
+            *value = *value * factor;
+        }
+    }
+
+    /// Resets every value to zero
+    pub fn clear(&mut self) {
+        self.values.fill(F::zero());
+    }
+
+    /// Samples the field at the `point`, interpolating bilinearly between the values held at
+    /// the centres of the four closest cells. Points beyond the outermost centres take the
+    /// values of the edge.
+    pub fn sample(&self, point: (F, F)) -> F {
+        let ((x0, x1, tx), (y0, y1, ty)) = (self.axis(point.0, 0), self.axis(point.1, 1));
+        let v = |x, y| self.values[self.index([x, y])];
+
+        let bottom = v(x0, y0) + (v(x1, y0) - v(x0, y0)) * tx;
+        let top = v(x0, y1) + (v(x1, y1) - v(x0, y1)) * tx;
+
+        bottom + (top - bottom) * ty
+    }
+
+    /// Gradient of the bilinear interpolation of [`ScalarField::sample`] at the `point`, in
+    /// value per world unit along the x and y axis, pointing towards the higher values
+    pub fn gradient(&self, point: (F, F)) -> (F, F) {
+        let ((x0, x1, tx), (y0, y1, ty)) = (self.axis(point.0, 0), self.axis(point.1, 1));
+        let v = |x, y| self.values[self.index([x, y])];
+        let one = F::one();
+
+        let dx = ((v(x1, y0) - v(x0, y0)) * (one - ty) + (v(x1, y1) - v(x0, y1)) * ty)
+            / self.cell_size[0];
+        let dy = ((v(x0, y1) - v(x0, y0)) * (one - tx) + (v(x1, y1) - v(x1, y0)) * tx)
+            / self.cell_size[1];
+
+        (dx, dy)
+    }
+
+    /// Position of the value along the `axis`, as the two cells to interpolate between and
+    /// the fraction of the way from the first to the second
+    fn axis(&self, v: F, axis: usize) -> (u32, u32, F) {
+        let half = F::from_f64(0.5).unwrap();
+        let last = self.cells[axis] - 1;
+
+        // Coordinates relative to the centre of the first cell, in cells
+        let u = ((v - self.min[axis]) / self.cell_size[axis] - half)
+            .max(F::zero())
+            .min(F::from_u32(last).unwrap());
+
+        let c0 = u.floor().to_u32().unwrap_or(0).min(last.saturating_sub(1));
+        let c1 = (c0 + 1).min(last);
+
+        (c0, c1, u - F::from_u32(c0).unwrap())
+    }
+
+    fn index(&self, cell: [u32; 2]) -> usize {
+        cell[1] as usize * self.cells[0] as usize + cell[0] as usize
+    }
+}
@@ -0,0 +1,219 @@
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
+use alloc::vec::Vec;
+
+use num_traits::Float;
+
+use crate::geometry::Geometry;
+use crate::hashgrid::{Coordinate, DataIndex, Entity};
+use crate::partition::SpatialQuery;
+
+/// Change in the state of an entity regarding a fence, queued by the updates of a
+/// [`Geofence`] and consumed through [`Geofence::poll_events`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GeofenceEvent<Id, F> {
+    /// The entity moved inside the fence
+    Entered { fence: Id, entity: Id },
+    /// The entity stayed inside the fence for the `duration`, at least as long as the dwell
+    /// time of the manager. Reported once per stay
+    Dwelling { fence: Id, entity: Id, duration: F },
+    /// The entity moved out of the fence
+    Exited { fence: Id, entity: Id },
+}
+
+/// Time at which an entity entered a fence
+#[derive(Debug)]
+struct Stay<F> {
+    since: F,
+    dwelling: bool,
+}
+
+/// State kept by the [`Geofence`] for every registered fence
+#[derive(Debug)]
+struct Fence<F, Id> {
+    geometry: Geometry<F>,
+    inside: BTreeMap<Id, Stay<F>>,
+}
+
+impl<F: Float, Id: DataIndex> Fence<F, Id> {
+    /// Moves the entity inside or outside the fence at the time `now`, queueing the changes
+    fn track(
+        &mut self,
+        fence: Id,
+        entity: Id,
+        inside: bool,
+        now: F,
+        dwell_time: F,
+        events: &mut VecDeque<GeofenceEvent<Id, F>>,
+    ) {
+        match (self.inside.get_mut(&entity), inside) {
+            (None, true) => {
+                self.inside.insert(
+                    entity,
+                    Stay {
+                        since: now,
+                        dwelling: false,
+                    },
+                );
+                events.push_back(GeofenceEvent::Entered { fence, entity });
+            }
+            (Some(stay), true) => {
+                let duration = now - stay.since;
+                if !stay.dwelling && duration >= dwell_time {
+                    stay.dwelling = true;
+                    events.push_back(GeofenceEvent::Dwelling {
+                        fence,
+                        entity,
+                        duration,
+                    });
+                }
+            }
+            (Some(_), false) => {
+                self.inside.remove(&entity);
+                events.push_back(GeofenceEvent::Exited { fence, entity });
+            }
+            (None, false) => {}
+        }
+    }
+}
+
+/// # Geofence
+///
+/// Tracks the entities moving in and out of a set of fences, as in asset tracking. Fences are
+/// registered as [`Geometry`] with [`Geofence::add_fence`], then the positions of the entities
+/// are fed either all at once from a spatial structure through [`Geofence::update_with`], or
+/// one entity at a time through [`Geofence::update_entity`].
+///
+/// Every update is stamped with the current time, in any unit, and the state transitions of
+/// the entities are queued as [`GeofenceEvent`]s drained with [`Geofence::poll_events`]. An
+/// entity staying inside a fence for the dwell time set with [`Geofence::set_dwell_time`] is
+/// reported as dwelling once per stay.
+///
+/// Fence ids live in their own namespace, separate from the entity ids.
+#[derive(Debug)]
+pub struct Geofence<F, Id> {
+    dwell_time: F,
+    fences: BTreeMap<Id, Fence<F, Id>>,
+    events: VecDeque<GeofenceEvent<Id, F>>,
+}
+
+impl<F, Id> Default for Geofence<F, Id>
+where
+    F: Float,
+    Id: DataIndex,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F, Id> Geofence<F, Id>
+where
+    F: Float,
+    Id: DataIndex,
+{
+    /// Creates the manager without any fence, with a zero dwell time
+    pub fn new() -> Self {
+        Self {
+            dwell_time: F::zero(),
+            fences: BTreeMap::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Sets the time an entity has to stay inside a fence before being reported as dwelling.
+    ///
+    /// With a zero dwell time, entities dwell from the first update following their entry.
+    pub fn set_dwell_time(&mut self, dwell_time: F) {
+        self.dwell_time = dwell_time.max(F::zero());
+    }
+
+    /// Returns the time an entity has to stay inside a fence before being reported as dwelling
+    pub fn dwell_time(&self) -> F {
+        self.dwell_time
+    }
+
+    /// Registers the fence covering the `geometry`.
+    ///
+    /// Adding an already registered fence only replaces its geometry, the entities inside it
+    /// are kept until the next update.
+    pub fn add_fence(&mut self, fence: Id, geometry: Geometry<F>) {
+        self.fences
+            .entry(fence)
+            .and_modify(|f| f.geometry = geometry)
+            .or_insert_with(|| Fence {
+                geometry,
+                inside: BTreeMap::new(),
+            });
+    }
+
+    /// Removes the fence, without reporting the entities inside it as exiting.
+    ///
+    /// Returns `true` if the fence was registered.
+    pub fn remove_fence(&mut self, fence: Id) -> bool {
+        self.fences.remove(&fence).is_some()
+    }
+
+    /// Updates the state of every entity from a spatial structure holding the whole world at
+    /// the time `now`.
+    ///
+    /// Every fence queries the structure, entities missing from the structure are reported as
+    /// exiting the fences they were inside.
+    pub fn update_with<'e, S, T>(&mut self, structure: &S, now: F)
+    where
+        S: SpatialQuery<Item = &'e T, Scalar = F>,
+        T: Coordinate<Item = F> + Entity<ID = Id> + 'e,
+    {
+        // Candidates buffer shared by every query of the update
+        let mut candidates = Vec::new();
+
+        for (&id, fence) in self.fences.iter_mut() {
+            structure.query_into(&fence.geometry, &mut candidates);
+            let inside = candidates.iter().map(|e| e.id()).collect::<BTreeSet<_>>();
+
+            let tracked = fence.inside.keys().copied().collect::<Vec<_>>();
+            for entity in tracked.into_iter().filter(|e| !inside.contains(e)) {
+                fence.track(id, entity, false, now, self.dwell_time, &mut self.events);
+            }
+            for entity in inside {
+                fence.track(id, entity, true, now, self.dwell_time, &mut self.events);
+            }
+        }
+    }
+
+    /// Updates the state of a single entity at the `position`, at the time `now`, testing it
+    /// against every fence
+    pub fn update_entity(&mut self, entity: Id, position: (F, F, F), now: F) {
+        for (&id, fence) in self.fences.iter_mut() {
+            let inside = fence.geometry.contains(position);
+            fence.track(id, entity, inside, now, self.dwell_time, &mut self.events);
+        }
+    }
+
+    /// Removes the entity from every fence it is inside, reporting it as exiting them, such as
+    /// when the entity despawns
+    pub fn remove_entity(&mut self, entity: Id) {
+        for (&id, fence) in self.fences.iter_mut() {
+            if fence.inside.remove(&entity).is_some() {
+                self.events
+                    .push_back(GeofenceEvent::Exited { fence: id, entity });
+            }
+        }
+    }
+
+    /// Returns the entities inside the fence as of the latest update, ordered by id
+    pub fn inside(&self, fence: Id) -> impl Iterator<Item = Id> + '_ {
+        self.fences
+            .get(&fence)
+            .into_iter()
+            .flat_map(|f| f.inside.keys().copied())
+    }
+
+    /// Drains the queued events in the order they were produced.
+    ///
+    /// Events accumulate across updates until they are polled, so systems which do not
+    /// consume them every tick still receive every change.
+    pub fn poll_events(&mut self) -> impl Iterator<Item = GeofenceEvent<Id, F>> + '_ {
+        self.events.drain(..)
+    }
+}
@@ -0,0 +1,1029 @@
+#[cfg(feature = "simd")]
+use alloc::vec::Vec;
+
+use num_traits::Float;
+
+use crate::error::SpatialError;
+#[cfg(feature = "simd")]
+use crate::hashgrid::Coordinate;
+use crate::math::distance;
+
+/// Number of points tested at once by the batched containment tests of the queries, when the
+/// `simd` feature is enabled
+#[cfg(feature = "simd")]
+pub const LANES: usize = 8;
+
+/// # Geometry
+///
+/// Shapes used to express spatial queries independently of the structure being queried.
+/// Every shape is defined in world units, 2D shapes are simply expressed with a zero `z`
+/// component (and a zero height for rectangles).
+///
+/// * `Point:` A single point in space, only matching entities located exactly at the point
+/// * `Rect:` Axis aligned rectangle (2D) or box (3D) defined by its centre and size on each axis
+/// * `Radius:` Circle (2D) or sphere (3D) defined by its centre and radius
+/// * `HSlab:` Horizontal band of every point whose `y` lies between the bounds, whatever its `x` and `z`
+/// * `VSlab:` Vertical band of every point whose `x` lies between the bounds, whatever its `y` and `z`
+/// * `Cone:` Field of view from the `apex` looking along the `direction`, covering the points up to
+///   the `range` away whose angle to the direction is at most the `angle`, in radians
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Geometry<F> {
+    Point((F, F, F)),
+    Rect {
+        centre: (F, F, F),
+        size: (F, F, F),
+    },
+    Radius {
+        centre: (F, F, F),
+        radius: F,
+    },
+    HSlab {
+        y_min: F,
+        y_max: F,
+    },
+    VSlab {
+        x_min: F,
+        x_max: F,
+    },
+    Cone {
+        apex: (F, F, F),
+        direction: (F, F, F),
+        angle: F,
+        range: F,
+    },
+}
+
+impl<F: Float> Geometry<F> {
+    /// Creates a [`Geometry::Point`] at the coordinates
+    pub fn point(coordinates: (F, F, F)) -> Self {
+        Geometry::Point(coordinates)
+    }
+
+    /// Creates a [`Geometry::Rect`] from its centre and size on each axis.
+    ///
+    /// Negative sizes are caught by a debug assertion, see [`Geometry::try_rect`] for sizes
+    /// which are not known to be valid.
+    pub fn rect(centre: (F, F, F), size: (F, F, F)) -> Self {
+        let non_negative = |v: F| v.is_nan() || v >= F::zero();
+        debug_assert!(
+            non_negative(size.0) && non_negative(size.1) && non_negative(size.2),
+            "rect size must not be negative"
+        );
+
+        Geometry::Rect { centre, size }
+    }
+
+    /// Same as [`Geometry::rect`], except that a non-finite component or a negative size is
+    /// reported as a [`SpatialError::InvalidQuery`]
+    pub fn try_rect(centre: (F, F, F), size: (F, F, F)) -> Result<Self, SpatialError> {
+        let rect = Geometry::Rect { centre, size };
+        rect.is_valid()
+            .then_some(rect)
+            .ok_or(SpatialError::InvalidQuery)
+    }
+
+    /// Creates a [`Geometry::Rect`] spanning from the `min` to the `max` corner
+    pub fn rect_from_corners(min: (F, F, F), max: (F, F, F)) -> Self {
+        let two = F::one() + F::one();
+
+        Geometry::Rect {
+            centre: (
+                (min.0 + max.0) / two,
+                (min.1 + max.1) / two,
+                (min.2 + max.2) / two,
+            ),
+            size: (max.0 - min.0, max.1 - min.1, max.2 - min.2),
+        }
+    }
+
+    /// Creates a [`Geometry::Radius`] from its centre and radius.
+    ///
+    /// A negative radius is caught by a debug assertion, see [`Geometry::try_radius`] for
+    /// radii which are not known to be valid.
+    pub fn radius(centre: (F, F, F), radius: F) -> Self {
+        debug_assert!(
+            radius.is_nan() || radius >= F::zero(),
+            "radius must not be negative"
+        );
+
+        Geometry::Radius { centre, radius }
+    }
+
+    /// Same as [`Geometry::radius`], except that a non-finite component or a negative radius
+    /// is reported as a [`SpatialError::InvalidQuery`]
+    pub fn try_radius(centre: (F, F, F), radius: F) -> Result<Self, SpatialError> {
+        let sphere = Geometry::Radius { centre, radius };
+        sphere
+            .is_valid()
+            .then_some(sphere)
+            .ok_or(SpatialError::InvalidQuery)
+    }
+
+    /// Creates a [`Geometry::HSlab`] spanning the `y` coordinates from `y_min` to `y_max`
+    pub fn hslab(y_min: F, y_max: F) -> Self {
+        Geometry::HSlab { y_min, y_max }
+    }
+
+    /// Creates a [`Geometry::VSlab`] spanning the `x` coordinates from `x_min` to `x_max`
+    pub fn vslab(x_min: F, x_max: F) -> Self {
+        Geometry::VSlab { x_min, x_max }
+    }
+
+    /// Creates a [`Geometry::Cone`] from its apex, the direction it looks along, its half angle
+    /// in radians and its range. The direction does not have to be normalized.
+    ///
+    /// A negative angle or range is caught by a debug assertion, see [`Geometry::try_cone`] for
+    /// cones which are not known to be valid.
+    pub fn cone(apex: (F, F, F), direction: (F, F, F), angle: F, range: F) -> Self {
+        let non_negative = |v: F| v.is_nan() || v >= F::zero();
+        debug_assert!(
+            non_negative(angle) && non_negative(range),
+            "cone angle and range must not be negative"
+        );
+
+        Geometry::Cone {
+            apex,
+            direction,
+            angle,
+            range,
+        }
+    }
+
+    /// Same as [`Geometry::cone`], except that a non-finite component, a zero direction, an
+    /// angle outside `[0, π]` or a negative range is reported as a
+    /// [`SpatialError::InvalidQuery`]
+    pub fn try_cone(
+        apex: (F, F, F),
+        direction: (F, F, F),
+        angle: F,
+        range: F,
+    ) -> Result<Self, SpatialError> {
+        let cone = Geometry::Cone {
+            apex,
+            direction,
+            angle,
+            range,
+        };
+        cone.is_valid()
+            .then_some(cone)
+            .ok_or(SpatialError::InvalidQuery)
+    }
+
+    /// Centre of the shape, slabs being centred on the origin along their unbounded axes and
+    /// cones on their apex
+    pub fn centre(&self) -> (F, F, F) {
+        let two = F::one() + F::one();
+
+        match *self {
+            Geometry::Point(point) => point,
+            Geometry::Rect { centre, .. } => centre,
+            Geometry::Radius { centre, .. } => centre,
+            Geometry::HSlab { y_min, y_max } => (F::zero(), (y_min + y_max) / two, F::zero()),
+            Geometry::VSlab { x_min, x_max } => ((x_min + x_max) / two, F::zero(), F::zero()),
+            Geometry::Cone { apex, .. } => apex,
+        }
+    }
+
+    /// Same shape moved by the `offset`, slabs only moving across their bounded axis
+    pub(crate) fn translated(&self, offset: (F, F, F)) -> Self {
+        let shift = |p: (F, F, F)| (p.0 + offset.0, p.1 + offset.1, p.2 + offset.2);
+
+        match *self {
+            Geometry::Point(point) => Geometry::Point(shift(point)),
+            Geometry::Rect { centre, size } => Geometry::Rect {
+                centre: shift(centre),
+                size,
+            },
+            Geometry::Radius { centre, radius } => Geometry::Radius {
+                centre: shift(centre),
+                radius,
+            },
+            Geometry::HSlab { y_min, y_max } => Geometry::HSlab {
+                y_min: y_min + offset.1,
+                y_max: y_max + offset.1,
+            },
+            Geometry::VSlab { x_min, x_max } => Geometry::VSlab {
+                x_min: x_min + offset.0,
+                x_max: x_max + offset.0,
+            },
+            Geometry::Cone {
+                apex,
+                direction,
+                angle,
+                range,
+            } => Geometry::Cone {
+                apex: shift(apex),
+                direction,
+                angle,
+                range,
+            },
+        }
+    }
+
+    /// Returns the `min` and `max` corners of the axis aligned box enclosing the shape.
+    ///
+    /// The box of a slab is infinite along its unbounded axes.
+    pub fn bounding_box(&self) -> ((F, F, F), (F, F, F)) {
+        let two = F::one() + F::one();
+        let (inf, neg_inf) = (F::infinity(), F::neg_infinity());
+
+        match *self {
+            Geometry::Point(point) => (point, point),
+            Geometry::Rect { centre, size } => {
+                let half = (size.0 / two, size.1 / two, size.2 / two);
+                (
+                    (centre.0 - half.0, centre.1 - half.1, centre.2 - half.2),
+                    (centre.0 + half.0, centre.1 + half.1, centre.2 + half.2),
+                )
+            }
+            Geometry::Radius { centre, radius } => (
+                (centre.0 - radius, centre.1 - radius, centre.2 - radius),
+                (centre.0 + radius, centre.1 + radius, centre.2 + radius),
+            ),
+            Geometry::HSlab { y_min, y_max } => ((neg_inf, y_min, neg_inf), (inf, y_max, inf)),
+            Geometry::VSlab { x_min, x_max } => ((x_min, neg_inf, neg_inf), (x_max, inf, inf)),
+            Geometry::Cone {
+                apex,
+                direction,
+                angle,
+                range,
+            } => {
+                let (axis, cos) = cone_axis(direction, angle);
+                let sin = (F::one() - cos * cos).max(F::zero()).sqrt();
+                let (apex, axis) = (axes(apex), axes(axis));
+
+                let (mut min, mut max) = (apex, apex);
+                for i in 0..3 {
+                    // Directions along the axis within the cone reach the full range, the
+                    // others are bounded by the rim of the cone, or by the apex
+                    let rim = |sign: F| {
+                        let rim = sign * range * cos * axis[i]
+                            + range * sin * (F::one() - axis[i] * axis[i]).max(F::zero()).sqrt();
+                        match sign * axis[i] >= cos {
+                            true => range,
+                            false => rim.max(F::zero()),
+                        }
+                    };
+
+                    max[i] = apex[i] + rim(F::one());
+                    min[i] = apex[i] - rim(-F::one());
+                }
+
+                ((min[0], min[1], min[2]), (max[0], max[1], max[2]))
+            }
+        }
+    }
+
+    /// Tests whether every component of the shape is finite and its extent is not negative
+    pub fn is_valid(&self) -> bool {
+        let finite = |p: (F, F, F)| p.0.is_finite() && p.1.is_finite() && p.2.is_finite();
+
+        match *self {
+            Geometry::Point(point) => finite(point),
+            Geometry::Rect { centre, size } => {
+                finite(centre)
+                    && finite(size)
+                    && size.0 >= F::zero()
+                    && size.1 >= F::zero()
+                    && size.2 >= F::zero()
+            }
+            Geometry::Radius { centre, radius } => {
+                finite(centre) && radius.is_finite() && radius >= F::zero()
+            }
+            Geometry::HSlab {
+                y_min: min,
+                y_max: max,
+            }
+            | Geometry::VSlab {
+                x_min: min,
+                x_max: max,
+            } => min.is_finite() && max.is_finite() && min <= max,
+            Geometry::Cone {
+                apex,
+                direction,
+                angle,
+                range,
+            } => {
+                let pi = F::from(core::f64::consts::PI).unwrap();
+
+                finite(apex)
+                    && finite(direction)
+                    && direction != (F::zero(), F::zero(), F::zero())
+                    && (F::zero()..=pi).contains(&angle)
+                    && range.is_finite()
+                    && range >= F::zero()
+            }
+        }
+    }
+
+    /// Tests whether the point lies inside the shape, boundaries included
+    pub fn contains(&self, point: (F, F, F)) -> bool {
+        match *self {
+            Geometry::Point(p) => p == point,
+            Geometry::Rect { .. } | Geometry::HSlab { .. } | Geometry::VSlab { .. } => {
+                let (min, max) = self.bounding_box();
+
+                (min.0..=max.0).contains(&point.0)
+                    && (min.1..=max.1).contains(&point.1)
+                    && (min.2..=max.2).contains(&point.2)
+            }
+            Geometry::Radius { centre, radius } => {
+                let dx = point.0 - centre.0;
+                let dy = point.1 - centre.1;
+                let dz = point.2 - centre.2;
+
+                dx * dx + dy * dy + dz * dz <= radius * radius
+            }
+            Geometry::Cone {
+                apex,
+                direction,
+                angle,
+                range,
+            } => {
+                let (axis, cos) = cone_axis(direction, angle);
+                let v = (point.0 - apex.0, point.1 - apex.1, point.2 - apex.2);
+                let length = dot(v, v).sqrt();
+
+                length <= range && dot(v, axis) >= length * cos
+            }
+        }
+    }
+
+    /// Tests whether the shape and the `other` shape overlap, boundaries included.
+    ///
+    /// Cones are tested exactly against points, circles, spheres and flat shapes. A box is
+    /// tested against the apex, axis, corners and edges of the cone, missing only the cones
+    /// which cross the inside of its faces without reaching any of those, while two cones are
+    /// compared by their bounding boxes.
+    pub fn intersects(&self, other: &Geometry<F>) -> bool {
+        match (*self, *other) {
+            (Geometry::Point(point), shape) | (shape, Geometry::Point(point)) => {
+                shape.contains(point)
+            }
+            (cone @ Geometry::Cone { .. }, Geometry::Radius { centre, radius })
+            | (Geometry::Radius { centre, radius }, cone @ Geometry::Cone { .. }) => {
+                cone_distance(&cone, centre) <= radius
+            }
+            (Geometry::Cone { .. }, Geometry::Cone { .. }) => {
+                let (a_min, a_max) = self.bounding_box();
+                let (b_min, b_max) = other.bounding_box();
+
+                Geometry::rect_from_corners(a_min, a_max)
+                    .intersects(&Geometry::rect_from_corners(b_min, b_max))
+            }
+            (cone @ Geometry::Cone { .. }, shape) | (shape, cone @ Geometry::Cone { .. }) => {
+                cone_box_intersects(&cone, &shape)
+            }
+            (
+                Geometry::Radius { centre, radius },
+                Geometry::Radius {
+                    centre: other_centre,
+                    radius: other_radius,
+                },
+            ) => Geometry::Radius {
+                centre,
+                radius: radius + other_radius,
+            }
+            .contains(other_centre),
+            (Geometry::Radius { centre, radius }, shape)
+            | (shape, Geometry::Radius { centre, radius }) => {
+                // Closest point of the box shaped geometry to the centre of the sphere
+                let (min, max) = shape.bounding_box();
+                let closest = (
+                    centre.0.max(min.0).min(max.0),
+                    centre.1.max(min.1).min(max.1),
+                    centre.2.max(min.2).min(max.2),
+                );
+
+                Geometry::Radius { centre, radius }.contains(closest)
+            }
+            // Every other shape is an axis aligned box, possibly unbounded
+            _ => {
+                let (a_min, a_max) = self.bounding_box();
+                let (b_min, b_max) = other.bounding_box();
+
+                a_min.0 <= b_max.0
+                    && b_min.0 <= a_max.0
+                    && a_min.1 <= b_max.1
+                    && b_min.1 <= a_max.1
+                    && a_min.2 <= b_max.2
+                    && b_min.2 <= a_max.2
+            }
+        }
+    }
+
+    /// Tests whether the segment from `a` to `b` touches the shape, boundaries included
+    pub fn intersects_segment(&self, a: (F, F, F), b: (F, F, F)) -> bool {
+        let d = (b.0 - a.0, b.1 - a.1, b.2 - a.2);
+
+        match *self {
+            Geometry::Point(centre) => Geometry::Radius {
+                centre,
+                radius: F::zero(),
+            }
+            .intersects_segment(a, b),
+            Geometry::Cone { .. } => cone_segment_intersects(self, a, b),
+            Geometry::Radius { centre, .. } => {
+                // Closest point of the segment to the centre of the sphere
+                let length_sq = d.0 * d.0 + d.1 * d.1 + d.2 * d.2;
+                let t = match length_sq > F::zero() {
+                    true => {
+                        (((centre.0 - a.0) * d.0 + (centre.1 - a.1) * d.1 + (centre.2 - a.2) * d.2)
+                            / length_sq)
+                            .max(F::zero())
+                            .min(F::one())
+                    }
+                    false => F::zero(),
+                };
+
+                self.contains((a.0 + t * d.0, a.1 + t * d.1, a.2 + t * d.2))
+            }
+            // Clipping the segment against the slabs of the box along each axis
+            _ => {
+                let (min, max) = self.bounding_box();
+                let (a, d, min, max) = (axes(a), axes(d), axes(min), axes(max));
+
+                let (mut t_min, mut t_max) = (F::zero(), F::one());
+                for axis in 0..3 {
+                    if d[axis] == F::zero() {
+                        if a[axis] < min[axis] || a[axis] > max[axis] {
+                            return false;
+                        }
+                        continue;
+                    }
+
+                    let t0 = (min[axis] - a[axis]) / d[axis];
+                    let t1 = (max[axis] - a[axis]) / d[axis];
+                    t_min = t_min.max(t0.min(t1));
+                    t_max = t_max.min(t0.max(t1));
+
+                    if t_min > t_max {
+                        return false;
+                    }
+                }
+
+                true
+            }
+        }
+    }
+
+    /// Measures how deep the shape penetrates the `other` shape, for pushing overlapping shapes
+    /// apart in simple physics.
+    ///
+    /// Returns the unit normal along which the shape has to move out of the `other` one, along
+    /// with the depth to move it by, or `None` if the shapes do not overlap. Touching shapes
+    /// penetrate by a zero depth. Only rects and radii are supported, and axes along which a
+    /// rect is flat are ignored, so that 2D shapes are pushed out within their plane.
+    pub fn penetration(&self, other: &Geometry<F>) -> Option<((F, F, F), F)> {
+        if !(self.is_valid() && other.is_valid()) {
+            return None;
+        }
+
+        match (*self, *other) {
+            (
+                Geometry::Rect { centre, size },
+                Geometry::Rect {
+                    centre: other_centre,
+                    size: other_size,
+                },
+            ) => {
+                let two = F::one() + F::one();
+                let offset = axes((
+                    centre.0 - other_centre.0,
+                    centre.1 - other_centre.1,
+                    centre.2 - other_centre.2,
+                ));
+                let (size, other_size) = (axes(size), axes(other_size));
+
+                // Separating along the axis of the shallowest overlap
+                let mut shallowest: Option<(usize, F)> = None;
+                for axis in 0..3 {
+                    let reach = (size[axis] + other_size[axis]) / two;
+                    let depth = reach - offset[axis].abs();
+
+                    if depth < F::zero() {
+                        return None;
+                    }
+                    if reach > F::zero() && shallowest.is_none_or(|(_, d)| depth < d) {
+                        shallowest = Some((axis, depth));
+                    }
+                }
+
+                let (axis, depth) = shallowest?;
+                Some((axis_normal(axis, offset[axis] >= F::zero()), depth))
+            }
+            (
+                Geometry::Radius { centre, radius },
+                Geometry::Radius {
+                    centre: other_centre,
+                    radius: other_radius,
+                },
+            ) => {
+                let gap = distance(centre, other_centre);
+                let depth = radius + other_radius - gap;
+
+                if depth < F::zero() {
+                    return None;
+                }
+
+                // Concentric spheres are pushed apart along the x-axis
+                let normal = match gap > F::zero() {
+                    true => (
+                        (centre.0 - other_centre.0) / gap,
+                        (centre.1 - other_centre.1) / gap,
+                        (centre.2 - other_centre.2) / gap,
+                    ),
+                    false => axis_normal(0, true),
+                };
+
+                Some((normal, depth))
+            }
+            (Geometry::Radius { centre, radius }, rect @ Geometry::Rect { .. }) => {
+                sphere_box_penetration(centre, radius, &rect)
+            }
+            (rect @ Geometry::Rect { .. }, Geometry::Radius { centre, radius }) => {
+                sphere_box_penetration(centre, radius, &rect)
+                    .map(|(normal, depth)| ((-normal.0, -normal.1, -normal.2), depth))
+            }
+            _ => None,
+        }
+    }
+
+    /// Tests a batch of `N` points against the shape at once, boundaries included.
+    ///
+    /// The points are split into one array per axis and every lane is evaluated without
+    /// branching, which lets the compiler vectorize the test over the whole batch.
+    pub fn contains_lanes<const N: usize>(&self, points: [(F, F, F); N]) -> [bool; N] {
+        let xs = points.map(|p| p.0);
+        let ys = points.map(|p| p.1);
+        let zs = points.map(|p| p.2);
+
+        let mut inside = [false; N];
+        match *self {
+            Geometry::Point(p) => {
+                for (i, inside) in inside.iter_mut().enumerate() {
+                    *inside = (xs[i] == p.0) & (ys[i] == p.1) & (zs[i] == p.2);
+                }
+            }
+            Geometry::Rect { .. } | Geometry::HSlab { .. } | Geometry::VSlab { .. } => {
+                let (min, max) = self.bounding_box();
+
+                for (i, inside) in inside.iter_mut().enumerate() {
+                    *inside = (xs[i] >= min.0)
+                        & (xs[i] <= max.0)
+                        & (ys[i] >= min.1)
+                        & (ys[i] <= max.1)
+                        & (zs[i] >= min.2)
+                        & (zs[i] <= max.2);
+                }
+            }
+            Geometry::Radius { centre, radius } => {
+                let radius_sq = radius * radius;
+
+                for (i, inside) in inside.iter_mut().enumerate() {
+                    let dx = xs[i] - centre.0;
+                    let dy = ys[i] - centre.1;
+                    let dz = zs[i] - centre.2;
+
+                    *inside = dx * dx + dy * dy + dz * dz <= radius_sq;
+                }
+            }
+            Geometry::Cone {
+                apex,
+                direction,
+                angle,
+                range,
+            } => {
+                let (axis, cos) = cone_axis(direction, angle);
+                let range_sq = range * range;
+
+                for (i, inside) in inside.iter_mut().enumerate() {
+                    let dx = xs[i] - apex.0;
+                    let dy = ys[i] - apex.1;
+                    let dz = zs[i] - apex.2;
+                    let length_sq = dx * dx + dy * dy + dz * dz;
+                    let along = dx * axis.0 + dy * axis.1 + dz * axis.2;
+
+                    *inside = (length_sq <= range_sq) & (along >= length_sq.sqrt() * cos);
+                }
+            }
+        }
+
+        inside
+    }
+}
+
+/// Unit vector of the `direction` of a cone, along with the cosine of its `angle`
+fn cone_axis<F: Float>(direction: (F, F, F), angle: F) -> ((F, F, F), F) {
+    let length = dot(direction, direction).sqrt();
+    let axis = (
+        direction.0 / length,
+        direction.1 / length,
+        direction.2 / length,
+    );
+
+    (axis, angle.cos())
+}
+
+fn dot<F: Float>(a: (F, F, F), b: (F, F, F)) -> F {
+    a.0 * b.0 + a.1 * b.1 + a.2 * b.2
+}
+
+/// Distance from the `point` to the closest point of the `cone`, zero inside it
+fn cone_distance<F: Float>(cone: &Geometry<F>, point: (F, F, F)) -> F {
+    let Geometry::Cone {
+        apex,
+        direction,
+        angle,
+        range,
+    } = *cone
+    else {
+        unreachable!("the geometry is a cone");
+    };
+
+    if cone.contains(point) {
+        return F::zero();
+    }
+
+    // In the plane of the axis and the point, the cone is a circular sector
+    let (axis, cos) = cone_axis(direction, angle);
+    let v = (point.0 - apex.0, point.1 - apex.1, point.2 - apex.2);
+    let length = dot(v, v).sqrt();
+    let along = dot(v, axis);
+    let across = (length * length - along * along).max(F::zero()).sqrt();
+
+    match along >= length * cos {
+        // Within the angle, the point lies beyond the range
+        true => length - range,
+        // Otherwise the closest point lies on the edge of the sector
+        false => {
+            let sin = (F::one() - cos * cos).max(F::zero()).sqrt();
+            let t = (along * cos + across * sin).max(F::zero()).min(range);
+            let (dx, dy) = (along - t * cos, across - t * sin);
+
+            (dx * dx + dy * dy).sqrt()
+        }
+    }
+}
+
+/// Tests whether the segment from `a` to `b` touches the `cone`, see
+/// [`Geometry::intersects_segment`]
+fn cone_segment_intersects<F: Float>(cone: &Geometry<F>, a: (F, F, F), b: (F, F, F)) -> bool {
+    let Geometry::Cone {
+        apex,
+        direction,
+        angle,
+        range,
+    } = *cone
+    else {
+        unreachable!("the geometry is a cone");
+    };
+
+    if cone.contains(a) || cone.contains(b) {
+        return true;
+    }
+
+    // With both ends outside, the segment has to cross the spherical cap or the side of the
+    // cone to touch it
+    let (axis, cos) = cone_axis(direction, angle);
+    let p = (a.0 - apex.0, a.1 - apex.1, a.2 - apex.2);
+    let w = (b.0 - a.0, b.1 - a.1, b.2 - a.2);
+    let at = |t: F| (p.0 + t * w.0, p.1 + t * w.1, p.2 + t * w.2);
+    let two = F::one() + F::one();
+
+    let on_cap = unit_roots(dot(w, w), two * dot(p, w), dot(p, p) - range * range)
+        .into_iter()
+        .flatten()
+        .any(|t| dot(at(t), axis) >= range * cos);
+
+    let (pd, wd, cos_sq) = (dot(p, axis), dot(w, axis), cos * cos);
+    let on_side = unit_roots(
+        wd * wd - cos_sq * dot(w, w),
+        two * (pd * wd - cos_sq * dot(p, w)),
+        pd * pd - cos_sq * dot(p, p),
+    )
+    .into_iter()
+    .flatten()
+    .any(|t| {
+        let v = at(t);
+        // The squared equation also holds on the mirrored cone behind the apex
+        dot(v, axis) * cos >= F::zero() && dot(v, v) <= range * range
+    });
+
+    on_cap || on_side
+}
+
+/// Roots within `[0, 1]` of the polynomial `a t² + b t + c`
+fn unit_roots<F: Float>(a: F, b: F, c: F) -> [Option<F>; 2] {
+    let unit = |t: F| (F::zero()..=F::one()).contains(&t).then_some(t);
+
+    if a == F::zero() {
+        return match b == F::zero() {
+            true => [None, None],
+            false => [unit(-c / b), None],
+        };
+    }
+
+    let discriminant = b * b - (F::one() + F::one() + F::one() + F::one()) * a * c;
+    if discriminant < F::zero() {
+        return [None, None];
+    }
+
+    let (root, two_a) = (discriminant.sqrt(), (F::one() + F::one()) * a);
+    [unit((-b - root) / two_a), unit((-b + root) / two_a)]
+}
+
+/// Tests whether the box shaped geometry touches the `cone`, see [`Geometry::intersects`]
+fn cone_box_intersects<F: Float>(cone: &Geometry<F>, shape: &Geometry<F>) -> bool {
+    let Geometry::Cone {
+        apex,
+        direction,
+        range,
+        ..
+    } = *cone
+    else {
+        unreachable!("the geometry is a cone");
+    };
+
+    // Unbounded shapes are clipped to the bounding box of the cone first
+    let (c_min, c_max) = cone.bounding_box();
+    let (s_min, s_max) = shape.bounding_box();
+    let (min, max) = (
+        (
+            s_min.0.max(c_min.0),
+            s_min.1.max(c_min.1),
+            s_min.2.max(c_min.2),
+        ),
+        (
+            s_max.0.min(c_max.0),
+            s_max.1.min(c_max.1),
+            s_max.2.min(c_max.2),
+        ),
+    );
+
+    if min.0 > max.0 || min.1 > max.1 || min.2 > max.2 {
+        return false;
+    }
+
+    let clipped = Geometry::rect_from_corners(min, max);
+    if clipped.contains(apex) {
+        return true;
+    }
+
+    let (axis, _) = cone_axis(direction, F::zero());
+    let tip = (
+        apex.0 + axis.0 * range,
+        apex.1 + axis.1 * range,
+        apex.2 + axis.2 * range,
+    );
+    if clipped.intersects_segment(apex, tip) {
+        return true;
+    }
+
+    let corner = |i: usize| {
+        (
+            if i & 1 == 0 { min.0 } else { max.0 },
+            if i & 2 == 0 { min.1 } else { max.1 },
+            if i & 4 == 0 { min.2 } else { max.2 },
+        )
+    };
+
+    // Edges join the corners differing along a single axis
+    (0..8).any(|i| cone.contains(corner(i)))
+        || (0..8).any(|i| {
+            [1, 2, 4]
+                .into_iter()
+                .filter(|bit| i & bit == 0)
+                .any(|bit| cone_segment_intersects(cone, corner(i), corner(i | bit)))
+        })
+}
+
+/// Components of the coordinates as an array, to loop over the axes
+fn axes<F: Float>(p: (F, F, F)) -> [F; 3] {
+    [p.0, p.1, p.2]
+}
+
+/// Unit vector along the `axis`, pointing towards the positive side if `positive` is set
+fn axis_normal<F: Float>(axis: usize, positive: bool) -> (F, F, F) {
+    let mut normal = [F::zero(); 3];
+    normal[axis] = match positive {
+        true => F::one(),
+        false => -F::one(),
+    };
+
+    (normal[0], normal[1], normal[2])
+}
+
+/// Penetration of the sphere into the `rect`, see [`Geometry::penetration`]
+fn sphere_box_penetration<F: Float>(
+    centre: (F, F, F),
+    radius: F,
+    rect: &Geometry<F>,
+) -> Option<((F, F, F), F)> {
+    let (min, max) = rect.bounding_box();
+    let closest = (
+        centre.0.max(min.0).min(max.0),
+        centre.1.max(min.1).min(max.1),
+        centre.2.max(min.2).min(max.2),
+    );
+
+    let gap = distance(centre, closest);
+    if gap > F::zero() {
+        let depth = radius - gap;
+
+        return (depth >= F::zero()).then(|| {
+            let normal = (
+                (centre.0 - closest.0) / gap,
+                (centre.1 - closest.1) / gap,
+                (centre.2 - closest.2) / gap,
+            );
+            (normal, depth)
+        });
+    }
+
+    // The centre lies inside the rect, leaving through the closest face
+    let (centre, min, max) = (axes(centre), axes(min), axes(max));
+
+    let mut closest_face: Option<(usize, F, bool)> = None;
+    for axis in (0..3).filter(|&axis| max[axis] > min[axis]) {
+        let (to_min, to_max) = (centre[axis] - min[axis], max[axis] - centre[axis]);
+        let (exit, positive) = match to_max <= to_min {
+            true => (to_max, true),
+            false => (to_min, false),
+        };
+
+        if closest_face.is_none_or(|(_, e, _)| exit < e) {
+            closest_face = Some((axis, exit, positive));
+        }
+    }
+
+    let (axis, exit, positive) = closest_face.unwrap_or((0, F::zero(), true));
+    Some((axis_normal(axis, positive), radius + exit))
+}
+
+#[cfg(feature = "rand")]
+impl<F: Float> Geometry<F> {
+    /// Samples a point uniformly distributed inside the shape, such as a spawn position within
+    /// the same area used to query the surroundings.
+    ///
+    /// Circles are sampled as spheres, see [`Geometry::sample_point_planar`] for 2D worlds.
+    /// Returns `None` for the slabs, which are unbounded, and for invalid shapes.
+    pub fn sample_point<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Option<(F, F, F)> {
+        self.sample(rng, true)
+    }
+
+    /// Same as [`Geometry::sample_point`], except that the samples are taken in the `x`, `y`
+    /// plane of the centre of the shape, circles being sampled as discs and cones as the sector
+    /// of their angle around their direction projected onto the plane
+    pub fn sample_point_planar<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Option<(F, F, F)> {
+        self.sample(rng, false)
+    }
+
+    /// Samples the shape, along the z-axis as well if `spatial` is set
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R, spatial: bool) -> Option<(F, F, F)> {
+        if !self.is_valid() {
+            return None;
+        }
+
+        let two = F::one() + F::one();
+
+        // Uniform offset in the range [-1, 1)
+        let mut offset = || two * F::from(rng.gen::<f64>()).unwrap() - F::one();
+
+        match *self {
+            Geometry::Point(point) => Some(point),
+            Geometry::Rect { centre, size } => {
+                let x = centre.0 + offset() * size.0 / two;
+                let y = centre.1 + offset() * size.1 / two;
+                let z = match spatial {
+                    true => centre.2 + offset() * size.2 / two,
+                    false => centre.2,
+                };
+
+                Some((x, y, z))
+            }
+            Geometry::Radius { centre, radius } => loop {
+                // Rejection sampling within the enclosing cube, or square
+                let (dx, dy) = (offset(), offset());
+                let dz = match spatial {
+                    true => offset(),
+                    false => F::zero(),
+                };
+
+                if dx * dx + dy * dy + dz * dz <= F::one() {
+                    break Some((
+                        centre.0 + dx * radius,
+                        centre.1 + dy * radius,
+                        centre.2 + dz * radius,
+                    ));
+                }
+            },
+            Geometry::Cone {
+                apex,
+                direction,
+                angle,
+                range,
+            } => {
+                let (axis, cos) = cone_axis(direction, angle);
+                let mut unit = || F::from(rng.gen::<f64>()).unwrap();
+
+                // Distance from the apex and direction from it, uniform over the volume of
+                // the cone, or over the area of the sector within the plane
+                let (length, heading) = match spatial {
+                    true => {
+                        let along = F::one() - unit() * (F::one() - cos);
+                        let across = (F::one() - along * along).max(F::zero()).sqrt();
+                        let around = two * F::from(core::f64::consts::PI).unwrap() * unit();
+                        let (u, v) = perpendiculars(axis);
+                        let (cos_around, sin_around) =
+                            (around.cos() * across, around.sin() * across);
+
+                        let heading = (
+                            along * axis.0 + cos_around * u.0 + sin_around * v.0,
+                            along * axis.1 + cos_around * u.1 + sin_around * v.1,
+                            along * axis.2 + cos_around * u.2 + sin_around * v.2,
+                        );
+                        (range * unit().cbrt(), heading)
+                    }
+                    // Cones pointing straight up or down only cross the plane at their apex
+                    false if axis.0 == F::zero() && axis.1 == F::zero() => return Some(apex),
+                    false => {
+                        let around = axis.1.atan2(axis.0) + angle * (two * unit() - F::one());
+                        (
+                            range * unit().sqrt(),
+                            (around.cos(), around.sin(), F::zero()),
+                        )
+                    }
+                };
+
+                Some((
+                    apex.0 + length * heading.0,
+                    apex.1 + length * heading.1,
+                    apex.2 + length * heading.2,
+                ))
+            }
+            Geometry::HSlab { .. } | Geometry::VSlab { .. } => None,
+        }
+    }
+}
+
+/// Two unit vectors perpendicular to the unit `axis` and to each other
+#[cfg(feature = "rand")]
+fn perpendiculars<F: Float>(axis: (F, F, F)) -> ((F, F, F), (F, F, F)) {
+    // Crossing with the world axis least aligned with the axis
+    let helper = match axis.0.abs() <= axis.1.abs() && axis.0.abs() <= axis.2.abs() {
+        true => (F::one(), F::zero(), F::zero()),
+        false if axis.1.abs() <= axis.2.abs() => (F::zero(), F::one(), F::zero()),
+        false => (F::zero(), F::zero(), F::one()),
+    };
+
+    let cross = |a: (F, F, F), b: (F, F, F)| {
+        (
+            a.1 * b.2 - a.2 * b.1,
+            a.2 * b.0 - a.0 * b.2,
+            a.0 * b.1 - a.1 * b.0,
+        )
+    };
+
+    let u = cross(axis, helper);
+    let length = dot(u, u).sqrt();
+    let u = (u.0 / length, u.1 / length, u.2 / length);
+
+    (u, cross(axis, u))
+}
+
+/// Appends the candidates located inside the geometry to the results, testing [`LANES`]
+/// candidates at a time and the remainder one by one
+#[cfg(feature = "simd")]
+pub(crate) fn extend_contained<'a, F, T>(
+    geometry: &Geometry<F>,
+    candidates: &[&'a T],
+    results: &mut Vec<&'a T>,
+) where
+    F: Float,
+    T: Coordinate<Item = F>,
+{
+    let mut chunks = candidates.chunks_exact(LANES);
+    for chunk in &mut chunks {
+        let points: [(F, F, F); LANES] =
+            core::array::from_fn(|i| (chunk[i].x(), chunk[i].y(), chunk[i].z()));
+
+        let inside = geometry.contains_lanes(points);
+        results.extend(
+            chunk
+                .iter()
+                .zip(inside)
+                .filter(|(_, inside)| *inside)
+                .map(|(e, _)| *e),
+        );
+    }
+
+    results.extend(
+        chunks
+            .remainder()
+            .iter()
+            .filter(|e| geometry.contains((e.x(), e.y(), e.z())))
+            .copied(),
+    );
+}
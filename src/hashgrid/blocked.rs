@@ -0,0 +1,192 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+use core::mem::size_of;
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+
+use super::{Boundary, CellId, HashGrid};
+
+/// Static blockage of the cells of a floor, one bit per cell within the grid bounds.
+///
+/// The bits are only allocated once a cell of the floor is marked.
+#[derive(Debug, Clone, Default)]
+pub(super) struct BlockMask {
+    /// Range of the signed cell coordinates covered by the bits, both ends included
+    range: [[i64; 2]; 2],
+    bits: Vec<u64>,
+}
+
+impl BlockMask {
+    /// Bit of the `cell`, or `None` if the cell lies outside the mask
+    fn bit(&self, cell: [i64; 2]) -> Option<usize> {
+        let [lo, hi] = self.range;
+        if self.bits.is_empty()
+            || !(lo[0]..=hi[0]).contains(&cell[0])
+            || !(lo[1]..=hi[1]).contains(&cell[1])
+        {
+            return None;
+        }
+
+        let width = (hi[0] - lo[0] + 1) as usize;
+        Some((cell[1] - lo[1]) as usize * width + (cell[0] - lo[0]) as usize)
+    }
+
+    fn get(&self, cell: [i64; 2]) -> bool {
+        self.bit(cell)
+            .is_some_and(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Sets the bit of the `cell`, the mask covering the `range` if it was not allocated yet
+    fn set(&mut self, range: [[i64; 2]; 2], cell: [i64; 2], blocked: bool) {
+        if self.bits.is_empty() {
+            if !blocked {
+                return;
+            }
+
+            let [lo, hi] = range;
+            let area = ((hi[0] - lo[0] + 1) * (hi[1] - lo[1] + 1)) as usize;
+            *self = Self {
+                range,
+                bits: vec![0; area.div_ceil(64)],
+            };
+        }
+
+        if let Some(bit) = self.bit(cell) {
+            match blocked {
+                true => self.bits[bit / 64] |= 1 << (bit % 64),
+                false => self.bits[bit / 64] &= !(1 << (bit % 64)),
+            }
+        }
+    }
+
+    /// Cells whose bit is set
+    fn cells(&self) -> impl Iterator<Item = [i64; 2]> + '_ {
+        let [lo, hi] = self.range;
+        (lo[1]..=hi[1])
+            .flat_map(move |y| (lo[0]..=hi[0]).map(move |x| [x, y]))
+            .filter(|&cell| self.get(cell))
+    }
+
+    /// Bytes reserved by the bits
+    pub(super) fn bytes(&self) -> usize {
+        self.bits.capacity() * size_of::<u64>()
+    }
+}
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Marks the `cells` as blocked by static terrain, independently of the entities, such as
+    /// walls and water.
+    ///
+    /// Blocked cells cannot be crossed by [`HashGrid::pathfind`] and [`HashGrid::flow_field`],
+    /// and do not give positions to [`HashGrid::find_free_positions`]. The blockage stays in
+    /// place when the resolution of a floor changes, every cell overlapping a blocked area
+    /// being blocked in turn. Cells outside the grid bounds are ignored.
+    pub fn mark_blocked<I>(&mut self, cells: I)
+    where
+        I: IntoIterator<Item = CellId>,
+    {
+        self.set_blocked(cells, true);
+    }
+
+    /// Clears the blockage of the `cells`, see [`HashGrid::mark_blocked`]
+    pub fn unmark_blocked<I>(&mut self, cells: I)
+    where
+        I: IntoIterator<Item = CellId>,
+    {
+        self.set_blocked(cells, false);
+    }
+
+    /// Tests whether the cell containing the `point` is blocked, see [`HashGrid::mark_blocked`].
+    ///
+    /// Points outside the grid bounds are clamped into them, the same way entities are
+    /// wrapped on insertion.
+    pub fn is_blocked(&self, point: (F, F, F)) -> bool {
+        let (_, _, floor) = self.get_cell_coordinates(self.clamp(point));
+        self.cell_blocked(floor, self.world_cell(floor, point))
+    }
+
+    /// Tests whether the `cell` is blocked, see [`HashGrid::mark_blocked`]
+    pub fn is_cell_blocked(&self, cell: CellId) -> bool {
+        self.cell_blocked(cell.floor, [cell.x, cell.y])
+    }
+
+    /// Tests whether the signed `cell` of the `floor` is blocked
+    pub(super) fn cell_blocked(&self, floor: usize, cell: [i64; 2]) -> bool {
+        self.blocked.get(floor).is_some_and(|mask| mask.get(cell))
+    }
+
+    /// Range of the signed cell coordinates of the `floor` within the grid bounds, both ends
+    /// included
+    pub(super) fn floor_range(&self, floor: usize) -> [[i64; 2]; 2] {
+        let (min, max) = (self.bounds.min(), self.bounds.max());
+
+        [
+            self.world_cell(floor, (min[0], min[1], min[2])),
+            self.world_cell(floor, (max[0], max[1], max[2])),
+        ]
+    }
+
+    /// Areas blocked on the `floor`, as the `min` and `max` corners of the blocked cells
+    pub(super) fn blocked_areas(&self, floor: usize) -> Vec<((F, F), (F, F))> {
+        let (size_x, size_y) = self.floor_cell_sizes(floor);
+        let half = F::from_f64(0.5).unwrap();
+
+        self.blocked[floor]
+            .cells()
+            .map(|cell| {
+                let (x, y) = self.cell_centre(floor, cell);
+                (
+                    (x - size_x * half, y - size_y * half),
+                    (x + size_x * half, y + size_y * half),
+                )
+            })
+            .collect()
+    }
+
+    /// Blocks every cell of the `floor` overlapping the `areas`, once the resolution of the
+    /// floor changed
+    pub(super) fn reblock(&mut self, floor: usize, areas: Vec<((F, F), (F, F))>) {
+        self.blocked[floor] = BlockMask::default();
+
+        let (size_x, size_y) = self.floor_cell_sizes(floor);
+        let origin = self.origin();
+
+        // Cells only touching an area along an edge are left free
+        let first = |v: F, size: F| (v / size).floor().to_i64().unwrap_or(0);
+        let last = |v: F, size: F| (v / size).ceil().to_i64().unwrap_or(0) - 1;
+
+        let range = self.floor_range(floor);
+        for (min, max) in areas {
+            let (min, max) = (
+                (min.0 - origin.0, min.1 - origin.1),
+                (max.0 - origin.0, max.1 - origin.1),
+            );
+
+            for y in first(min.1, size_y)..=last(max.1, size_y) {
+                for x in first(min.0, size_x)..=last(max.0, size_x) {
+                    self.blocked[floor].set(range, [x, y], true);
+                }
+            }
+        }
+    }
+
+    fn set_blocked<I>(&mut self, cells: I, blocked: bool)
+    where
+        I: IntoIterator<Item = CellId>,
+    {
+        for cell in cells {
+            if cell.floor >= self.floors() {
+                continue;
+            }
+
+            let range = self.floor_range(cell.floor);
+            self.blocked[cell.floor].set(range, [cell.x, cell.y], blocked);
+        }
+    }
+}
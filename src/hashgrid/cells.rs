@@ -0,0 +1,372 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::mem::{size_of, take};
+
+use num_traits::{FromPrimitive, PrimInt, ToPrimitive};
+
+use super::grid::Grid;
+use crate::memory::hash_table_bytes;
+
+/// Storage of the cells of each floor of a [`HashGrid`](super::HashGrid), selected with
+/// [`HashGrid::with_storage`](super::HashGrid::with_storage).
+///
+/// * `Sparse:` Cells are kept in a HashMap, only the occupied ones taking memory. Suited to
+///   huge worlds where most cells are empty
+/// * `Dense:` Cells are kept in an array indexed by their hash index, sized for every cell
+///   of the floor up front. Lookups skip hashing altogether, which is faster when most cells
+///   are occupied. Floors finer than about 724 by 724 cells keep their extra cells in a
+///   HashMap as well
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CellStorage {
+    #[default]
+    Sparse,
+    Dense,
+}
+
+/// Largest number of slots reserved by dense cells, covering the hash indices of about 724 by
+/// 724 cells. Hash indices beyond fall back to the map of the sparse cells
+const MAX_SLOTS: usize = 1 << 20;
+
+/// Cells of a floor, mapping the hash index of each occupied cell to the [`Bucket`] of its
+/// items.
+///
+/// Dense cells keep a slot for every hash index up to the largest one of the floor, within
+/// [`MAX_SLOTS`], any other hash index falling back to the map of the sparse cells. Both
+/// storages behave the same, cells left empty being dropped from the occupied ones.
+pub(super) struct Cells<Hx, E, M, S> {
+    storage: CellStorage,
+    /// Cells indexed by their hash index, empty for sparse cells
+    slots: Vec<Bucket<E, M>>,
+    /// Number of occupied slots
+    occupied: usize,
+    /// Cells whose hash index has no slot
+    map: Grid<Hx, Bucket<E, M>, S>,
+}
+
+/// Items of a cell along with the metadata each of them was filed with, kept in parallel
+/// lists so that the items can be borrowed on their own
+pub(super) struct Bucket<E, M> {
+    items: Vec<E>,
+    meta: Vec<M>,
+}
+
+impl<E, M> Bucket<E, M> {
+    /// Items of the cell, in the order they were filed in
+    pub(super) fn items(&self) -> &[E] {
+        &self.items
+    }
+
+    /// Iterates over the items of the cell along with their metadata
+    pub(super) fn iter(&self) -> impl Iterator<Item = (&E, &M)> + '_ {
+        self.items.iter().zip(self.meta.iter())
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Keeps the items for which `f` returns `true`, in their order
+    pub(super) fn retain<R>(&mut self, mut f: R)
+    where
+        R: FnMut(&E, &M) -> bool,
+    {
+        let mut kept = 0;
+        for i in 0..self.items.len() {
+            if f(&self.items[i], &self.meta[i]) {
+                self.items.swap(kept, i);
+                self.meta.swap(kept, i);
+                kept += 1;
+            }
+        }
+
+        self.items.truncate(kept);
+        self.meta.truncate(kept);
+    }
+
+    /// Bytes reserved for the items and their metadata
+    pub(super) fn bytes(&self) -> usize {
+        self.items.capacity() * size_of::<E>() + self.meta.capacity() * size_of::<M>()
+    }
+
+    fn push(&mut self, item: E, meta: M) {
+        self.items.push(item);
+        self.meta.push(meta);
+    }
+
+    fn remove(&mut self, index: usize) -> (E, M) {
+        (self.items.remove(index), self.meta.remove(index))
+    }
+
+    fn position<P>(&self, mut predicate: P) -> Option<usize>
+    where
+        P: FnMut(&E, &M) -> bool,
+    {
+        self.iter().position(|(item, meta)| predicate(item, meta))
+    }
+}
+
+impl<E, M> Default for Bucket<E, M> {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            meta: Vec::new(),
+        }
+    }
+}
+
+impl<E, M> IntoIterator for Bucket<E, M> {
+    type Item = (E, M);
+    type IntoIter = core::iter::Zip<alloc::vec::IntoIter<E>, alloc::vec::IntoIter<M>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter().zip(self.meta)
+    }
+}
+
+// Printed as the list of the items, the metadata being an implementation detail
+impl<E: fmt::Debug, M> fmt::Debug for Bucket<E, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.items.iter()).finish()
+    }
+}
+
+impl<Hx, E, M, S> Cells<Hx, E, M, S>
+where
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Creates sparse cells hashed with the `hasher`
+    pub(super) fn sparse(hasher: S) -> Self {
+        Self {
+            storage: CellStorage::Sparse,
+            slots: Vec::new(),
+            occupied: 0,
+            map: Grid::with_hasher(hasher),
+        }
+    }
+
+    /// Creates dense cells with a slot for the hash indices up to `max_key`, larger hash
+    /// indices being hashed with the `hasher`
+    pub(super) fn dense(max_key: Hx, hasher: S) -> Self {
+        let mut cells = Self::sparse(hasher);
+        cells.storage = CellStorage::Dense;
+        cells.reserve_slots(max_key);
+
+        cells
+    }
+
+    /// Storage of the cells
+    pub(super) fn storage(&self) -> CellStorage {
+        self.storage
+    }
+
+    /// Sizes the slots of empty dense cells for the hash indices up to `max_key`, at most
+    /// [`MAX_SLOTS`] of them
+    pub(super) fn reserve_slots(&mut self, max_key: Hx) {
+        debug_assert!(self.is_empty());
+
+        if self.storage == CellStorage::Dense {
+            let len = max_key
+                .to_usize()
+                .map_or(MAX_SLOTS, |key| key.saturating_add(1))
+                .min(MAX_SLOTS);
+            self.slots.clear();
+            self.slots.resize_with(len, Bucket::default);
+        }
+    }
+
+    /// Items of the cell with the hash index `key`, or `None` if the cell is empty
+    pub(super) fn get(&self, key: &Hx) -> Option<&[E]> {
+        self.bucket(key).map(|bucket| bucket.items())
+    }
+
+    /// Bucket of the cell with the hash index `key`, or `None` if the cell is empty
+    pub(super) fn bucket(&self, key: &Hx) -> Option<&Bucket<E, M>> {
+        match self.slot(key) {
+            Some(i) => Some(&self.slots[i]).filter(|cell| !cell.is_empty()),
+            None => self.map.get(key),
+        }
+    }
+
+    /// Number of occupied cells
+    pub(super) fn len(&self) -> usize {
+        self.occupied + self.map.len()
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterates over the occupied cells along with their hash index
+    pub(super) fn iter(&self) -> impl Iterator<Item = (Hx, &Bucket<E, M>)> + '_ {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| !cell.is_empty())
+            .map(|(i, cell)| (Hx::from_usize(i).unwrap(), cell))
+            .chain(self.map.iter().map(|(&key, cell)| (key, cell)))
+    }
+
+    /// Iterates over the buckets of the occupied cells
+    pub(super) fn values(&self) -> impl Iterator<Item = &Bucket<E, M>> + '_ {
+        self.iter().map(|(_, cell)| cell)
+    }
+
+    /// Appends the `item` filed with the `meta` to the cell with the hash index `key`
+    pub(super) fn push(&mut self, key: Hx, item: E, meta: M) {
+        match self.slot(&key) {
+            Some(i) => {
+                if self.slots[i].is_empty() {
+                    self.occupied += 1;
+                }
+                self.slots[i].push(item, meta);
+            }
+            None => self.map.entry(key).or_default().push(item, meta),
+        }
+    }
+
+    /// Takes the bucket of the cell with the hash index `key` out, leaving the cell empty
+    pub(super) fn remove(&mut self, key: &Hx) -> Option<Bucket<E, M>> {
+        match self.slot(key) {
+            Some(i) if self.slots[i].is_empty() => None,
+            Some(i) => {
+                self.occupied -= 1;
+                Some(take(&mut self.slots[i]))
+            }
+            None => self.map.remove(key),
+        }
+    }
+
+    /// Keeps the occupied cells for which `f` returns `true`, `f` being free to change the
+    /// items of the cells. Cells left without items are dropped as well.
+    pub(super) fn retain<R>(&mut self, mut f: R)
+    where
+        R: FnMut(&Hx, &mut Bucket<E, M>) -> bool,
+    {
+        for (i, cell) in self.slots.iter_mut().enumerate() {
+            if cell.is_empty() {
+                continue;
+            }
+
+            if !f(&Hx::from_usize(i).unwrap(), cell) || cell.is_empty() {
+                *cell = Bucket::default();
+                self.occupied -= 1;
+            }
+        }
+
+        self.map
+            .retain(|key, cell| f(key, cell) && !cell.is_empty());
+    }
+
+    /// Keeps the items of the cell with the hash index `key` for which `f` returns `true`,
+    /// dropping the cell if it is left empty
+    pub(super) fn retain_in<R>(&mut self, key: &Hx, f: R)
+    where
+        R: FnMut(&E, &M) -> bool,
+    {
+        let cell = match self.slot(key) {
+            Some(i) => Some(&mut self.slots[i]),
+            None => self.map.get_mut(key),
+        };
+
+        let emptied = cell.is_some_and(|cell| {
+            cell.retain(f);
+            cell.is_empty()
+        });
+        if emptied {
+            self.forget_cell(key);
+        }
+    }
+
+    /// Takes the first item matching the `predicate` out of the occupied cells, dropping its
+    /// cell if it is left empty. Returns the item and its metadata along with the hash index
+    /// of its cell.
+    pub(super) fn take_first<P>(&mut self, mut predicate: P) -> Option<(Hx, E, M)>
+    where
+        P: FnMut(&E, &M) -> bool,
+    {
+        let (key, (item, meta)) = self
+            .slots
+            .iter_mut()
+            .enumerate()
+            .find_map(|(i, cell)| {
+                let index = cell.position(&mut predicate)?;
+                Some((Hx::from_usize(i).unwrap(), cell.remove(index)))
+            })
+            .or_else(|| {
+                self.map.iter_mut().find_map(|(&key, cell)| {
+                    let index = cell.position(&mut predicate)?;
+                    Some((key, cell.remove(index)))
+                })
+            })?;
+
+        if self.bucket(&key).is_none_or(|cell| cell.is_empty()) {
+            self.forget_cell(&key);
+        }
+
+        Some((key, item, meta))
+    }
+
+    /// Takes the buckets of every occupied cell out, along with the hash index of their cell
+    pub(super) fn drain(&mut self) -> Vec<(Hx, Bucket<E, M>)> {
+        self.occupied = 0;
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, cell)| !cell.is_empty())
+            .map(|(i, cell)| (Hx::from_usize(i).unwrap(), take(cell)))
+            .chain(self.map.drain())
+            .collect()
+    }
+
+    /// Bytes reserved to map the hash indices to the buckets, see
+    /// [`MemoryFootprint`](crate::memory::MemoryFootprint)
+    pub(super) fn bucket_bytes(&self) -> usize {
+        self.slots.capacity() * size_of::<Bucket<E, M>>()
+            + hash_table_bytes::<(Hx, Bucket<E, M>)>(self.map.capacity())
+    }
+
+    /// Index of the slot of the hash index `key`, if the cells have one for it
+    fn slot(&self, key: &Hx) -> Option<usize> {
+        key.to_usize().filter(|&i| i < self.slots.len())
+    }
+
+    /// Drops an emptied cell from the occupied ones
+    fn forget_cell(&mut self, key: &Hx) {
+        match self.slot(key) {
+            Some(i) => {
+                // Emptied in place, the allocation of the cell is released along with it
+                self.slots[i] = Bucket::default();
+                self.occupied -= 1;
+            }
+            None => {
+                self.map.remove(key);
+            }
+        }
+    }
+}
+
+// Implemented by hand so that both storages print as a map of the occupied cells
+impl<Hx, E, M, S> fmt::Debug for Cells<Hx, E, M, S>
+where
+    Hx: fmt::Debug,
+    E: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(
+                self.slots
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, cell)| !cell.is_empty()),
+            )
+            .entries(self.map.iter())
+            .finish()
+    }
+}
@@ -0,0 +1,86 @@
+use core::hash::{BuildHasher, Hash};
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+
+use super::{Boundary, HashGrid};
+use crate::geometry::Geometry;
+
+/// Cell of a [`HashGrid`] in world space, returned by [`HashGrid::cells_overlapping`].
+///
+/// Coordinates are signed and counted from the origin of the frame as in
+/// [`HashGrid::pathfind`], so every cell of the world has its own id even though the cells
+/// of the grid are mirrored around the origin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CellId {
+    pub floor: usize,
+    pub x: i64,
+    pub y: i64,
+}
+
+impl CellId {
+    /// Cell coordinates on the x and y axis as used to compute the hash index of the cell,
+    /// see [`CellRef`](super::CellRef)
+    pub fn hash_coordinates(&self) -> [u32; 2] {
+        [
+            self.x.unsigned_abs().min(u32::MAX as u64) as u32,
+            self.y.unsigned_abs().min(u32::MAX as u64) as u32,
+        ]
+    }
+}
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Returns the area covered by the cell `cx`, `cy` of the `floor` in world space as a
+    /// [`Geometry::Rect`], such as for debug draws.
+    ///
+    /// The cell is given as signed coordinates as in [`CellId`], and the rect spans the height
+    /// of the floor.
+    pub fn cell_bounds(&self, cx: i64, cy: i64, floor: usize) -> Geometry<F> {
+        let (size_x, size_y) = self.floor_cell_sizes(floor);
+        let (x, y) = self.cell_centre(floor, [cx, cy]);
+
+        let floor_size = self.floor_size();
+        let half = F::from_f64(0.5).unwrap();
+        let z = self.origin().2 + (F::from_usize(floor).unwrap() + half) * floor_size;
+
+        Geometry::rect((x, y, z), (size_x, size_y, floor_size))
+    }
+
+    /// Iterates over the cells overlapping the bounding box of the `rect`, floor by floor
+    /// and row by row, such as the chunks to stream in around a viewer.
+    ///
+    /// The box is clamped into the grid bounds first, the same way entities are wrapped on
+    /// insertion, so every cell which may hold an entity of the area is returned.
+    pub fn cells_overlapping(&self, rect: &Geometry<F>) -> impl Iterator<Item = CellId> + '_ {
+        let (min, max) = rect.bounding_box();
+        let (bmin, bmax) = (self.bounds.min(), self.bounds.max());
+        let clamp = |v: F, axis: usize| v.min(bmax[axis]).max(bmin[axis]);
+
+        let origin = self.origin();
+        let (min_x, max_x) = (clamp(min.0, 0) - origin.0, clamp(max.0, 0) - origin.0);
+        let (min_y, max_y) = (clamp(min.1, 1) - origin.1, clamp(max.1, 1) - origin.1);
+
+        let last_floor = self.floors() - 1;
+        let floor_of = |z: F| {
+            ((clamp(z, 2) - origin.2) / self.floor_size())
+                .floor()
+                .to_usize()
+                .unwrap_or(0)
+                .min(last_floor)
+        };
+        let cell = |v: F, size: F| (v / size).floor().to_i64().unwrap_or(0);
+
+        (floor_of(min.2)..=floor_of(max.2)).flat_map(move |floor| {
+            let (size_x, size_y) = self.floor_cell_sizes(floor);
+            let range_x = cell(min_x, size_x)..=cell(max_x, size_x);
+
+            (cell(min_y, size_y)..=cell(max_y, size_y))
+                .flat_map(move |y| range_x.clone().map(move |x| CellId { floor, x, y }))
+        })
+    }
+}
@@ -0,0 +1,74 @@
+use alloc::{vec, vec::Vec};
+use core::hash::{BuildHasher, Hash};
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+use rustc_hash::FxBuildHasher;
+
+use super::grid::Grid;
+use super::{Coordinate, DataIndex, Entity, HashGrid};
+use crate::geometry::Geometry;
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Groups the entities of the grid into clusters, two entities belonging to the same
+    /// cluster whenever they are linked through a chain of entities each closer than the
+    /// `max_link_distance` to the next one.
+    ///
+    /// Every entity only looks for its links in the cells around it, the links being merged
+    /// with a union-find. The ids of each cluster are sorted, and the clusters are sorted by
+    /// their smallest id. Isolated entities form clusters of their own.
+    pub fn clusters<Id>(&self, max_link_distance: F) -> Vec<Vec<Id>>
+    where
+        Id: DataIndex,
+        T: Coordinate<Item = F> + Entity<ID = Id>,
+    {
+        // Entities spanning several floors are filed in a cell of each, but listed once
+        let entities: Vec<_> = self.iter_all().collect();
+
+        // Indices of the entities in the union-find, keyed by the address of their data
+        let mut indices = Grid::with_capacity_and_hasher(entities.len(), FxBuildHasher);
+        for (index, &(entity, _)) in entities.iter().enumerate() {
+            indices.insert(entity as *const T as usize, index);
+        }
+
+        let mut parents: Vec<usize> = (0..entities.len()).collect();
+        let mut links = Vec::new();
+
+        for (index, &(_, position)) in entities.iter().enumerate() {
+            let reach = Geometry::radius(position, max_link_distance);
+            self.query_into(&reach, &mut links);
+
+            for &link in links.iter() {
+                if let Some(&other) = indices.get(&(link as *const T as usize)) {
+                    let (a, b) = (find(&mut parents, index), find(&mut parents, other));
+                    parents[a.max(b)] = a.min(b);
+                }
+            }
+        }
+
+        let mut clusters: Vec<Vec<Id>> = vec![Vec::new(); entities.len()];
+        for (index, (entity, _)) in entities.iter().enumerate() {
+            let root = find(&mut parents, index);
+            clusters[root].push(entity.id());
+        }
+
+        let mut clusters: Vec<_> = clusters.into_iter().filter(|c| !c.is_empty()).collect();
+        clusters.iter_mut().for_each(|c| c.sort_unstable());
+        clusters.sort_unstable_by_key(|c| c[0]);
+
+        clusters
+    }
+}
+
+/// Finds the root of the set holding the `index`, halving the path along the way
+fn find(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
@@ -0,0 +1,72 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::hash::{BuildHasher, Hash};
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+
+use super::{Coordinate, DataIndex, Entity, HashGrid};
+
+/// Changes between two snapshots of a grid, returned by [`HashGrid::diff`]
+///
+/// * `added:` Ids of the entities only found in the newer snapshot
+/// * `removed:` Ids of the entities only found in the older snapshot
+/// * `moved:` Ids of the entities found in both snapshots at different coordinates
+///
+/// Every list is sorted by id.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GridDiff<Id> {
+    pub added: Vec<Id>,
+    pub removed: Vec<Id>,
+    pub moved: Vec<Id>,
+}
+
+impl<Id> GridDiff<Id> {
+    /// Returns `true` if both snapshots hold the same entities at the same coordinates
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+}
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Lists the entities added, removed and moved in this grid since the `older` snapshot,
+    /// so replication layers can compute the world deltas from the grids they already keep.
+    ///
+    /// Entities are matched by id, the grids being free to reference different copies of
+    /// the data.
+    pub fn diff<Id>(&self, older: &HashGrid<'_, F, T, Hx, S>) -> GridDiff<Id>
+    where
+        Id: DataIndex,
+        T: Coordinate<Item = F> + Entity<ID = Id>,
+    {
+        let positions = |grid: &HashGrid<'_, F, T, Hx, S>| {
+            grid.iter_all()
+                .map(|(entity, position)| (entity.id(), position))
+                .collect::<BTreeMap<_, _>>()
+        };
+
+        let (newer, older) = (positions(self), positions(older));
+
+        let mut diff = GridDiff::default();
+
+        for (&id, position) in &newer {
+            match older.get(&id) {
+                None => diff.added.push(id),
+                Some(previous) if previous != position => diff.moved.push(id),
+                Some(_) => {}
+            }
+        }
+
+        diff.removed = older
+            .keys()
+            .filter(|id| !newer.contains_key(id))
+            .copied()
+            .collect();
+
+        diff
+    }
+}
@@ -0,0 +1,81 @@
+use alloc::{collections::BTreeMap, string::String};
+use core::{
+    fmt::Display,
+    fmt::Write,
+    hash::{BuildHasher, Hash},
+};
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+
+use super::{Boundary, Coordinate, HashGrid, YAxis};
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive + Display,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Exports the occupied cells of the grid as a GeoJSON `FeatureCollection`, so the grid can
+    /// be inspected in GIS tools such as QGIS or kepler.gl.
+    ///
+    /// Every occupied cell becomes a `Polygon` feature spanning the world rectangle of the cell,
+    /// with the `floor` index and the `count` of entities as properties. Entities wrapped into
+    /// the grid are accounted for in the boundary cell they were wrapped into.
+    ///
+    /// GeoJSON coordinates are y-up, so the y coordinates of a grid with a [`YAxis::Down`]
+    /// frame are negated.
+    pub fn to_geojson(&self) -> String
+    where
+        T: Coordinate<Item = F>,
+    {
+        let grid_max_bounds = self.bounds.max();
+        let grid_min_bounds = self.bounds.min();
+
+        // Counting the entities per world cell, the hash index alone is not enough since
+        // cells mirrored around the origin share the same index
+        let origin = self.origin();
+        let mut occupied = BTreeMap::<(usize, i64, i64), usize>::new();
+        for (floor, grid) in self.grids.iter().enumerate() {
+            for (entity, filing) in grid.values().flat_map(|bucket| bucket.iter()) {
+                let (x, y, _) = filing.position(*entity);
+                let x = x.min(grid_max_bounds[0]).max(grid_min_bounds[0]);
+                let y = y.min(grid_max_bounds[1]).max(grid_min_bounds[1]);
+
+                let (size_x, size_y) = self.floor_cell_sizes(floor);
+                let cx = ((x - origin.0) / size_x).floor().to_i64().unwrap_or(0);
+                let cy = ((y - origin.1) / size_y).floor().to_i64().unwrap_or(0);
+
+                *occupied.entry((floor, cx, cy)).or_insert(0) += 1;
+            }
+        }
+
+        let mut geojson = String::from(r#"{"type":"FeatureCollection","features":["#);
+
+        for (i, (&(floor, cx, cy), &count)) in occupied.iter().enumerate() {
+            let (size_x, size_y) = self.floor_cell_sizes(floor);
+            let x0 = F::from_i64(cx).unwrap() * size_x + origin.0;
+            let y0 = F::from_i64(cy).unwrap() * size_y + origin.1;
+            let x1 = x0 + size_x;
+            let y1 = y0 + size_y;
+
+            // Flipping a y-down cell keeps the ring counter-clockwise
+            let (y0, y1) = match self.frame().y_axis {
+                YAxis::Up => (y0, y1),
+                YAxis::Down => (-y1, -y0),
+            };
+
+            if i > 0 {
+                geojson.push(',');
+            }
+
+            // Writing into a String never fails
+            let _ = write!(
+                geojson,
+                r#"{{"type":"Feature","geometry":{{"type":"Polygon","coordinates":[[[{x0},{y0}],[{x1},{y0}],[{x1},{y1}],[{x0},{y1}],[{x0},{y0}]]]}},"properties":{{"floor":{floor},"count":{count}}}}}"#
+            );
+        }
+
+        geojson.push_str("]}");
+        geojson
+    }
+}
@@ -0,0 +1,186 @@
+use alloc::collections::BinaryHeap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{Ordering, Reverse};
+use core::hash::{BuildHasher, Hash};
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+
+use super::grid::DataRef;
+use super::pathfind::{CellCache, NEIGHBOURS};
+use super::{Coordinate, HashGrid};
+
+/// # Flow Field
+///
+/// Direction to follow from every cell of a floor to reach a goal along the cheapest path,
+/// built by [`HashGrid::flow_field`]. Any number of agents can steer towards the goal by
+/// looking up the direction of their cell, without searching a path each.
+///
+/// Cells are given as signed coordinates counted from the origin of the frame, as in
+/// [`HashGrid::pathfind`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowField<F> {
+    goal: [i64; 2],
+    range: [[i64; 2]; 2],
+    origin: (F, F),
+    cell_size: (F, F),
+    costs: Vec<F>,
+    directions: Vec<Option<[i8; 2]>>,
+}
+
+impl<F> FlowField<F>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+{
+    /// Cell of the goal
+    pub fn goal(&self) -> [i64; 2] {
+        self.goal
+    }
+
+    /// Signed coordinates of the cell containing the `point`, or `None` if it lies outside
+    /// the field
+    pub fn cell_of(&self, point: (F, F)) -> Option<[i64; 2]> {
+        let cell = [
+            ((point.0 - self.origin.0) / self.cell_size.0)
+                .floor()
+                .to_i64()?,
+            ((point.1 - self.origin.1) / self.cell_size.1)
+                .floor()
+                .to_i64()?,
+        ];
+
+        self.index(cell).map(|_| cell)
+    }
+
+    /// Total cost of the cheapest path from the `cell` to the goal, or `None` if the goal
+    /// cannot be reached from it
+    pub fn cost(&self, cell: [i64; 2]) -> Option<F> {
+        self.index(cell)
+            .map(|i| self.costs[i])
+            .filter(|cost| cost.is_finite())
+    }
+
+    /// Unit vector pointing from the `cell` towards the next cell of the cheapest path to the
+    /// goal, or `None` for the goal itself and the cells from which it cannot be reached
+    pub fn direction(&self, cell: [i64; 2]) -> Option<(F, F)> {
+        let [dx, dy] = self.index(cell).and_then(|i| self.directions[i])?;
+        let (dx, dy) = (F::from_i8(dx).unwrap(), F::from_i8(dy).unwrap());
+        let length = (dx * dx + dy * dy).sqrt();
+
+        Some((dx / length, dy / length))
+    }
+
+    /// Same as [`FlowField::direction`], for the cell containing the `point`
+    pub fn direction_at(&self, point: (F, F)) -> Option<(F, F)> {
+        self.cell_of(point).and_then(|cell| self.direction(cell))
+    }
+
+    fn index(&self, cell: [i64; 2]) -> Option<usize> {
+        let [lo, hi] = self.range;
+        if !(lo[0]..=hi[0]).contains(&cell[0]) || !(lo[1]..=hi[1]).contains(&cell[1]) {
+            return None;
+        }
+
+        let width = hi[0] - lo[0] + 1;
+        Some(((cell[1] - lo[1]) * width + cell[0] - lo[0]) as usize)
+    }
+}
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Builds the [`FlowField`] leading every cell of the floor of the `goal` to the cell of
+    /// the `goal`, with Dijkstra's algorithm spreading out from the goal.
+    ///
+    /// `cost_fn` gives the cost of entering a cell from its coordinates and the entities
+    /// located inside it, such as a higher cost for crowded cells, or `None` for cells which
+    /// cannot be crossed. Costs which are negative or not finite are treated as `None`, and
+    /// cells marked with [`HashGrid::mark_blocked`] cannot be crossed either.
+    /// Diagonal steps cost `√2` times more, and do not cut the corners of cells which cannot
+    /// be crossed.
+    pub fn flow_field<C>(&self, goal: (F, F, F), cost_fn: C) -> FlowField<F>
+    where
+        T: Coordinate<Item = F>,
+        C: Fn([i64; 2], &[DataRef<'a, T>]) -> Option<F>,
+    {
+        let (_, _, floor) = self.get_cell_coordinates(self.clamp(goal));
+        let target = self.world_cell(floor, goal);
+        let valid = |cost: Option<F>| cost.filter(|c| c.is_finite() && *c >= F::zero());
+
+        let mut cells = CellCache::new(self, floor, &cost_fn);
+        let range = cells.range();
+        let [lo, hi] = range;
+        let area = ((hi[0] - lo[0] + 1) * (hi[1] - lo[1] + 1)) as usize;
+
+        let mut field = FlowField {
+            goal: target,
+            range,
+            origin: (self.origin().0, self.origin().1),
+            cell_size: self.floor_cell_sizes(floor),
+            costs: vec![F::infinity(); area],
+            directions: vec![None; area],
+        };
+
+        let diagonal = F::from_f64(core::f64::consts::SQRT_2).unwrap();
+        let mut open = BinaryHeap::from([Reverse((Cost(F::zero()), target))]);
+        // The goal is clamped into the bounds, so its cell always belongs to the field
+        let start = field.index(target).unwrap();
+        field.costs[start] = F::zero();
+
+        while let Some(Reverse((Cost(cost), cell))) = open.pop() {
+            let i = field.index(cell).unwrap();
+            if cost > field.costs[i] {
+                continue;
+            }
+
+            for (dx, dy) in NEIGHBOURS {
+                let next = [cell[0] + dx, cell[1] + dy];
+                let Some(enter) = valid(cells.get(next).flatten()) else {
+                    continue;
+                };
+
+                let step = match dx != 0 && dy != 0 {
+                    // Diagonal steps may not cut the corner of a cell which cannot be crossed
+                    true if valid(cells.get([cell[0] + dx, cell[1]]).flatten()).is_none()
+                        || valid(cells.get([cell[0], cell[1] + dy]).flatten()).is_none() =>
+                    {
+                        continue
+                    }
+                    true => enter * diagonal,
+                    false => enter,
+                };
+
+                let j = field.index(next).unwrap();
+                if cost + step < field.costs[j] {
+                    field.costs[j] = cost + step;
+                    // Agents walk the search backwards, from the next cell towards this one
+                    field.directions[j] = Some([-dx as i8, -dy as i8]);
+                    open.push(Reverse((Cost(cost + step), next)));
+                }
+            }
+        }
+
+        field
+    }
+}
+
+/// Float cost ordered for the queue of Dijkstra's algorithm, costs never being NaN
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cost<F>(F);
+
+impl<F: PartialEq> Eq for Cost<F> {}
+
+impl<F: PartialOrd> PartialOrd for Cost<F> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<F: PartialOrd> Ord for Cost<F> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.partial_cmp(&other.0).unwrap_or(Ordering::Equal)
+    }
+}
@@ -1,24 +1,49 @@
+use alloc::{collections::BTreeSet, vec, vec::Vec};
 use core::fmt;
-use std::{
-    collections::{
-        hash_map::Entry::{Occupied, Vacant},
-        HashMap,
-    },
+use core::{
+    cmp::Ordering,
     fmt::Display,
-    hash::Hash,
+    hash::{BuildHasher, Hash},
+    mem::size_of,
+    ops::RangeInclusive,
+    ptr,
 };
+#[cfg(not(feature = "std"))]
+use hashbrown::HashMap;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
 
 use num_traits::{Float, FromPrimitive, One, PrimInt, ToPrimitive};
+use rustc_hash::FxBuildHasher;
 
+use crate::composite::GeometryGroup;
+use crate::error::SpatialError;
+use crate::geometry::Geometry;
+use crate::layers::LayerMask;
+use crate::linear::LinearIndex;
+use crate::math::distance;
+use crate::memory::{hash_table_bytes, MemoryFootprint};
+use crate::partition::{Positioned, SpatialInsertion, SpatialQuery, SpatialRemoval, SpatialUpdate};
+use crate::relevance::{Prioritized, RelevanceProfile};
+#[cfg(feature = "stats")]
+use crate::stats::QueryStats;
+#[cfg(feature = "stats")]
+use core::cell::Cell;
+
+use super::blocked::BlockMask;
+use super::cells::{CellStorage, Cells};
+use super::order::unpair;
 use super::{
-    Boundary, CellSizes, CellsPerAxis, Coordinate, DataIndex, Entity, GridBoundary, GridParameters,
-    HashIndex, Query, QueryResult, QueryType,
+    Boundary, CellRef, CellSizes, CellsPerAxis, Coordinate, CoordinateFrame, DataIndex, Entity,
+    ExtentEntity, GridBoundary, GridParameters, HashIndex, Origin, Query, QueryResult, QueryStatus,
+    QueryType,
 };
 
 /// Grid is an alias for HashMaps
 ///
-/// Its a wrapper around the core HashMap type and inherets all the functionalities of a HashMap
-pub type Grid<K, V> = HashMap<K, V>;
+/// Its a wrapper around the core HashMap type and inherets all the functionalities of a HashMap.
+/// Without the `std` feature the hashbrown HashMap, which backs the std one, is used instead
+pub type Grid<K, V, S = DefaultHasher> = HashMap<K, V, S>;
 
 /// Floors is an alias for vec type
 ///
@@ -34,6 +59,12 @@ pub type DataRef<'a, T> = &'a T;
 /// Type alias for default type used by the Hashgrid for hash index
 pub type DefaultHx = u64;
 
+/// Type alias for default hasher used by the HashGrid for its cells
+///
+/// Cells are keyed by plain integer hash indices, for which the Fx hasher is considerably faster
+/// than the DoS resistant SipHash used by default in std
+pub type DefaultHasher = FxBuildHasher;
+
 /// # HashGrid
 ///
 /// A 3D/2D spatial partitioning algorithm to manage the data quickly and efficiently according to the data's spatial
@@ -44,19 +75,92 @@ pub type DefaultHx = u64;
 /// * `F (Float type):` Defines the base float type such as `f32` or `f64` for spatial components (x , y, z) and calculations
 /// * `T (generic data type):` Defines the data type to insert into the grid, data mus live as long as the grid lives`
 /// * `Hx (HashIndex type):` Defines the type to be used for hashes for data search in grid, default type for `Hx` is `u64`
+/// * `S (BuildHasher type):` Defines the hasher used by the cells of each grid, default type for `S` is [`DefaultHasher`]
 ///
-#[derive(Debug)]
-pub struct HashGrid<'a, F, T, Hx = DefaultHx> {
-    pub grids: Floors<Grid<Hx, Vec<DataRef<'a, T>>>>,
+pub struct HashGrid<'a, F, T, Hx = DefaultHx, S = DefaultHasher> {
+    /// Cells of every floor, see [`HashGrid::occupied`]
+    pub(super) grids: Floors<Cells<Hx, DataRef<'a, T>, Filing<F>, S>>,
     pub params: GridParameters<F>,
     pub bounds: GridBoundary<F>,
     pub wrap: bool,
+    /// Number of cells along the x and y axis of every floor, see [`HashGrid::with_floor_cells`]
+    resolutions: Vec<[u32; 2]>,
+    /// Frame of the coordinates, see [`HashGrid::with_frame`]
+    frame: CoordinateFrame,
+    /// Orders the results of the queries by entity id, see [`HashGrid::set_deterministic`]
+    deterministic: Option<fn(&T, &T) -> Ordering>,
+    /// Tick passed to the latest [`HashGrid::expire`], from which the TTLs are counted
+    tick: u64,
+    /// Whether entities were inserted with a TTL since the latest [`HashGrid::expire`] found
+    /// none left, sparing the visit of every cell otherwise
+    expiring: bool,
+    /// Whether entities were ever filed on several floors, see [`HashGrid::insert_spanning`]
+    spanning: bool,
+    /// Whether entities were ever filed away from the coordinates they report, see
+    /// [`HashGrid::relocate`]
+    relocated: bool,
+    /// Number of mutations made to the cells so far, see [`HashGrid::generation`]
+    generation: u64,
+    /// Generation at which each cell of every floor last changed, cells emptied since
+    /// included, see [`HashGrid::cells_changed_since`]
+    versions: Floors<Grid<Hx, u64, S>>,
+    /// Cells of every floor blocked by static terrain, see [`HashGrid::mark_blocked`]
+    pub(super) blocked: Floors<BlockMask>,
+    /// Counters of the latest geometry query, kept in a cell since queries only borrow the
+    /// grid immutably. This makes the grid `!Sync` while the `stats` feature is enabled
+    #[cfg(feature = "stats")]
+    last_stats: Cell<QueryStats>,
 }
 
 impl<'a, F, T, Hx> HashGrid<'a, F, T, Hx>
 where
     F: Float + FromPrimitive + ToPrimitive,
     Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+{
+    /// Creates a new instance of [`HashGrid`] hashing its cells with the [`DefaultHasher`].
+    ///
+    /// See [`HashGrid::with_hasher`] for the description of the parameters.
+    pub fn new<B>(cells: [u32; 2], floors: usize, bounds: &B, wrap: bool) -> Self
+    where
+        B: Boundary<Item = F>,
+    {
+        Self::with_hasher(cells, floors, bounds, wrap, DefaultHasher::default())
+    }
+
+    /// Creates a new [`HashGrid`] holding every entity of the `index`, e.g. to switch a world
+    /// which grew too dense for a linear scan over to the grid at runtime.
+    ///
+    /// See [`HashGrid::with_hasher`] for the description of the parameters. Entities which
+    /// cannot be inserted, see [`HashGrid::insert`], are left out.
+    pub fn from_linear<B>(
+        index: &LinearIndex<'a, F, T>,
+        cells: [u32; 2],
+        floors: usize,
+        bounds: &B,
+        wrap: bool,
+    ) -> Self
+    where
+        B: Boundary<Item = F>,
+        T: Coordinate<Item = F>,
+    {
+        let mut grid = Self::new(cells, floors, bounds, wrap);
+        for (&entity, &position) in index.data().iter().zip(index.positions()) {
+            // Entities moved with `LinearIndex::relocate` are filed at their new coordinates
+            let filing = Filing {
+                position,
+                ..Filing::default()
+            };
+            let _: Result<_, SpatialError> = grid.file_as(entity, filing);
+        }
+        grid
+    }
+}
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
 {
     /// Creates a new instance of [`HashGrid`] according to the number of cells and the bounds
     /// defined as the parameters.
@@ -69,9 +173,12 @@ where
     ///
     /// This is a constructor method which returns the HashGrid lazily initialized without any data, later on you can use the [`HashGrid::update`]
     /// or [`HashGrid::insert`] methods to insert the data into the grid according the individual coordinates of the data.
-    pub fn new<B>(cells: [u32; 2], floors: usize, bounds: &B, wrap: bool) -> Self
+    ///
+    /// The cells of the grid are hashed with the `hasher`, use [`HashGrid::new`] for the [`DefaultHasher`].
+    pub fn with_hasher<B>(cells: [u32; 2], floors: usize, bounds: &B, wrap: bool, hasher: S) -> Self
     where
         B: Boundary<Item = F>,
+        S: Clone,
     {
         // Identifying the max number of floors to initialize
         // the grids at each floor
@@ -115,303 +222,1954 @@ where
         };
 
         Self {
-            grids: vec![Grid::new(); floors],
+            grids: (0..floors).map(|_| Cells::sparse(hasher.clone())).collect(),
             params,
             bounds,
             wrap,
+            resolutions: vec![cells; floors],
+            frame: CoordinateFrame::default(),
+            deterministic: None,
+            tick: 0,
+            expiring: false,
+            spanning: false,
+            relocated: false,
+            generation: 0,
+            versions: (0..floors)
+                .map(|_| Grid::with_hasher(hasher.clone()))
+                .collect(),
+            blocked: vec![BlockMask::default(); floors],
+            #[cfg(feature = "stats")]
+            last_stats: Cell::new(QueryStats::default()),
         }
     }
 
+    /// Inserts the reference to a single data of type [`Entity`] into the relevant cell of the grid
+    /// according to its spatial coordinates.
+    ///
+    /// If the entity lies outside the grid bounds, it is either wrapped around the nearest cell when
+    /// `wrap` is enabled, or it is not inserted at all.
     pub fn insert(&mut self, entity: DataRef<'a, T>)
     where
-        T: Coordinate<Item = F> + Entity,
+        T: Coordinate<Item = F>,
     {
-        // Getting the grid's extreme boundary parameters to apply the boundary
-        // limits to the calculated cell cords if necessary
-        let grid_max_bounds = self.bounds.max();
-        let grid_min_bounds = self.bounds.min();
+        // The data is not inserted if the wrap is disabled and the point is not within
+        // the bounds, or if its coordinates are not finite
+        let _: Result<_, SpatialError> = self.file(entity);
+    }
+
+    /// Same as [`HashGrid::insert`], except that the reason for not inserting the entity is
+    /// reported as a [`SpatialError`].
+    ///
+    /// Unlike [`HashGrid::insert`], an entity whose id is already in the grid is rejected with
+    /// [`SpatialError::DuplicateId`]. The grid does not keep an index of the entity ids, so
+    /// every cell is visited to detect it.
+    pub fn try_insert<Id>(&mut self, entity: DataRef<'a, T>) -> Result<(), SpatialError<Id>>
+    where
+        Id: DataIndex,
+        T: Coordinate<Item = F> + Entity<ID = Id>,
+    {
+        self.reject_duplicate(entity.id())?;
+        self.file(entity)
+    }
 
-        let mut coodrinates = (entity.x(), entity.y(), entity.z());
+    /// Files the entity into the cell of its coordinates, see [`HashGrid::try_insert`]
+    pub(crate) fn file<Id>(&mut self, entity: DataRef<'a, T>) -> Result<(), SpatialError<Id>>
+    where
+        T: Coordinate<Item = F>,
+    {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("insert").entered();
 
-        // Validating if the point is within the grid bounds
-        if !self.bounds.is_inside(coodrinates) {
-            // Wraps around the nearest cell to the grid if the point is outside and wrap
-            // is enabled
-            if self.wrap {
-                coodrinates.0 = coodrinates
-                    .0
-                    .min(grid_max_bounds[0])
-                    .max(grid_min_bounds[0]);
-                coodrinates.1 = coodrinates
-                    .1
-                    .min(grid_max_bounds[1])
-                    .max(grid_min_bounds[1]);
-                coodrinates.2 = coodrinates
-                    .2
-                    .min(grid_max_bounds[2])
-                    .max(grid_min_bounds[2]);
-            } else {
-                // Return without inserting the data if the wrap is disabled and the point is
-                // not withing the bounds
-                return;
-            }
-        }
+        self.file_as(entity, Filing::default())
+    }
 
-        // Resulting cell coordinates x, y and floor index
-        let (cx, cy, floor) = self.get_cell_coordinates(coodrinates);
+    /// Files the entity into the cell of its coordinates along with the `filing`
+    fn file_as<Id>(
+        &mut self,
+        entity: DataRef<'a, T>,
+        filing: Filing<F>,
+    ) -> Result<(), SpatialError<Id>>
+    where
+        T: Coordinate<Item = F>,
+    {
+        let (hashindex, floor) = self.resolve_cell(filing.position(entity))?;
+        self.relocated |= filing.position.is_some();
+        self.place(hashindex, floor, entity, filing);
 
-        // Calculating the unique hash index from the cell coordinates to find the cell
-        // for the entity
-        let hashindex = self.key(cx, cy);
-
-        // Inserting the the entity in to the identified cell of the grid at
-        // the identified floor
-        match self.grids[floor].entry(hashindex.key()) {
-            Occupied(mut entry) => {
-                // If the cell is already existing with some data,
-                // then we just update the cell with the current entity data
-                let grid_cell = entry.get_mut();
-                grid_cell.push(entity);
-            }
-            Vacant(entry) => {
-                // If the cell is not present already, we inserts the new cell
-                // with having the current entity data inside
-                entry.insert(vec![entity]);
-            }
-        }
+        Ok(())
     }
 
-    pub fn query<Id>(&self, query: Query<F, Id>) -> QueryResult<'a, F, Id, T>
+    /// Inserts the reference to the data the same way as [`HashGrid::insert`], tagging it with
+    /// the layers of the `mask`.
+    ///
+    /// Layered queries such as [`HashGrid::query_geometry_with_layers`] only return entities
+    /// sharing at least one layer with the mask of the query. Entities inserted without a mask
+    /// belong to every layer. The mask is filed along with the reference, so each insertion of
+    /// the same data keeps its own layers.
+    pub fn insert_with_layers(&mut self, entity: DataRef<'a, T>, mask: LayerMask)
+    where
+        T: Coordinate<Item = F>,
+    {
+        let _: Result<_, SpatialError> = self.file_with_layers(entity, mask);
+    }
+
+    /// Same as [`HashGrid::insert_with_layers`], except that the reason for not inserting the
+    /// entity is reported as a [`SpatialError`], duplicate ids included as for
+    /// [`HashGrid::try_insert`]
+    pub fn try_insert_with_layers<Id>(
+        &mut self,
+        entity: DataRef<'a, T>,
+        mask: LayerMask,
+    ) -> Result<(), SpatialError<Id>>
     where
         Id: DataIndex,
         T: Coordinate<Item = F> + Entity<ID = Id>,
     {
-        let radius_x = (F::from_u32(self.xcells()).unwrap() * query.radius())
-            .max(F::one())
-            .ceil()
-            .to_i32()
-            .unwrap();
-        let radius_y = (F::from_u32(self.ycells()).unwrap() * query.radius())
-            .max(F::one())
-            .ceil()
-            .to_i32()
-            .unwrap();
-        let radius_f = (F::from_usize(self.floors()).unwrap() * query.radius())
-            .max(F::one())
-            .ceil()
-            .to_i32()
-            .unwrap();
-
-        let (cx, cy, floor) = self.get_cell_coordinates((query.x(), query.y(), query.z()));
-
-        let base_cx = cx as i32;
-        let base_cy = cy as i32;
-        let base_floor = floor as i32;
+        self.reject_duplicate(entity.id())?;
+        self.file_with_layers(entity, mask)
+    }
 
-        let range_x = (base_cx - radius_x).max(0)..=(base_cx + radius_x).min(self.xcells() as i32);
-        let range_y = (base_cy - radius_y).max(0)..=(base_cy + radius_y).min(self.ycells() as i32);
-        let range_z =
-            (base_floor - radius_f).max(0)..=(base_floor + radius_f).min(self.floors() as i32 - 1);
-
-        let relevant_indices = range_x
-            .clone()
-            .flat_map(|dx| {
-                let range_z = range_z.clone();
-                range_y.clone().flat_map(move |dy| {
-                    range_z
-                        .clone()
-                        .map(move |dz| (dx as u32, dy as u32, dz as usize))
-                })
-            })
-            .map(|(dx, dy, df)| (self.key(dx, dy), df));
+    fn file_with_layers<Id>(
+        &mut self,
+        entity: DataRef<'a, T>,
+        mask: LayerMask,
+    ) -> Result<(), SpatialError<Id>>
+    where
+        T: Coordinate<Item = F>,
+    {
+        self.file_as(
+            entity,
+            Filing {
+                layers: mask,
+                ..Filing::default()
+            },
+        )
+    }
 
-        let mut result = QueryResult {
-            query,
-            data: Vec::new(),
-        };
+    /// Returns the layers the entity matching the `id` was inserted with, or `None` if no
+    /// entity matches the `id`.
+    ///
+    /// The grid does not keep an index of the entity locations, so every cell is visited until
+    /// the entity is found.
+    pub fn layers_of<Id>(&self, id: Id) -> Option<LayerMask>
+    where
+        Id: DataIndex,
+        T: Entity<ID = Id>,
+    {
+        self.grids
+            .iter()
+            .flat_map(|grid| grid.values())
+            .flat_map(|bucket| bucket.iter())
+            .find(|(e, _)| e.id() == id)
+            .map(|(_, filing)| filing.layers)
+    }
 
-        match query.query_type() {
-            QueryType::Find(id) => {
-                for (hashindex, floor) in relevant_indices {
-                    if let Some(d_list) = self.grids[floor].get(&hashindex.key()) {
-                        if let Some(&entity) = d_list.iter().find(|&&d| d.id() == id) {
-                            result.data.push(entity);
-                            break;
+    /// Inserts the reference to the data the same way as [`HashGrid::insert`], evicting it
+    /// once it is `ticks` old.
+    ///
+    /// The TTL is counted from the tick of the latest [`HashGrid::expire`], the entity is then
+    /// evicted by the first call to [`HashGrid::expire`] with a tick at or past its expiry.
+    /// Like the layers, the TTL is filed along with the reference.
+    pub fn insert_with_ttl(&mut self, entity: DataRef<'a, T>, ticks: u64)
+    where
+        T: Coordinate<Item = F>,
+    {
+        let _: Result<_, SpatialError> = self.file_with_ttl(entity, ticks);
+    }
+
+    /// Same as [`HashGrid::insert_with_ttl`], except that the reason for not inserting the
+    /// entity is reported as a [`SpatialError`], duplicate ids included as for
+    /// [`HashGrid::try_insert`]
+    pub fn try_insert_with_ttl<Id>(
+        &mut self,
+        entity: DataRef<'a, T>,
+        ticks: u64,
+    ) -> Result<(), SpatialError<Id>>
+    where
+        Id: DataIndex,
+        T: Coordinate<Item = F> + Entity<ID = Id>,
+    {
+        self.reject_duplicate(entity.id())?;
+        self.file_with_ttl(entity, ticks)
+    }
+
+    fn file_with_ttl<Id>(
+        &mut self,
+        entity: DataRef<'a, T>,
+        ticks: u64,
+    ) -> Result<(), SpatialError<Id>>
+    where
+        T: Coordinate<Item = F>,
+    {
+        self.file_as(
+            entity,
+            Filing {
+                expiry: Some(self.tick.saturating_add(ticks)),
+                ..Filing::default()
+            },
+        )?;
+        self.expiring = true;
+
+        Ok(())
+    }
+
+    /// Inserts the reference to an entity with a vertical extent, such as an elevator or a
+    /// tower, into every floor its span from `z_min` to `z_max` reaches.
+    ///
+    /// The entity is filed in the cell of its `x` and `y` coordinates on each floor. Geometry
+    /// queries match it if the geometry contains its position at any height of the span, and
+    /// return it once even when several of its floors are visited. Like the layers, the span is
+    /// filed along with the reference.
+    pub fn insert_spanning(&mut self, entity: DataRef<'a, T>, z_min: F, z_max: F)
+    where
+        T: Coordinate<Item = F>,
+    {
+        let _: Result<_, SpatialError> = self.place_spanning(
+            entity,
+            (entity.x(), entity.y()),
+            (z_min, z_max),
+            Filing::default(),
+        );
+    }
+
+    /// Same as [`HashGrid::insert_spanning`], except that the reason for not inserting the
+    /// entity is reported as a [`SpatialError`], duplicate ids included as for
+    /// [`HashGrid::try_insert`]
+    pub fn try_insert_spanning<Id>(
+        &mut self,
+        entity: DataRef<'a, T>,
+        z_min: F,
+        z_max: F,
+    ) -> Result<(), SpatialError<Id>>
+    where
+        Id: DataIndex,
+        T: Coordinate<Item = F> + Entity<ID = Id>,
+    {
+        self.reject_duplicate(entity.id())?;
+        self.place_spanning(
+            entity,
+            (entity.x(), entity.y()),
+            (z_min, z_max),
+            Filing::default(),
+        )
+    }
+
+    /// Evicts the entities inserted with a TTL which expired by the `current_tick`, cell by
+    /// cell. Cells left empty are dropped from the grid.
+    ///
+    /// Entities inserted without a TTL are never evicted. Returns the number of evicted entities.
+    pub fn expire(&mut self, current_tick: u64) -> usize {
+        self.tick = current_tick;
+
+        if !self.expiring {
+            return 0;
+        }
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("expire", evicted = tracing::field::Empty).entered();
+
+        // Cells losing entities are marked with the generation bumped below
+        let generation = self.generation.wrapping_add(1);
+
+        // Entities spanning several floors are evicted from each of them, and counted once
+        let mut spanning = Spanning::new();
+        let mut evicted = 0;
+        let mut expiring = false;
+
+        for (grid, versions) in self.grids.iter_mut().zip(self.versions.iter_mut()) {
+            grid.retain(|&key, bucket| {
+                let before = bucket.len();
+                bucket.retain(|&entity, filing| match filing.expiry {
+                    Some(tick) if tick <= current_tick => {
+                        if spanning.first(entity, filing) {
+                            evicted += 1;
                         }
+                        false
                     }
-                }
-            }
-            QueryType::Relevant => {
-                for (hashindex, floor) in relevant_indices {
-                    if let Some(d_list) = self.grids[floor].get(&hashindex.key()) {
-                        result.data.extend_from_slice(d_list);
+                    expiry => {
+                        expiring |= expiry.is_some();
+                        true
                     }
+                });
+
+                if bucket.len() != before {
+                    versions.insert(key, generation);
                 }
-            }
+                !bucket.is_empty()
+            });
+        }
+
+        self.expiring = expiring;
+        if evicted > 0 {
+            self.bump_generation();
         }
 
-        result
+        #[cfg(feature = "tracing")]
+        span.record("evicted", evicted);
+
+        evicted
     }
 
-    /// Inserts the references to individual data from the list of data into the relevant cells of the grid by finding
-    /// unique [`HashIndex`] through cell coordinates. These cell coordinates are based on the
-    /// data of type [`Entity`] individual spatial coordinates.
+    /// Gives the `floor` its own number of `cells` along the x and y axis, such as a coarse
+    /// underground and a fine surface, chained after the constructor as in
+    /// `HashGrid::new([64, 64], 2, &bounds, false).with_floor_cells(0, [8, 8])`.
     ///
-    /// This methods takes the reference list of data of any type which must implements the following traits:
+    /// Every method maps the coordinates onto the resolution of their floor transparently.
+    /// Entities already on the floor are filed again under the new resolution.
     ///
-    /// * [`Entity`]: This trait imposes the data to have a unique id which can be used to find an entity in the grid
-    /// * [`Coordinate`]: This trait imposes the type to return the spatial coordinates `x, y, z`.
+    /// # Panics
     ///
-    /// Every `entity` or data of type `Entity` is then inserted into the belonging cell using
-    /// the unique `HashIndex`.
-    pub fn update(&mut self, data: &'a [T])
+    /// Panics if the `floor` is not a floor of the grid.
+    pub fn with_floor_cells(mut self, floor: usize, cells: [u32; 2]) -> Self
     where
-        T: Coordinate<Item = F> + Entity,
+        T: Coordinate<Item = F>,
     {
-        // Getting the grid's extreme boundary parameters to apply the boundary
-        // limits to the calculated cell cords if necessary
-        let grid_max_bounds = self.bounds.max();
-        let grid_min_bounds = self.bounds.min();
+        assert!(
+            floor < self.floors(),
+            "Invalid floor {floor}, the grid only has {} floors",
+            self.floors()
+        );
 
-        for entity in data.iter() {
-            // Getting the cell coordinates from entity coordinates
-            // z-axis from the entity coordinates defines at which floor of the grid
-            // to look for the cell
-            let mut coodrinates = (entity.x(), entity.y(), entity.z());
-
-            // Wrapping around the nearest grid bounds if the wrap is enabled and the
-            // entity is outside the grid bounds or else do not add the entity inside the grid
-            if !self.bounds.is_inside(coodrinates) {
-                if self.wrap {
-                    coodrinates.0 = coodrinates
-                        .0
-                        .min(grid_max_bounds[0])
-                        .max(grid_min_bounds[0]);
-                    coodrinates.1 = coodrinates
-                        .1
-                        .min(grid_max_bounds[1])
-                        .max(grid_min_bounds[1]);
-                    coodrinates.2 = coodrinates
-                        .2
-                        .min(grid_max_bounds[2])
-                        .max(grid_min_bounds[2]);
-                } else {
-                    continue;
-                }
-            }
+        self.set_floor_cells(floor, cells);
+        self
+    }
 
-            // Resulting cell coordinates x, y and floor index
-            let (cx, cy, floor) = self.get_cell_coordinates(coodrinates);
-
-            // Calculating the unique hash index from the cell coordinates to find the cell
-            // for the entity
-            let hashindex = self.key(cx, cy);
-
-            // Inserting the the entity in to the identified cell of the grid at
-            // the identified floor
-            match self.grids[floor].entry(hashindex.key()) {
-                Occupied(mut entry) => {
-                    // If the cell is already existing with some data,
-                    // then we just update the cell with the current entity data
-                    let grid_cell = entry.get_mut();
-                    grid_cell.push(entity);
-                }
-                Vacant(entry) => {
-                    // If the cell is not present already, we inserts the new cell
-                    // with having the current entity data inside
-                    entry.insert(vec![entity]);
-                }
-            }
+    /// Keeps the cells of every floor in the given [`CellStorage`], chained after the
+    /// constructor as in `HashGrid::new([64, 64], 1, &bounds, false).with_storage(CellStorage::Dense)`.
+    ///
+    /// Dense floors reserve a slot for every hash index of their resolution up front, about
+    /// twice the number of cells, and pay off once most of the cells are occupied. Entities
+    /// already in the grid are filed again into the new storage.
+    pub fn with_storage(mut self, storage: CellStorage) -> Self
+    where
+        T: Coordinate<Item = F>,
+        S: Clone,
+    {
+        let entities: Vec<_> = self
+            .grids
+            .iter_mut()
+            .flat_map(|grid| grid.drain())
+            .flat_map(|(_, bucket)| bucket)
+            .collect();
+
+        for floor in 0..self.floors() {
+            self.grids[floor] = self.empty_cells(floor, storage);
         }
+        for (entity, filing) in entities {
+            let _ = self.refile(entity, filing);
+        }
+
+        self
     }
 
-    /// Calculates the cells coordinates from the entity coordinates to find the cell
-    /// location inside the grid.
-    ///
-    /// Reutrns the `Floor` number, `x` and `y` components of the cell in search.
-    pub fn get_cell_coordinates(&self, coordinates: (F, F, F)) -> (u32, u32, usize) {
-        // Destructuring the entity coordinates into x, y, z components
-        let (x, y, z) = coordinates;
+    /// Returns the [`CellStorage`] of the cells of the grid
+    pub fn storage(&self) -> CellStorage {
+        self.grids
+            .first()
+            .map_or(CellStorage::default(), |grid| grid.storage())
+    }
 
-        // Normalizing the x and y component according to cell size to find the
-        // cell coordinates inside the grid
-        let cx = (x / self.cell_size_x()).floor().abs().to_u32().unwrap();
-        let cy = (y / self.cell_size_y()).floor().abs().to_u32().unwrap();
+    /// Returns the number of occupied cells of the `floor`, none past the last floor
+    pub fn occupied_cells(&self, floor: usize) -> usize {
+        self.grids.get(floor).map_or(0, |grid| grid.len())
+    }
 
-        // Getting the floor index from the z component
-        let floor = (z / self.floor_size()).floor().to_usize().unwrap();
+    /// Returns the entities filed in the cell with the hash index `key` of the `floor`, none if
+    /// the cell is empty
+    pub fn cell_entities(&self, floor: usize, key: Hx) -> &[DataRef<'a, T>] {
+        self.grids
+            .get(floor)
+            .and_then(|grid| grid.get(&key))
+            .unwrap_or_default()
+    }
 
-        (cx, cy, floor)
+    /// Iterates over the occupied cells of the `floor` along with their hash index, in no
+    /// particular order. See [`HashGrid::cells_in_order`] for a stable order.
+    pub fn occupied(&self, floor: usize) -> impl Iterator<Item = (Hx, &[DataRef<'a, T>])> + '_ {
+        self.grids
+            .get(floor)
+            .into_iter()
+            .flat_map(|grid| grid.iter())
+            .map(|(key, bucket)| (key, bucket.items()))
     }
 
-    /// Calculates the unique hash of a specefic cell in the [`HashGrid`] to retreive or
-    /// insert the entity or data of type [`Entity`]. It calculate the unique hash id through
-    /// cantor pairing formula which uses the cell coordinates x and y as the `k1` and `k2`
-    /// components and z componenet to determine on which `floor` to look for the data.
+    /// Adjusts the number of cells of every floor so that occupied cells hold about
+    /// `target_per_cell` entities on average, for grids created before the number of entities
+    /// was known.
     ///
-    /// __Cantor pairing formula__:
+    /// The current occupancy of each floor is measured, and the cells along both axis are
+    /// scaled by the square root of its ratio to the target, the entities spreading over the
+    /// area of the smaller or larger cells. The cells along each axis are capped so that the
+    /// hash indices of the floor still fit in `Hx`, see [`HashGrid::key`]. Floors whose
+    /// resolution changes are filed again in place, empty floors are left untouched. Returns
+    /// the number of floors retuned.
     ///
-    /// `((k1 + k2) * (k1 + k2 + 1)) / 2 + k2`
+    /// # Panics
     ///
-    /// Reutrns the unique cantor number calculate from the cell coordinates as [`HashIndex`]
-    pub fn key(&self, k1: u32, k2: u32) -> HashIndex<Hx> {
-        (((k1 + k2) * (k1 + k2 + 1)) / 2 + k2).into()
+    /// Panics if the `target_per_cell` is not strictly positive.
+    pub fn retune(&mut self, target_per_cell: F) -> usize
+    where
+        T: Coordinate<Item = F>,
+    {
+        assert!(
+            target_per_cell > F::zero(),
+            "The target number of entities per cell must be positive"
+        );
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("retune", retuned = tracing::field::Empty).entered();
+
+        let max_cells = Self::max_axis_cells();
+
+        let mut retuned = 0;
+        for floor in 0..self.floors() {
+            let grid = &self.grids[floor];
+            if grid.is_empty() {
+                continue;
+            }
+
+            let entities = grid.values().map(|d_list| d_list.len()).sum::<usize>();
+            let average = F::from_usize(entities).unwrap() / F::from_usize(grid.len()).unwrap();
+            let scale = (average / target_per_cell).sqrt();
+
+            let cells = self.floor_cells(floor).map(|c| {
+                (F::from_u32(c).unwrap() * scale)
+                    .round()
+                    .to_u32()
+                    .unwrap_or(u32::MAX)
+                    .clamp(1, max_cells)
+            });
+
+            if cells != self.floor_cells(floor) {
+                self.set_floor_cells(floor, cells);
+                retuned += 1;
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        span.record("retuned", retuned);
+
+        retuned
     }
 
-    /// Cell size defined for cells on x-axis
+    /// Sets the [`CoordinateFrame`] of the entities, chained after the constructor. Entities
+    /// already in the grid are filed again according to the frame.
+    pub fn with_frame(mut self, frame: CoordinateFrame) -> Self
+    where
+        T: Coordinate<Item = F>,
+    {
+        self.frame = frame;
+
+        let entities: Vec<_> = self
+            .grids
+            .iter_mut()
+            .flat_map(|grid| grid.drain())
+            .flat_map(|(_, bucket)| bucket)
+            .collect();
+        for (entity, filing) in entities {
+            let _ = self.refile(entity, filing);
+        }
+
+        self
+    }
+
+    /// Returns the [`CoordinateFrame`] of the grid
+    pub fn frame(&self) -> CoordinateFrame {
+        self.frame
+    }
+
+    /// Coordinates from which the cells are counted, according to the [`Origin`] of the frame
+    pub(crate) fn origin(&self) -> (F, F, F) {
+        match self.frame.origin {
+            Origin::Centre => (F::zero(), F::zero(), F::zero()),
+            Origin::Corner => {
+                let min = self.bounds.min();
+                (min[0], min[1], min[2])
+            }
+        }
+    }
+
+    /// Enables or disables the deterministic mode of the grid.
     ///
-    /// Returns the size in base `float` type being used in the [`HashGrid`]
-    pub fn cell_size_x(&self) -> F {
-        self.params.cell_sizes.x_size
+    /// Queries visit the cells in ascending cell order, never in the iteration order of the
+    /// cell HashMaps, but the entities of a cell are returned in the order they were inserted in.
+    /// Peers of a lockstep simulation inserting the same entities in different orders would
+    /// therefore see different result orders. In the deterministic mode the results of
+    /// [`HashGrid::query`], of the geometry queries such as [`HashGrid::query_geometry`] and of
+    /// the [`SpatialQuery`] implementation are sorted by the [`DataIndex`] of the entities
+    /// before being returned.
+    pub fn set_deterministic(&mut self, deterministic: bool)
+    where
+        T: Entity,
+    {
+        self.deterministic = deterministic.then_some(|a: &T, b: &T| a.id().cmp(&b.id()));
     }
 
-    /// Cell size defined for cells on y-axis
+    /// Whether the results of the queries are sorted by entity id, see
+    /// [`HashGrid::set_deterministic`]
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic.is_some()
+    }
+
+    /// Sorts the `results` by the id of their entities in the deterministic mode, leaving them
+    /// in cell order otherwise
+    fn sort_results<R>(&self, results: &mut [R], entity: fn(&R) -> DataRef<'a, T>) {
+        if let Some(order) = self.deterministic {
+            results.sort_by(|a, b| order(entity(a), entity(b)));
+        }
+    }
+
+    /// Generation of the grid, increased by every insertion, removal and refiling of entities
+    /// made through its methods, and attached to the results of [`HashGrid::query`].
     ///
-    /// Returns the size in base `float` type being used in the [`HashGrid`]
-    pub fn cell_size_y(&self) -> F {
-        self.params.cell_sizes.y_size
+    /// Callers caching the results of a query can compare the generation of the results with
+    /// the current one to tell whether the grid changed since, and only query it again if it
+    /// did.
+    pub fn generation(&self) -> u64 {
+        self.generation
     }
 
-    /// Floor height defined for floors on z-axis
+    /// Returns the cells which changed after the generation `version`, along with the
+    /// generation at which each of them last changed, floor by floor.
     ///
-    /// Returns the size in base `float` type being used in the [`HashGrid`]
-    pub fn floor_size(&self) -> F {
-        self.params.cell_sizes.floor_size
+    /// Replication systems can send only these cells to their clients, passing the
+    /// [`HashGrid::generation`] of the latest sync as the `version`. Cells emptied since are
+    /// returned without any data, so that clients drop them as well. Every cell which ever
+    /// held an entity keeps its version, which costs a small entry per cell.
+    pub fn cells_changed_since(&self, version: u64) -> Vec<(CellRef<'_, 'a, T>, u64)> {
+        let mut cells = Vec::new();
+
+        for (floor, versions) in self.versions.iter().enumerate() {
+            let mut floor_cells = versions
+                .iter()
+                .filter(|&(_, &changed)| changed > version)
+                .map(|(key, &changed)| {
+                    let data = self.grids[floor].get(key).unwrap_or_default();
+                    let coordinates = unpair(key.to_u64().unwrap());
+
+                    (
+                        CellRef {
+                            floor,
+                            coordinates,
+                            data,
+                        },
+                        changed,
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            floor_cells.sort_by_key(|(cell, _)| [cell.coordinates[1], cell.coordinates[0]]);
+            cells.append(&mut floor_cells);
+        }
+
+        cells
     }
 
-    /// Returns the total number of cells along the x-axis
-    pub fn xcells(&self) -> u32 {
-        self.params.cell_per_axis.xcells
+    /// Same as [`HashGrid::query`], only returning the entities sharing at least one layer with
+    /// the `mask`, see [`HashGrid::insert_with_layers`]
+    pub fn query_with_layers<Id>(
+        &self,
+        query: Query<F, Id>,
+        mask: LayerMask,
+    ) -> QueryResult<'a, F, Id, T>
+    where
+        Id: DataIndex,
+        T: Coordinate<Item = F> + Entity<ID = Id>,
+    {
+        self.query_within(query, (0, usize::MAX), Some(mask))
     }
 
-    /// Returns the total number of cells along the y-axis
-    pub fn ycells(&self) -> u32 {
-        self.params.cell_per_axis.ycells
+    pub fn query<Id>(&self, query: Query<F, Id>) -> QueryResult<'a, F, Id, T>
+    where
+        Id: DataIndex,
+        T: Coordinate<Item = F> + Entity<ID = Id>,
+    {
+        self.query_within(query, (0, usize::MAX), None)
     }
 
-    /// Returns the total number of floors along the z-axis
-    pub fn floors(&self) -> usize {
-        self.params.cell_per_axis.floors
+    /// Same as [`HashGrid::query`], only searching the `floors`, such as sounds which should
+    /// not leak across the levels of a building even when the radius reaches them.
+    ///
+    /// Floors outside the range are skipped, whatever the radius of the query.
+    pub fn query_on_floors<Id>(
+        &self,
+        query: Query<F, Id>,
+        floors: RangeInclusive<usize>,
+    ) -> QueryResult<'a, F, Id, T>
+    where
+        Id: DataIndex,
+        T: Coordinate<Item = F> + Entity<ID = Id>,
+    {
+        self.query_within(query, (*floors.start(), *floors.end()), None)
     }
-}
 
-impl<'a, F, T, Hx> fmt::Display for HashGrid<'a, F, T, Hx>
-where
-    F: Float + FromPrimitive + ToPrimitive + Display,
-    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
-{
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "HashGrid \n[\n  Grids: {}\n  ", self.grids.len())?;
-        write!(
-            f,
-            "Grid Parameters \n  [\n    floors: {}\n    floor-size: {}\n    cells-on-x: {}\n    cell-size-x: {}\n    cells-on-y: {}\n    cell-size-y: {}\n  ]\n  ",
-            self.floors(), self.floor_size(), self.xcells(), self.cell_size_x(), self.ycells(), self.cell_size_y()
-        )?;
+    /// Runs the `query` over the floors from the `first` to the `last` one within its radius,
+    /// only returning the entities sharing a layer with the `mask` if any
+    fn query_within<Id>(
+        &self,
+        query: Query<F, Id>,
+        (first, last): (usize, usize),
+        mask: Option<LayerMask>,
+    ) -> QueryResult<'a, F, Id, T>
+    where
+        Id: DataIndex,
+        T: Coordinate<Item = F> + Entity<ID = Id>,
+    {
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("query", results = tracing::field::Empty).entered();
 
-        let center = self.bounds.centre();
-        let size = self.bounds.size();
+        // Query points outside the bounds are wrapped onto the nearest cell like the
+        // entities, when the wrap is enabled
+        let mut coordinates = (query.x(), query.y(), query.z());
+        let status = if self.bounds.is_inside(coordinates) {
+            QueryStatus::InBounds
+        } else if self.wrap {
+            let grid_max_bounds = self.bounds.max();
+            let grid_min_bounds = self.bounds.min();
 
-        write!(
+            coordinates.0 = coordinates
+                .0
+                .min(grid_max_bounds[0])
+                .max(grid_min_bounds[0]);
+            coordinates.1 = coordinates
+                .1
+                .min(grid_max_bounds[1])
+                .max(grid_min_bounds[1]);
+            coordinates.2 = coordinates
+                .2
+                .min(grid_max_bounds[2])
+                .max(grid_min_bounds[2]);
+
+            QueryStatus::Clamped
+        } else {
+            QueryStatus::OutOfBounds
+        };
+
+        // Number of cells spanned by the radius, given in world units, on an axis whose cells
+        // have the `size`. At least the neighbouring cells are always visited
+        let radius_cells = |size: F| {
+            (query.radius() / size)
+                .max(F::one())
+                .ceil()
+                .to_i32()
+                .unwrap_or(i32::MAX)
+        };
+
+        let (_, _, floor) = self.get_cell_coordinates(coordinates);
+        let origin = self.origin();
+
+        let base_floor = floor as i32;
+        let radius_f = radius_cells(self.floor_size());
+
+        // Floors within the radius, narrowed down to the floors searched
+        let floor_index = |f: usize| f.min(i32::MAX as usize) as i32;
+        let range_z = base_floor.saturating_sub(radius_f).max(floor_index(first))
+            ..=base_floor
+                .saturating_add(radius_f)
+                .min(floor_index(last))
+                .min(self.floors() as i32 - 1);
+
+        let relevant_indices = range_z.flat_map(|df| {
+            let df = df as usize;
+            let [xcells, ycells] = self.floor_cells(df);
+            let (size_x, size_y) = self.floor_cell_sizes(df);
+
+            // Points too far out to be counted in cells are left beyond the last cell
+            let base_cx = ((coordinates.0 - origin.0) / size_x)
+                .floor()
+                .abs()
+                .to_i32()
+                .unwrap_or(i32::MAX);
+            let base_cy = ((coordinates.1 - origin.1) / size_y)
+                .floor()
+                .abs()
+                .to_i32()
+                .unwrap_or(i32::MAX);
+            let (radius_x, radius_y) = (radius_cells(size_x), radius_cells(size_y));
+
+            let range_x = base_cx.saturating_sub(radius_x).max(0)
+                ..=base_cx.saturating_add(radius_x).min(xcells as i32);
+            let range_y = base_cy.saturating_sub(radius_y).max(0)
+                ..=base_cy.saturating_add(radius_y).min(ycells as i32);
+
+            range_x
+                .flat_map(move |dx| range_y.clone().map(move |dy| (dx as u32, dy as u32)))
+                .map(move |(dx, dy)| (self.key(dx, dy), df))
+        });
+
+        // Entities are collected along with their filing, so that the result can test them
+        // where they are filed
+        let mut found = Vec::new();
+
+        match query.query_type() {
+            QueryType::Find(id) => {
+                for (hashindex, floor) in relevant_indices {
+                    if let Some(bucket) = self.grids[floor].bucket(&hashindex.key()) {
+                        if let Some((&entity, &filing)) = bucket
+                            .iter()
+                            .find(|(e, filing)| e.id() == id && filing.in_layers(mask))
+                        {
+                            found.push((entity, filing));
+                            break;
+                        }
+                    }
+                }
+            }
+            QueryType::Relevant => {
+                // Entities spanning several floors are found once per floor visited
+                let mut spanning = Spanning::new();
+
+                for (hashindex, floor) in relevant_indices {
+                    if let Some(bucket) = self.grids[floor].bucket(&hashindex.key()) {
+                        found.extend(
+                            bucket
+                                .iter()
+                                .filter(|&(&e, filing)| {
+                                    filing.in_layers(mask) && spanning.first(e, filing)
+                                })
+                                .map(|(&e, &filing)| (e, filing)),
+                        );
+                    }
+                }
+            }
+        }
+
+        // Entities are collected cell by cell, in the order they were inserted in, sorting
+        // them makes the result independent of the insertion order as well
+        self.sort_results(&mut found, |&(e, _)| e);
+
+        #[cfg(feature = "tracing")]
+        span.record("results", found.len());
+
+        let (data, filings) = found.into_iter().unzip();
+        QueryResult {
+            query,
+            status,
+            data,
+            filings,
+            generation: self.generation,
+        }
+    }
+
+    /// Queries the grid for every entity located inside the [`Geometry`].
+    ///
+    /// Only the cells overlapping the bounding box of the geometry are visited, and every
+    /// candidate from those cells is tested against the exact shape before being returned.
+    pub fn query_geometry(&self, geometry: &Geometry<F>) -> Vec<DataRef<'a, T>>
+    where
+        T: Coordinate<Item = F>,
+    {
+        let mut results = Vec::new();
+        self.query_into(geometry, &mut results);
+        results
+    }
+
+    /// Queries the grid for every entity located inside the `include` geometry but outside the
+    /// `exclude` one, such as the ring between two radii.
+    ///
+    /// Only the cells overlapping the `include` geometry are visited, both shapes being tested
+    /// on every candidate in the same pass. Boundaries of the `exclude` geometry are excluded.
+    pub fn query_difference(
+        &self,
+        include: &Geometry<F>,
+        exclude: &Geometry<F>,
+    ) -> Vec<DataRef<'a, T>>
+    where
+        T: Coordinate<Item = F>,
+    {
+        let (min, max) = include.bounding_box();
+
+        // Entities spanning several floors are found once per floor visited
+        let mut spanning = Spanning::new();
+
+        let mut results: Vec<_> = self
+            .cells_in_box(min, max)
+            .filter_map(|(hashindex, floor)| self.grids[floor].bucket(&hashindex.key()))
+            .flat_map(|bucket| bucket.iter())
+            .filter(|&(&e, filing)| {
+                let point = filing.position(e);
+                filing.inside(include, point)
+                    && !filing.inside(exclude, point)
+                    && spanning.first(e, filing)
+            })
+            .map(|(&e, _)| e)
+            .collect();
+
+        self.sort_results(&mut results, |&e| e);
+        results
+    }
+
+    /// Queries the grid for every entity located inside the [`GeometryGroup`], such as the
+    /// entities in one zone or another but not in a third one.
+    ///
+    /// Only the cells around each part of the group are visited, every cell at most once, and
+    /// the whole group is tested on every candidate in the same pass.
+    pub fn query_group(&self, group: &GeometryGroup<F>) -> Vec<DataRef<'a, T>>
+    where
+        T: Coordinate<Item = F>,
+    {
+        let mut cells = group
+            .boxes()
+            .into_iter()
+            .flat_map(|(min, max)| self.cells_in_box(min, max))
+            .map(|(hashindex, floor)| (floor, hashindex.key()))
+            .collect::<Vec<_>>();
+        cells.sort_unstable();
+        cells.dedup();
+
+        // Entities spanning several floors are found once per floor visited
+        let mut spanning = Spanning::new();
+
+        let mut results: Vec<_> = cells
+            .into_iter()
+            .filter_map(|(floor, key)| self.grids[floor].bucket(&key))
+            .flat_map(|bucket| bucket.iter())
+            .filter(|&(&e, filing)| group.contains(filing.position(e)) && spanning.first(e, filing))
+            .map(|(&e, _)| e)
+            .collect();
+
+        self.sort_results(&mut results, |&e| e);
+        results
+    }
+
+    /// Same as [`HashGrid::query_geometry`], only returning the entities sharing at least one
+    /// layer with the `mask`, see [`HashGrid::insert_with_layers`]
+    pub fn query_geometry_with_layers(
+        &self,
+        geometry: &Geometry<F>,
+        mask: LayerMask,
+    ) -> Vec<DataRef<'a, T>>
+    where
+        T: Coordinate<Item = F>,
+    {
+        let mut results = Vec::new();
+        self.query_layers_into(geometry, Some(mask), &mut results);
+        results
+    }
+
+    /// Same as [`HashGrid::query_geometry`], except that an invalid geometry is reported as a
+    /// [`SpatialError::InvalidQuery`] instead of matching nothing
+    pub fn try_query_geometry<Id>(
+        &self,
+        geometry: &Geometry<F>,
+    ) -> Result<Vec<DataRef<'a, T>>, SpatialError<Id>>
+    where
+        Id: DataIndex,
+        T: Coordinate<Item = F> + Entity<ID = Id>,
+    {
+        if !geometry.is_valid() {
+            return Err(SpatialError::InvalidQuery);
+        }
+
+        Ok(self.query_geometry(geometry))
+    }
+
+    /// Queries the grid for the neighbours of the entity matching the `id`, every other entity
+    /// located within the `radius` of its coordinates.
+    ///
+    /// The grid does not keep an index of the entity locations, so every cell is visited until
+    /// the entity is found. Returns nothing if no entity matches the `id`.
+    pub fn query_around<Id>(&self, id: Id, radius: F) -> Vec<DataRef<'a, T>>
+    where
+        Id: DataIndex,
+        T: Coordinate<Item = F> + Entity<ID = Id>,
+    {
+        self.try_query_around(id, radius).unwrap_or_default()
+    }
+
+    /// Same as [`HashGrid::query_around`], except that a missing entity is reported as a
+    /// [`SpatialError::UnknownEntity`]
+    pub fn try_query_around<Id>(
+        &self,
+        id: Id,
+        radius: F,
+    ) -> Result<Vec<DataRef<'a, T>>, SpatialError<Id>>
+    where
+        Id: DataIndex,
+        T: Coordinate<Item = F> + Entity<ID = Id>,
+    {
+        let (entity, filing) = self
+            .grids
+            .iter()
+            .flat_map(|grid| grid.values())
+            .flat_map(|bucket| bucket.iter())
+            .find(|(e, _)| e.id() == id)
+            .ok_or(SpatialError::UnknownEntity(id))?;
+
+        let mut neighbours =
+            self.query_geometry(&Geometry::radius(filing.position(entity), radius));
+        neighbours.retain(|e| e.id() != id);
+
+        Ok(neighbours)
+    }
+
+    /// Queries the grid for every entity whose extent overlaps the [`Geometry`].
+    ///
+    /// Entities are filed by their coordinates, so the cells within `reach` of the geometry
+    /// bounding box are visited as well. `reach` must be at least the largest distance the
+    /// extent of an entity spans from its coordinates, entities reaching further may be missed.
+    pub fn query_extents(&self, geometry: &Geometry<F>, reach: F) -> Vec<DataRef<'a, T>>
+    where
+        T: ExtentEntity<Item = F>,
+    {
+        let (min, max) = geometry.bounding_box();
+        let min = (min.0 - reach, min.1 - reach, min.2 - reach);
+        let max = (max.0 + reach, max.1 + reach, max.2 + reach);
+
+        // Entities spanning several floors are found once per floor visited
+        let mut spanning = Spanning::new();
+
+        let mut results = Vec::new();
+        for (hashindex, floor) in self.cells_in_box(min, max) {
+            if let Some(bucket) = self.grids[floor].bucket(&hashindex.key()) {
+                results.extend(
+                    bucket
+                        .iter()
+                        .filter(|&(&e, filing)| {
+                            filing.overlaps(geometry, e) && spanning.first(e, filing)
+                        })
+                        .map(|(&e, _)| e),
+                );
+            }
+        }
+
+        self.sort_results(&mut results, |&e| e);
+        results
+    }
+
+    /// Queries the grid for every entity whose extent overlaps the `rect`, along with the
+    /// fraction of the extent covered by the rect, in the range `(0, 1]`. Used for partial
+    /// visibility, or damage scaled by the exposure of the entities.
+    ///
+    /// Extents are measured through their bounding box, and so is the `rect` if it is any
+    /// other shape. Axes along which either of them is flat do not weigh in on the fraction. See
+    /// [`HashGrid::query_extents`] for the meaning of `reach`.
+    pub fn query_with_overlap(&self, rect: &Geometry<F>, reach: F) -> Vec<(DataRef<'a, T>, F)>
+    where
+        T: ExtentEntity<Item = F>,
+    {
+        let (min, max) = rect.bounding_box();
+
+        self.query_extents(rect, reach)
+            .into_iter()
+            .map(|e| {
+                let (e_min, e_max) = e.extent().bounding_box();
+                let fraction = axis_overlap(e_min.0, e_max.0, min.0, max.0)
+                    * axis_overlap(e_min.1, e_max.1, min.1, max.1)
+                    * axis_overlap(e_min.2, e_max.2, min.2, max.2);
+
+                (e, fraction)
+            })
+            .filter(|&(_, fraction)| fraction > F::zero())
+            .collect()
+    }
+
+    /// Same as [`HashGrid::query_geometry`], except that the entities are written into the
+    /// `results` buffer, which is cleared first.
+    ///
+    /// The buffer keeps its capacity, so repeated queries reusing it do not allocate.
+    pub fn query_into(&self, geometry: &Geometry<F>, results: &mut Vec<DataRef<'a, T>>)
+    where
+        T: Coordinate<Item = F>,
+    {
+        self.query_layers_into(geometry, None, results)
+    }
+
+    /// Writes the entities located inside the [`Geometry`] into the `results`, only keeping
+    /// the ones sharing a layer with the `mask` if any
+    fn query_layers_into(
+        &self,
+        geometry: &Geometry<F>,
+        mask: Option<LayerMask>,
+        results: &mut Vec<DataRef<'a, T>>,
+    ) where
+        T: Coordinate<Item = F>,
+    {
+        let (min, max) = geometry.bounding_box();
+
+        results.clear();
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("query", results = tracing::field::Empty).entered();
+
+        #[cfg(feature = "stats")]
+        let mut stats = QueryStats::default();
+
+        // Entities spanning several floors are found once per floor visited
+        let mut spanning = Spanning::new();
+
+        for (hashindex, floor) in self.cells_in_box(min, max) {
+            #[cfg(feature = "stats")]
+            {
+                stats.cells_visited += 1;
+            }
+
+            if let Some(bucket) = self.grids[floor].bucket(&hashindex.key()) {
+                #[cfg(feature = "stats")]
+                {
+                    stats.cells_occupied += 1;
+                    stats.candidates_tested += bucket.len();
+                }
+
+                if !self.spanning && !self.relocated && mask.is_none() {
+                    #[cfg(feature = "simd")]
+                    crate::geometry::extend_contained(geometry, bucket.items(), results);
+
+                    #[cfg(not(feature = "simd"))]
+                    results.extend(
+                        bucket
+                            .items()
+                            .iter()
+                            .filter(|e| geometry.contains((e.x(), e.y(), e.z())))
+                            .copied(),
+                    );
+                } else {
+                    results.extend(
+                        bucket
+                            .iter()
+                            .filter(|&(&e, filing)| {
+                                filing.in_layers(mask)
+                                    && filing.inside(geometry, filing.position(e))
+                                    && spanning.first(e, filing)
+                            })
+                            .map(|(&e, _)| e),
+                    );
+                }
+            }
+        }
+
+        self.sort_results(results, |&e| e);
+
+        #[cfg(feature = "stats")]
+        {
+            stats.results = results.len();
+            self.last_stats.set(stats);
+        }
+
+        #[cfg(feature = "tracing")]
+        span.record("results", results.len());
+    }
+
+    /// Same as [`HashGrid::query_geometry`], except that the query stops after visiting
+    /// `max_cells` cells, so pathological queries cannot stall a frame.
+    ///
+    /// Returns the entities found within the visited cells, along with `true` if every cell
+    /// touched by the geometry was visited and the results are complete.
+    pub fn query_budgeted(
+        &self,
+        geometry: &Geometry<F>,
+        max_cells: usize,
+    ) -> (Vec<DataRef<'a, T>>, bool)
+    where
+        T: Coordinate<Item = F>,
+    {
+        let (min, max) = geometry.bounding_box();
+        let mut cells = self.cells_in_box(min, max);
+
+        // Entities spanning several floors are found once per floor visited
+        let mut spanning = Spanning::new();
+
+        let mut results = Vec::new();
+        for (hashindex, floor) in cells.by_ref().take(max_cells) {
+            if let Some(bucket) = self.grids[floor].bucket(&hashindex.key()) {
+                results.extend(
+                    bucket
+                        .iter()
+                        .filter(|&(&e, filing)| {
+                            filing.inside(geometry, filing.position(e)) && spanning.first(e, filing)
+                        })
+                        .map(|(&e, _)| e),
+                );
+            }
+        }
+
+        self.sort_results(&mut results, |&e| e);
+        (results, cells.next().is_none())
+    }
+
+    /// Same as [`HashGrid::query_geometry`], along with the position each entity is filed at
+    pub(crate) fn query_positioned(
+        &self,
+        geometry: &Geometry<F>,
+    ) -> Vec<Positioned<DataRef<'a, T>, F>>
+    where
+        T: Coordinate<Item = F>,
+    {
+        let mut results = Vec::new();
+        self.query_positioned_into(geometry, &mut results);
+        results
+    }
+
+    /// Same as [`HashGrid::query_positioned`], except that the entities are appended to the
+    /// `results` buffer
+    pub(crate) fn query_positioned_into(
+        &self,
+        geometry: &Geometry<F>,
+        results: &mut Vec<Positioned<DataRef<'a, T>, F>>,
+    ) where
+        T: Coordinate<Item = F>,
+    {
+        let (min, max) = geometry.bounding_box();
+
+        // Entities spanning several floors are found once per floor visited
+        let mut spanning = Spanning::new();
+
+        let start = results.len();
+        results.extend(
+            self.cells_in_box(min, max)
+                .filter_map(|(hashindex, floor)| self.grids[floor].bucket(&hashindex.key()))
+                .flat_map(|bucket| bucket.iter())
+                .filter(|&(&e, filing)| {
+                    filing.inside(geometry, filing.position(e)) && spanning.first(e, filing)
+                })
+                .map(|(&e, filing)| (e, filing.position(e))),
+        );
+
+        self.sort_results(&mut results[start..], |&(e, _)| e);
+    }
+
+    /// Returns the counters collected by the latest geometry query on the grid
+    #[cfg(feature = "stats")]
+    pub fn last_query_stats(&self) -> QueryStats {
+        self.last_stats.get()
+    }
+
+    /// Queries the grid for every entity within the outermost ring of the relevance `profile`
+    /// around the `point`.
+    ///
+    /// The entities are bucketed by [`Priority`](crate::relevance::Priority) and scored
+    /// according to the profile falloff.
+    pub fn query_prioritized(
+        &self,
+        point: (F, F, F),
+        profile: &RelevanceProfile<F>,
+    ) -> Prioritized<DataRef<'a, T>, F>
+    where
+        T: Coordinate<Item = F>,
+    {
+        let candidates = self.query_positioned(&Geometry::radius(point, profile.radius()));
+
+        Prioritized::from_distances(
+            profile,
+            candidates
+                .into_iter()
+                .map(|(e, position)| (e, distance(point, position))),
+        )
+    }
+
+    /// Inserts the references to individual data from the list of data into the relevant cells of the grid by finding
+    /// unique [`HashIndex`] through cell coordinates. These cell coordinates are based on the
+    /// data of type [`Entity`] individual spatial coordinates.
+    ///
+    /// This methods takes the reference list of data of any type which must implements the following traits:
+    ///
+    /// * [`Entity`]: This trait imposes the data to have a unique id which can be used to find an entity in the grid
+    /// * [`Coordinate`]: This trait imposes the type to return the spatial coordinates `x, y, z`.
+    ///
+    /// Every `entity` or data of type `Entity` is then inserted into the belonging cell using
+    /// the unique `HashIndex`.
+    pub fn update(&mut self, data: &'a [T])
+    where
+        T: Coordinate<Item = F>,
+    {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("update", entities = data.len()).entered();
+
+        for entity in data.iter() {
+            self.insert(entity);
+        }
+    }
+
+    /// Iterates over every entity of the grid along with its coordinates, floor by floor and
+    /// in no particular order within a floor, so the world state can be snapshotted without
+    /// keeping a separate list of the entities.
+    pub fn iter_all(&self) -> impl Iterator<Item = (DataRef<'a, T>, (F, F, F))> + '_
+    where
+        T: Coordinate<Item = F>,
+    {
+        let mut spanning = Spanning::new();
+
+        self.grids
+            .iter()
+            .flat_map(|grid| grid.values())
+            .flat_map(|bucket| bucket.iter())
+            .filter(move |&(&entity, filing)| spanning.first(entity, filing))
+            .map(|(&entity, filing)| (entity, filing.position(entity)))
+    }
+
+    /// Collects every entity of the grid along with its coordinates, see [`HashGrid::iter_all`]
+    pub fn to_vec(&self) -> Vec<Positioned<DataRef<'a, T>, F>>
+    where
+        T: Coordinate<Item = F>,
+    {
+        self.iter_all().collect()
+    }
+
+    /// Removes the data matching the `id` from the grid.
+    ///
+    /// The grid does not keep an index of the entity locations, so every cell is visited
+    /// until the entity is found. Cells left empty after the removal are dropped from the grid.
+    ///
+    /// Returns `true` if the entity was found and removed.
+    pub fn remove<Id>(&mut self, id: Id) -> bool
+    where
+        Id: DataIndex,
+        T: Entity<ID = Id>,
+    {
+        self.try_remove(id).is_ok()
+    }
+
+    /// Same as [`HashGrid::remove`], except that the removed reference is returned, or a
+    /// [`SpatialError::UnknownEntity`] if the entity is not in the grid
+    pub fn try_remove<Id>(&mut self, id: Id) -> Result<DataRef<'a, T>, SpatialError<Id>>
+    where
+        Id: DataIndex,
+        T: Entity<ID = Id>,
+    {
+        let (entity, _) = self.take(id).ok_or(SpatialError::UnknownEntity(id))?;
+
+        Ok(entity)
+    }
+
+    /// Moves the data matching the `id` into the cell which contains the `coordinates`.
+    ///
+    /// Since the grid only holds immutable references to the data, the entity is filed under
+    /// the given coordinates rather than the ones it reports through [`Coordinate`]. Queries
+    /// and refilings then use these coordinates for the entity until it is relocated again or
+    /// removed. If the destination lies outside the grid bounds while `wrap` is disabled, the
+    /// entity is left untouched. Entities inserted with [`HashGrid::insert_spanning`] keep their vertical
+    /// span, only moving along the x and y axis.
+    ///
+    /// Returns `true` if the entity was found and relocated.
+    pub fn relocate<Id>(&mut self, id: Id, coordinates: (F, F, F)) -> bool
+    where
+        Id: DataIndex,
+        T: Entity<ID = Id>,
+    {
+        self.try_relocate(id, coordinates).is_ok()
+    }
+
+    /// Same as [`HashGrid::relocate`], except that the reason for not relocating the entity is
+    /// reported as a [`SpatialError`]
+    pub fn try_relocate<Id>(
+        &mut self,
+        id: Id,
+        coordinates: (F, F, F),
+    ) -> Result<(), SpatialError<Id>>
+    where
+        Id: DataIndex,
+        T: Entity<ID = Id>,
+    {
+        let target = self.resolve_cell(coordinates)?;
+
+        // Taking the entity out directly, so it keeps its layers
+        let taken = self.take(id).ok_or(SpatialError::UnknownEntity(id))?;
+        self.place_relocated(taken, coordinates, target)
+    }
+
+    /// Same as [`HashGrid::try_relocate`], except that the entity is moved out of this grid
+    /// into the `grid`, e.g. across the border of two shards
+    pub(crate) fn try_relocate_into<Id>(
+        &mut self,
+        id: Id,
+        coordinates: (F, F, F),
+        grid: &mut Self,
+    ) -> Result<(), SpatialError<Id>>
+    where
+        Id: DataIndex,
+        T: Entity<ID = Id>,
+    {
+        let target = grid.resolve_cell(coordinates)?;
+
+        let taken = self.take(id).ok_or(SpatialError::UnknownEntity(id))?;
+        grid.place_relocated(taken, coordinates, target)
+    }
+
+    /// Files the entity taken out of a grid at the `coordinates` it is relocated to, which
+    /// resolved into the `target` cell
+    fn place_relocated<Id>(
+        &mut self,
+        (entity, filing): (DataRef<'a, T>, Filing<F>),
+        coordinates: (F, F, F),
+        (hashindex, floor): (HashIndex<Hx>, usize),
+    ) -> Result<(), SpatialError<Id>> {
+        let filing = Filing {
+            position: Some(coordinates),
+            ..filing
+        };
+        self.relocated = true;
+
+        match filing.span {
+            Some(span) => {
+                self.place_spanning(entity, (coordinates.0, coordinates.1), span, filing)?
+            }
+            None => self.place(hashindex, floor, entity, filing),
+        }
+
+        Ok(())
+    }
+
+    /// Refiles the entities held by every cell overlapping the `region` according to the
+    /// coordinates they currently report through [`Coordinate`].
+    ///
+    /// This is meant for data whose coordinates change behind the shared references, through
+    /// interior mutability, many at a time within one area. The cells outside the region are
+    /// left untouched, and entities moved with [`HashGrid::relocate`] stay at the coordinates
+    /// they were relocated to. Entities which can no longer be inserted, being out of bounds
+    /// with `wrap` disabled or having non-finite coordinates, are dropped from the grid.
+    ///
+    /// Returns the number of entities refiled into the grid.
+    pub fn rebuild_region(&mut self, region: &Geometry<F>) -> usize
+    where
+        T: Coordinate<Item = F>,
+    {
+        #[cfg(feature = "tracing")]
+        let span =
+            tracing::debug_span!("rebuild_region", refiled = tracing::field::Empty).entered();
+
+        let (min, max) = region.bounding_box();
+        let cells: Vec<_> = self.cells_in_box(min, max).collect();
+
+        // Emptying all the cells first, so that entities refiled into a cell of the
+        // region are not taken out again
+        let mut entities = Vec::new();
+        let generation = self.generation.wrapping_add(1);
+        for (hashindex, floor) in cells {
+            if let Some(bucket) = self.grids[floor].remove(&hashindex.key()) {
+                self.versions[floor].insert(hashindex.key(), generation);
+                entities.extend(bucket);
+            }
+        }
+
+        if !entities.is_empty() {
+            self.bump_generation();
+        }
+
+        // Entities spanning several floors of the region are refiled once
+        let mut spanning = Spanning::new();
+        entities.retain(|(e, filing)| spanning.first(e, filing));
+
+        let mut refiled = 0;
+        for (entity, filing) in entities {
+            if self.refile(entity, filing).is_ok() {
+                refiled += 1;
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        span.record("refiled", refiled);
+
+        refiled
+    }
+
+    /// Resolves the cell hash and the floor index for the given coordinates, wrapping them
+    /// around the nearest cell if they are outside the grid bounds and wrap is enabled.
+    ///
+    /// Fails if the coordinates are not finite, or if they are outside the bounds and wrap
+    /// is disabled.
+    pub(super) fn resolve_cell<Id>(
+        &self,
+        mut coordinates: (F, F, F),
+    ) -> Result<(HashIndex<Hx>, usize), SpatialError<Id>> {
+        let as_f64 = |c: (F, F, F)| {
+            [
+                c.0.to_f64().unwrap_or(f64::NAN),
+                c.1.to_f64().unwrap_or(f64::NAN),
+                c.2.to_f64().unwrap_or(f64::NAN),
+            ]
+        };
+
+        // Non-finite coordinates cannot be mapped onto any cell
+        if !(coordinates.0.is_finite() && coordinates.1.is_finite() && coordinates.2.is_finite()) {
+            return Err(SpatialError::InvalidCoordinates(as_f64(coordinates)));
+        }
+
+        // Validating if the point is within the grid bounds
+        if !self.bounds.is_inside(coordinates) {
+            if !self.wrap {
+                return Err(SpatialError::OutOfBounds(as_f64(coordinates)));
+            }
+
+            // Getting the grid's extreme boundary parameters to apply the boundary
+            // limits to the calculated cell cords
+            let grid_max_bounds = self.bounds.max();
+            let grid_min_bounds = self.bounds.min();
+
+            coordinates.0 = coordinates
+                .0
+                .min(grid_max_bounds[0])
+                .max(grid_min_bounds[0]);
+            coordinates.1 = coordinates
+                .1
+                .min(grid_max_bounds[1])
+                .max(grid_min_bounds[1]);
+            coordinates.2 = coordinates
+                .2
+                .min(grid_max_bounds[2])
+                .max(grid_min_bounds[2]);
+        }
+
+        // Resulting cell coordinates x, y and floor index
+        let (cx, cy, floor) = self.get_cell_coordinates(coordinates);
+
+        // Calculating the unique hash index from the cell coordinates to find the cell
+        // for the entity
+        Ok((self.key(cx, cy), floor))
+    }
+
+    /// Pushes the entity along with its `filing` into the cell identified by the hash index at
+    /// the given floor
+    fn place(
+        &mut self,
+        hashindex: HashIndex<Hx>,
+        floor: usize,
+        entity: DataRef<'a, T>,
+        filing: Filing<F>,
+    ) {
+        self.bump_generation();
+        self.versions[floor].insert(hashindex.key(), self.generation);
+
+        self.grids[floor].push(hashindex.key(), entity, filing);
+    }
+
+    /// Changes the resolution of the `floor`, filing its entities again
+    fn set_floor_cells(&mut self, floor: usize, cells: [u32; 2])
+    where
+        T: Coordinate<Item = F>,
+    {
+        let blocked = self.blocked_areas(floor);
+        self.resolutions[floor] = cells;
+        self.reblock(floor, blocked);
+        self.bump_generation();
+
+        // Cells of the previous resolution are reported as emptied
+        let generation = self.generation;
+        let mut entities = Vec::new();
+        for (key, bucket) in self.grids[floor].drain() {
+            self.versions[floor].insert(key, generation);
+            entities.extend(bucket);
+        }
+
+        // Dense cells are sized after the resolution of the floor
+        let max_key = self.max_key(floor);
+        self.grids[floor].reserve_slots(max_key);
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("rehash", floor, entities = entities.len()).entered();
+
+        for (entity, filing) in entities {
+            let _ = self.refile(entity, filing);
+        }
+    }
+
+    /// Moves the `entity` filed with the `filing` in the cell `key` of the `floor` to the
+    /// `coordinates`, resolved beforehand into the `target` cell, keeping its layers, TTL and span
+    pub(super) fn shift<Id>(
+        &mut self,
+        (entity, filing): (DataRef<'a, T>, Filing<F>),
+        (floor, key): (usize, Hx),
+        coordinates: (F, F, F),
+        target: (HashIndex<Hx>, usize),
+    ) -> Result<(), SpatialError<Id>> {
+        let filing = Filing {
+            position: Some(coordinates),
+            ..filing
+        };
+        self.relocated = true;
+
+        match filing.span {
+            Some(span) => {
+                self.unfile(entity);
+                self.place_spanning(entity, (coordinates.0, coordinates.1), span, filing)
+            }
+            None => {
+                self.grids[floor].retain_in(&key, |&e, _| !ptr::eq(e, entity));
+
+                self.bump_generation();
+                self.versions[floor].insert(key, self.generation);
+                self.place(target.0, target.1, entity, filing);
+                Ok(())
+            }
+        }
+    }
+
+    /// Files the entity into the cell of the `position` on every floor reached by the `span`,
+    /// recording the span in its `filing` for the queries
+    fn place_spanning<Id>(
+        &mut self,
+        entity: DataRef<'a, T>,
+        position: (F, F),
+        span: (F, F),
+        filing: Filing<F>,
+    ) -> Result<(), SpatialError<Id>> {
+        let (z_min, z_max) = (span.0.min(span.1), span.0.max(span.1));
+        let filing = Filing {
+            span: Some((z_min, z_max)),
+            ..filing
+        };
+
+        let (_, lowest) = self.resolve_cell((position.0, position.1, z_min))?;
+        let (_, highest) = self.resolve_cell((position.0, position.1, z_max))?;
+
+        // Resolving each floor from a height in its middle, kept within the bounds
+        let half = F::from_f64(0.5).unwrap();
+        let (z_bottom, z_top) = (self.bounds.min()[2], self.bounds.max()[2]);
+        let z_origin = self.origin().2;
+
+        for floor in lowest..=highest {
+            let z = z_origin + (F::from_usize(floor).unwrap() + half) * self.floor_size();
+            let z = z.min(z_top).max(z_bottom);
+
+            let (hashindex, resolved) = self.resolve_cell((position.0, position.1, z))?;
+            if resolved == floor {
+                self.place(hashindex, floor, entity, filing);
+            }
+        }
+        self.spanning = true;
+
+        Ok(())
+    }
+
+    /// Files the entity again at the position of its `filing`, across its vertical span if it
+    /// was inserted with [`HashGrid::insert_spanning`]
+    fn refile(&mut self, entity: DataRef<'a, T>, filing: Filing<F>) -> Result<(), SpatialError>
+    where
+        T: Coordinate<Item = F>,
+    {
+        let position = filing.position(entity);
+
+        match filing.span {
+            Some(span) => {
+                // Dropping the copies left on the other floors first
+                self.unfile(entity);
+                self.place_spanning(entity, (position.0, position.1), span, filing)
+            }
+            None => {
+                let (hashindex, floor) = self.resolve_cell(position)?;
+                self.place(hashindex, floor, entity, filing);
+                Ok(())
+            }
+        }
+    }
+
+    /// Takes every copy of the reference to the `entity` out of the cells, dropping the cells
+    /// left empty
+    fn unfile(&mut self, entity: DataRef<'a, T>) {
+        self.bump_generation();
+        let generation = self.generation;
+
+        for (grid, versions) in self.grids.iter_mut().zip(self.versions.iter_mut()) {
+            grid.retain(|&key, bucket| {
+                let before = bucket.len();
+                bucket.retain(|&e, _| !ptr::eq(e, entity));
+
+                if bucket.len() != before {
+                    versions.insert(key, generation);
+                }
+                !bucket.is_empty()
+            });
+        }
+    }
+
+    /// Empty cells of the `floor` kept in the `storage`
+    fn empty_cells(
+        &self,
+        floor: usize,
+        storage: CellStorage,
+    ) -> Cells<Hx, DataRef<'a, T>, Filing<F>, S>
+    where
+        S: Clone,
+    {
+        let hasher = self.versions[floor].hasher().clone();
+
+        match storage {
+            CellStorage::Sparse => Cells::sparse(hasher),
+            CellStorage::Dense => Cells::dense(self.max_key(floor), hasher),
+        }
+    }
+
+    /// Largest hash index of the cells of the `floor` within the bounds
+    fn max_key(&self, floor: usize) -> Hx {
+        let [x, y] = self.floor_cells(floor);
+        self.key(x, y).key()
+    }
+
+    /// Largest number of cells along an axis for which every hash index of a floor fits in
+    /// the `u32` pairing of [`HashGrid::key`] and in `Hx`
+    fn max_axis_cells() -> u32 {
+        let max_hx = Hx::max_value().to_u64().unwrap_or(u64::MAX);
+
+        // The largest hash index of a floor of n by n cells is key(n, n), whose pairing
+        // multiplies 2n by 2n + 1 before halving it
+        let fits = |n: u64| {
+            let product = 2 * n * (2 * n + 1);
+            product <= u32::MAX as u64 && product / 2 + n <= max_hx
+        };
+        let mut n = (max_hx.min(u32::MAX as u64) as f64).sqrt() as u64;
+        while n > 1 && !fits(n) {
+            n -= 1;
+        }
+
+        n as u32
+    }
+
+    /// Counts a mutation of the cells, see [`HashGrid::generation`]
+    fn bump_generation(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// Iterates over the entities moved with [`HashGrid::relocate`] along with the coordinates
+    /// they were relocated to, entities spanning several floors once per floor
+    pub(crate) fn relocations(&self) -> impl Iterator<Item = (DataRef<'a, T>, (F, F, F))> + '_ {
+        self.grids
+            .iter()
+            .flat_map(|grid| grid.values())
+            .flat_map(|bucket| bucket.iter())
+            .filter_map(|(&entity, filing)| Some((entity, filing.position?)))
+    }
+
+    /// Fails with [`SpatialError::DuplicateId`] if an entity matching the `id` is in the grid
+    pub(crate) fn reject_duplicate<Id>(&self, id: Id) -> Result<(), SpatialError<Id>>
+    where
+        Id: DataIndex,
+        T: Entity<ID = Id>,
+    {
+        match self.holds(id) {
+            true => Err(SpatialError::DuplicateId(id)),
+            false => Ok(()),
+        }
+    }
+
+    /// Tests whether an entity matching the `id` is in the grid, visiting every cell
+    pub(crate) fn holds<Id>(&self, id: Id) -> bool
+    where
+        Id: DataIndex,
+        T: Entity<ID = Id>,
+    {
+        self.grids
+            .iter()
+            .flat_map(|grid| grid.values())
+            .any(|bucket| bucket.items().iter().any(|e| e.id() == id))
+    }
+
+    /// Takes the entity matching the `id` out of its cells along with its filing, dropping
+    /// the cells left empty
+    fn take<Id>(&mut self, id: Id) -> Option<(DataRef<'a, T>, Filing<F>)>
+    where
+        Id: DataIndex,
+        T: Entity<ID = Id>,
+    {
+        for (floor, grid) in self.grids.iter_mut().enumerate() {
+            if let Some((key, entity, filing)) = grid.take_first(|d, _| d.id() == id) {
+                self.generation = self.generation.wrapping_add(1);
+                self.versions[floor].insert(key, self.generation);
+
+                // Entities spanning several floors are taken out of each of them
+                if filing.span.is_some() {
+                    self.unfile(entity);
+                }
+                return Some((entity, filing));
+            }
+        }
+
+        None
+    }
+
+    /// Iterates over the hash index and floor of every cell overlapping the axis aligned box
+    /// spanning from the `min` to the `max` corner.
+    ///
+    /// The box is clamped into the grid bounds first, the same way entities are wrapped on
+    /// insertion, so that cells holding wrapped entities are visited as well.
+    pub(crate) fn cells_in_box(
+        &self,
+        min: (F, F, F),
+        max: (F, F, F),
+    ) -> impl Iterator<Item = (HashIndex<Hx>, usize)> + '_ {
+        let grid_max_bounds = self.bounds.max();
+        let grid_min_bounds = self.bounds.min();
+
+        let clamp = |v: F, axis: usize| v.min(grid_max_bounds[axis]).max(grid_min_bounds[axis]);
+
+        // Cells are counted from the origin of the frame
+        let origin = self.origin();
+        let (min_x, max_x) = (clamp(min.0, 0) - origin.0, clamp(max.0, 0) - origin.0);
+        let (min_y, max_y) = (clamp(min.1, 1) - origin.1, clamp(max.1, 1) - origin.1);
+
+        // Floors are indexed from zero upwards, anything below or above the
+        // grid is mapped to the lowest or highest floor
+        let last_floor = self.floors() - 1;
+        let floor_of = |z: F| {
+            ((clamp(z, 2) - origin.2) / self.floor_size())
+                .floor()
+                .to_usize()
+                .unwrap_or(0)
+                .min(last_floor)
+        };
+        let range_z = floor_of(min.2)..=floor_of(max.2);
+
+        // Each floor has its own resolution, so the cell ranges are resolved per floor
+        range_z.flat_map(move |floor| {
+            let (size_x, size_y) = self.floor_cell_sizes(floor);
+            let range_x = axis_cells(min_x, max_x, size_x);
+            let range_y = axis_cells(min_y, max_y, size_y);
+
+            range_x
+                .flat_map(move |cx| range_y.clone().map(move |cy| (cx, cy)))
+                .map(move |(cx, cy)| (self.key(cx, cy), floor))
+        })
+    }
+
+    /// Calculates the cells coordinates from the entity coordinates to find the cell
+    /// location inside the grid.
+    ///
+    /// Heights below the first floor or above the last one are mapped to the nearest floor,
+    /// and cell coordinates which do not fit in a `u32` saturate, so that any point gives a
+    /// cell. Use [`HashGrid::try_insert`] to have the coordinates validated.
+    ///
+    /// Reutrns the `Floor` number, `x` and `y` components of the cell in search.
+    pub fn get_cell_coordinates(&self, coordinates: (F, F, F)) -> (u32, u32, usize) {
+        // Destructuring the entity coordinates into x, y, z components, relative to the
+        // origin of the frame
+        let origin = self.origin();
+        let (x, y, z) = (
+            coordinates.0 - origin.0,
+            coordinates.1 - origin.1,
+            coordinates.2 - origin.2,
+        );
+
+        // Getting the floor index from the z component, heights below the origin of the
+        // frame falling onto the first floor
+        let floor = (z / self.floor_size())
+            .floor()
+            .to_usize()
+            .unwrap_or(0)
+            .min(self.floors() - 1);
+
+        // Normalizing the x and y component according to the cell size of the floor to
+        // find the cell coordinates inside the grid
+        let (size_x, size_y) = self.floor_cell_sizes(floor);
+        let cell = |v: F, size: F| (v / size).floor().abs().to_u32().unwrap_or(u32::MAX);
+        let (cx, cy) = (cell(x, size_x), cell(y, size_y));
+
+        (cx, cy, floor)
+    }
+
+    /// Calculates the unique hash of a specefic cell in the [`HashGrid`] to retreive or
+    /// insert the entity or data of type [`Entity`]. It calculate the unique hash id through
+    /// cantor pairing formula which uses the cell coordinates x and y as the `k1` and `k2`
+    /// components and z componenet to determine on which `floor` to look for the data.
+    ///
+    /// __Cantor pairing formula__:
+    ///
+    /// `((k1 + k2) * (k1 + k2 + 1)) / 2 + k2`
+    ///
+    /// Reutrns the unique cantor number calculate from the cell coordinates as [`HashIndex`]
+    pub fn key(&self, k1: u32, k2: u32) -> HashIndex<Hx> {
+        (((k1 + k2) * (k1 + k2 + 1)) / 2 + k2).into()
+    }
+
+    /// Cell size defined for cells on x-axis
+    ///
+    /// Returns the size in base `float` type being used in the [`HashGrid`]
+    pub fn cell_size_x(&self) -> F {
+        self.params.cell_sizes.x_size
+    }
+
+    /// Cell size defined for cells on y-axis
+    ///
+    /// Returns the size in base `float` type being used in the [`HashGrid`]
+    pub fn cell_size_y(&self) -> F {
+        self.params.cell_sizes.y_size
+    }
+
+    /// Floor height defined for floors on z-axis
+    ///
+    /// Returns the size in base `float` type being used in the [`HashGrid`]
+    pub fn floor_size(&self) -> F {
+        self.params.cell_sizes.floor_size
+    }
+
+    /// Returns the total number of cells along the x-axis
+    pub fn xcells(&self) -> u32 {
+        self.params.cell_per_axis.xcells
+    }
+
+    /// Returns the total number of cells along the y-axis
+    pub fn ycells(&self) -> u32 {
+        self.params.cell_per_axis.ycells
+    }
+
+    /// Returns the total number of floors along the z-axis
+    pub fn floors(&self) -> usize {
+        self.params.cell_per_axis.floors
+    }
+
+    /// Returns the number of cells along the x and y axis of the `floor`, which is the
+    /// resolution of the grid unless configured through [`HashGrid::with_floor_cells`]
+    pub fn floor_cells(&self, floor: usize) -> [u32; 2] {
+        self.resolutions
+            .get(floor)
+            .copied()
+            .unwrap_or([self.xcells(), self.ycells()])
+    }
+
+    /// Returns the cell sizes along the x and y axis of the `floor`
+    pub fn floor_cell_sizes(&self, floor: usize) -> (F, F) {
+        let [xcells, ycells] = self.floor_cells(floor);
+        let size = self.bounds.size();
+
+        (
+            size[0] / F::from_u32(xcells).unwrap(),
+            size[1] / F::from_u32(ycells).unwrap(),
+        )
+    }
+
+    /// Reports the memory used by the grid, see [`MemoryFootprint`] for the breakdown.
+    ///
+    /// The data referenced by the grid is owned by the caller and is not accounted for.
+    pub fn memory_footprint(&self) -> MemoryFootprint {
+        let structure = size_of::<Self>()
+            + self.grids.capacity() * size_of::<Cells<Hx, DataRef<'a, T>, Filing<F>, S>>()
+            + self.versions.capacity() * size_of::<Grid<Hx, u64, S>>()
+            + self.blocked.capacity() * size_of::<BlockMask>()
+            + self.blocked.iter().map(|mask| mask.bytes()).sum::<usize>();
+
+        let buckets = self
+            .grids
+            .iter()
+            .map(|grid| grid.bucket_bytes())
+            .sum::<usize>()
+            + self
+                .versions
+                .iter()
+                .map(|versions| hash_table_bytes::<(Hx, u64)>(versions.capacity()))
+                .sum::<usize>();
+
+        let items = self
+            .grids
+            .iter()
+            .flat_map(|grid| grid.values())
+            .map(|bucket| bucket.bytes())
+            .sum();
+
+        MemoryFootprint {
+            structure,
+            buckets,
+            items,
+        }
+    }
+}
+
+impl<'a, F, T, Hx, S> SpatialInsertion for HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+    T: Coordinate<Item = F>,
+{
+    type Item = DataRef<'a, T>;
+
+    fn insert(&mut self, item: Self::Item) {
+        HashGrid::insert(self, item)
+    }
+}
+
+impl<'a, F, T, Hx, S> SpatialQuery for HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+    T: Coordinate<Item = F>,
+{
+    type Item = DataRef<'a, T>;
+    type Scalar = F;
+    type Results = vec::IntoIter<DataRef<'a, T>>;
+
+    fn query(&self, geometry: &Geometry<F>) -> Self::Results {
+        self.query_geometry(geometry).into_iter()
+    }
+
+    fn query_into(&self, geometry: &Geometry<F>, results: &mut Vec<Self::Item>) {
+        HashGrid::query_into(self, geometry, results)
+    }
+
+    fn query_positioned_into(
+        &self,
+        geometry: &Geometry<F>,
+        results: &mut Vec<Positioned<Self::Item, F>>,
+    ) {
+        results.clear();
+        HashGrid::query_positioned_into(self, geometry, results)
+    }
+}
+
+impl<'a, F, T, Hx, S> SpatialRemoval for HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+    T: Entity,
+{
+    type Id = T::ID;
+
+    fn remove(&mut self, id: Self::Id) -> bool {
+        HashGrid::remove(self, id)
+    }
+}
+
+impl<'a, F, T, Hx, S> SpatialUpdate for HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+    T: Entity,
+{
+    type Id = T::ID;
+    type Position = (F, F, F);
+
+    fn relocate(&mut self, id: Self::Id, position: Self::Position) -> bool {
+        HashGrid::relocate(self, id, position)
+    }
+}
+
+// Implemented by hand since deriving it would require the hasher to implement Debug, which
+// the default hasher does not
+impl<F, T, Hx, S> fmt::Debug for HashGrid<'_, F, T, Hx, S>
+where
+    F: fmt::Debug,
+    T: fmt::Debug,
+    Hx: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HashGrid")
+            .field("grids", &self.grids)
+            .field("params", &self.params)
+            .field("bounds", &self.bounds)
+            .field("wrap", &self.wrap)
+            .field("deterministic", &self.deterministic.is_some())
+            .field("tick", &self.tick)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a, F, T, Hx, S> fmt::Display for HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive + Display,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HashGrid \n[\n  Grids: {}\n  ", self.grids.len())?;
+        write!(
+            f,
+            "Grid Parameters \n  [\n    floors: {}\n    floor-size: {}\n    cells-on-x: {}\n    cell-size-x: {}\n    cells-on-y: {}\n    cell-size-y: {}\n  ]\n  ",
+            self.floors(), self.floor_size(), self.xcells(), self.cell_size_x(), self.ycells(), self.cell_size_y()
+        )?;
+
+        let center = self.bounds.centre();
+        let size = self.bounds.size();
+
+        write!(
             f,
             "Grid Boundary \n  [\n    Center: (x= {}, y= {}, z= {})\n    size-per-axis: ({}, {}, {})\n  ]\n]",
             center[0], center[1], center[2], size[0], size[1], size[2]
@@ -419,3 +2177,144 @@ where
         Ok(())
     }
 }
+
+/// Metadata filed along with each reference to an entity, which travels with it when it is
+/// moved or filed again
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Filing<F> {
+    /// Layers of the entity, see [`HashGrid::insert_with_layers`]
+    pub(super) layers: LayerMask,
+    /// Tick at which the entity expires, see [`HashGrid::insert_with_ttl`]
+    pub(super) expiry: Option<u64>,
+    /// Vertical span of the entity filed on several floors, see [`HashGrid::insert_spanning`]
+    pub(super) span: Option<(F, F)>,
+    /// Coordinates the entity was moved to, overriding the ones it reports, see
+    /// [`HashGrid::relocate`]
+    pub(super) position: Option<(F, F, F)>,
+}
+
+impl<F: Float> Filing<F> {
+    /// Tests whether the entity shares a layer with the `mask`, any entity passing without one
+    pub(super) fn in_layers(&self, mask: Option<LayerMask>) -> bool {
+        mask.is_none_or(|mask| self.layers.intersects(mask))
+    }
+
+    /// Tests whether the `geometry` contains the entity filed at the `position`, testing the
+    /// height of its span closest to the geometry if it spans several floors
+    pub(super) fn inside(&self, geometry: &Geometry<F>, position: (F, F, F)) -> bool {
+        match self.span {
+            Some((z_min, z_max)) => {
+                let z = geometry.centre().2.max(z_min).min(z_max);
+                geometry.contains((position.0, position.1, z))
+            }
+            None => geometry.contains(position),
+        }
+    }
+
+    /// Position the `entity` is filed at, the coordinates it was relocated to if any or the
+    /// ones it reports otherwise
+    pub(super) fn position<T>(&self, entity: &T) -> (F, F, F)
+    where
+        T: Coordinate<Item = F>,
+    {
+        self.position
+            .unwrap_or_else(|| (entity.x(), entity.y(), entity.z()))
+    }
+
+    /// Extent of the `entity` moved along with it to the position it is filed at
+    pub(super) fn extent<T>(&self, entity: &T) -> Geometry<F>
+    where
+        T: ExtentEntity<Item = F>,
+    {
+        match self.position {
+            Some(p) => {
+                entity
+                    .extent()
+                    .translated((p.0 - entity.x(), p.1 - entity.y(), p.2 - entity.z()))
+            }
+            None => entity.extent(),
+        }
+    }
+
+    /// Tests whether the extent of the `entity` overlaps the `geometry`, moving it to the
+    /// height of its span closest to the geometry if it spans several floors
+    pub(super) fn overlaps<T>(&self, geometry: &Geometry<F>, entity: &T) -> bool
+    where
+        T: ExtentEntity<Item = F>,
+    {
+        let extent = self.extent(entity);
+
+        match self.span {
+            Some((z_min, z_max)) => {
+                let height = geometry.centre().2.max(z_min).min(z_max);
+                let z = self.position(entity).2;
+                extent
+                    .translated((F::zero(), F::zero(), height - z))
+                    .intersects(geometry)
+            }
+            None => extent.intersects(geometry),
+        }
+    }
+}
+
+impl<F> Default for Filing<F> {
+    fn default() -> Self {
+        Self {
+            layers: LayerMask::ALL,
+            expiry: None,
+            span: None,
+            position: None,
+        }
+    }
+}
+
+/// Entities filed on several floors already found while visiting the cells, so that each of
+/// them is only returned once. They are told apart by reference, every floor holding a copy
+/// of the same one.
+pub(super) struct Spanning<T>(BTreeSet<*const T>);
+
+impl<T> Spanning<T> {
+    pub(super) fn new() -> Self {
+        Self(BTreeSet::new())
+    }
+
+    /// Tests whether the `entity` filed with the `filing` is found for the first time
+    pub(super) fn first<F>(&mut self, entity: &T, filing: &Filing<F>) -> bool {
+        filing.span.is_none() || self.0.insert(entity as *const T)
+    }
+}
+
+/// Fraction of the interval from `min` to `max` covered by the interval from `lo` to `hi`.
+///
+/// Intervals are either fully covered or not at all when one of them is flat, such as the
+/// z-axis of 2D shapes.
+fn axis_overlap<F: Float>(min: F, max: F, lo: F, hi: F) -> F {
+    let length = max - min;
+    if length <= F::zero() || hi <= lo {
+        return match min <= hi && lo <= max {
+            true => F::one(),
+            false => F::zero(),
+        };
+    }
+
+    ((max.min(hi) - min.max(lo)) / length).max(F::zero())
+}
+
+/// Range of the cell coordinates covering the interval from `min` to `max` on a single axis.
+///
+/// Cell coordinates are the absolute values of the floored normalized components, so an
+/// interval crossing zero folds over the first cell and covers everything up to the largest
+/// of its two ends.
+fn axis_cells<F: Float>(min: F, max: F, cell_size: F) -> core::ops::RangeInclusive<u32> {
+    let lo = (min / cell_size).floor();
+    let hi = (max / cell_size).floor();
+
+    let c_lo = lo.abs().to_u32().unwrap_or(0);
+    let c_hi = hi.abs().to_u32().unwrap_or(0);
+
+    if lo < F::zero() && hi >= F::zero() {
+        0..=c_lo.max(c_hi)
+    } else {
+        c_lo.min(c_hi)..=c_lo.max(c_hi)
+    }
+}
@@ -0,0 +1,162 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+
+use super::{Coordinate, DataIndex, Entity, HashGrid};
+use crate::error::SpatialError;
+
+/// Handle to a group of entities moving together, such as a formation or a convoy, created by
+/// [`HashGrid::create_group`] and moved by [`HashGrid::relocate_group`].
+///
+/// The group tracks the position each member is filed at, so that successive moves add up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityGroup<Id, F> {
+    members: Vec<(Id, (F, F, F))>,
+}
+
+impl<Id, F> EntityGroup<Id, F> {
+    /// Ids of the members along with the positions they are filed at, in ascending id order
+    pub fn members(&self) -> &[(Id, (F, F, F))] {
+        &self.members
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+}
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Groups the entities matching the `ids`, so that they can be moved together with
+    /// [`HashGrid::relocate_group`]. Members start at the position they are filed at, and ids
+    /// given more than once are grouped once.
+    ///
+    /// Fails with [`SpatialError::UnknownEntity`] if any of the ids is not in the grid.
+    pub fn create_group<Id, I>(&self, ids: I) -> Result<EntityGroup<Id, F>, SpatialError<Id>>
+    where
+        Id: DataIndex,
+        T: Entity<ID = Id> + Coordinate<Item = F>,
+        I: IntoIterator<Item = Id>,
+    {
+        let mut members = ids
+            .into_iter()
+            .map(|id| (id, None))
+            .collect::<BTreeMap<Id, Option<(F, F, F)>>>();
+
+        for (&entity, filing) in self
+            .grids
+            .iter()
+            .flat_map(|grid| grid.values())
+            .flat_map(|bucket| bucket.iter())
+        {
+            if let Some(position) = members.get_mut(&entity.id()) {
+                *position = Some(filing.position(entity));
+            }
+        }
+
+        let members = members
+            .into_iter()
+            .map(|(id, position)| {
+                position
+                    .map(|p| (id, p))
+                    .ok_or(SpatialError::UnknownEntity(id))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(EntityGroup { members })
+    }
+
+    /// Moves every member of the `group` by the `delta`, keeping their layers, TTL and spans.
+    /// Members inserted with [`HashGrid::insert_spanning`] only move along the x and y axis.
+    ///
+    /// Members are located in a single pass over the cells, instead of one search per member as
+    /// with [`HashGrid::relocate`]. The move is all or nothing: every destination is resolved
+    /// before any member is taken out, and if any member has left the grid or cannot be filed
+    /// at its new position, nothing is moved. Returns `true` if the group was moved.
+    pub fn relocate_group<Id>(&mut self, group: &mut EntityGroup<Id, F>, delta: (F, F, F)) -> bool
+    where
+        Id: DataIndex,
+        T: Entity<ID = Id>,
+    {
+        self.try_relocate_group(group, delta).is_ok()
+    }
+
+    /// Same as [`HashGrid::relocate_group`], except that the reason for not moving the group is
+    /// reported as a [`SpatialError`]
+    pub fn try_relocate_group<Id>(
+        &mut self,
+        group: &mut EntityGroup<Id, F>,
+        delta: (F, F, F),
+    ) -> Result<(), SpatialError<Id>>
+    where
+        Id: DataIndex,
+        T: Entity<ID = Id>,
+    {
+        let index = group
+            .members
+            .iter()
+            .enumerate()
+            .map(|(i, &(id, _))| (id, i))
+            .collect::<BTreeMap<_, _>>();
+
+        // Cell each member is filed in, the first one found for the spanning members
+        let mut found = (0..group.len()).map(|_| None).collect::<Vec<_>>();
+
+        for (floor, grid) in self.grids.iter().enumerate() {
+            for (key, bucket) in grid.iter() {
+                for (&entity, &filing) in bucket.iter() {
+                    if let Some(&i) = index.get(&entity.id()) {
+                        found[i].get_or_insert(((entity, filing), (floor, key)));
+                    }
+                }
+            }
+        }
+
+        if let Some(missing) = found.iter().position(Option::is_none) {
+            return Err(SpatialError::UnknownEntity(group.members[missing].0));
+        }
+        let found = found.into_iter().flatten().collect::<Vec<_>>();
+
+        // Spanning members keep their span, only moving along the x and y axis
+        let moved = group
+            .members
+            .iter()
+            .zip(&found)
+            .map(|(&(_, p), ((_, filing), _))| match filing.span {
+                Some(_) => (p.0 + delta.0, p.1 + delta.1, p.2),
+                None => (p.0 + delta.0, p.1 + delta.1, p.2 + delta.2),
+            })
+            .collect::<Vec<_>>();
+
+        // Every destination is resolved before taking any member out, across the whole span
+        // of the spanning members
+        let targets = found
+            .iter()
+            .zip(&moved)
+            .map(|(((_, filing), _), &p)| match filing.span {
+                Some((z_min, z_max)) => {
+                    self.resolve_cell((p.0, p.1, z_max))?;
+                    self.resolve_cell((p.0, p.1, z_min))
+                }
+                None => self.resolve_cell(p),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (i, ((entry, cell), target)) in found.into_iter().zip(targets).enumerate() {
+            self.shift(entry, cell, moved[i], target)?;
+            group.members[i].1 = moved[i];
+        }
+
+        Ok(())
+    }
+}
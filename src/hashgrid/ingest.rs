@@ -0,0 +1,79 @@
+use core::hash::{BuildHasher, Hash};
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+
+use super::grid::DataRef;
+use super::{Coordinate, HashGrid};
+
+/// Progress of an [`Ingest`], reported after every batch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IngestProgress {
+    /// Entities inserted into the grid so far
+    pub inserted: usize,
+    /// Entities rejected so far, for being outside the bounds of a grid without wrap or
+    /// having non-finite coordinates
+    pub rejected: usize,
+}
+
+/// Batched insertion of a stream of entities into a [`HashGrid`], created by
+/// [`HashGrid::ingest`].
+///
+/// The ingest is an iterator which inserts a single batch on each call to `next`, and yields
+/// the progress so far. Nothing is pulled from the stream until the ingest is iterated, so the
+/// consumer controls the pace of the insertion.
+#[derive(Debug)]
+pub struct Ingest<'g, 'a, F, T, Hx, S, I> {
+    grid: &'g mut HashGrid<'a, F, T, Hx, S>,
+    items: I,
+    batch: usize,
+    progress: IngestProgress,
+}
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Inserts the entities of the stream into the grid in batches of `batch` entities, with a
+    /// minimum of one, see [`Ingest`].
+    ///
+    /// Services indexing live feeds, such as vehicle telemetry, can use the progress to
+    /// throttle the stream or to yield to other work between batches.
+    pub fn ingest<I>(&mut self, items: I, batch: usize) -> Ingest<'_, 'a, F, T, Hx, S, I::IntoIter>
+    where
+        I: IntoIterator<Item = DataRef<'a, T>>,
+    {
+        Ingest {
+            grid: self,
+            items: items.into_iter(),
+            batch: batch.max(1),
+            progress: IngestProgress::default(),
+        }
+    }
+}
+
+impl<'a, F, T, Hx, S, I> Iterator for Ingest<'_, 'a, F, T, Hx, S, I>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+    T: Coordinate<Item = F>,
+    I: Iterator<Item = DataRef<'a, T>>,
+{
+    type Item = IngestProgress;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut pulled = 0;
+
+        for entity in self.items.by_ref().take(self.batch) {
+            pulled += 1;
+            match self.grid.file::<u64>(entity) {
+                Ok(()) => self.progress.inserted += 1,
+                Err(_) => self.progress.rejected += 1,
+            }
+        }
+
+        (pulled > 0).then_some(self.progress)
+    }
+}
@@ -0,0 +1,126 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+
+use super::{Coordinate, DataIndex, Entity, HashGrid};
+use crate::math::distance;
+
+/// Entities within reach of each point of a batch, returned by [`HashGrid::distance_matrix`].
+///
+/// Rows are stored back to back in compressed sparse row layout: the row of the `i`-th point
+/// spans `offsets[i]..offsets[i + 1]` of the `ids` and `distances`, sorted by distance.
+///
+/// * `offsets:` Start of each row, followed by the total number of entries
+/// * `ids:` Ids of the entities of every row
+/// * `distances:` Distances from the point of the row to the entities
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistanceMatrix<Id, F> {
+    pub offsets: Vec<usize>,
+    pub ids: Vec<Id>,
+    pub distances: Vec<F>,
+}
+
+impl<Id, F> DistanceMatrix<Id, F> {
+    /// Ids and distances of the entities within reach of the `i`-th point
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is not lower than the number of points.
+    pub fn row(&self, i: usize) -> (&[Id], &[F]) {
+        let span = self.offsets[i]..self.offsets[i + 1];
+        (&self.ids[span.clone()], &self.distances[span])
+    }
+
+    /// Number of points, i.e. rows of the matrix
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Finds the entities within the `radius` of every point of the batch, along with their
+    /// distances, such as for many agents sensing their surroundings at once.
+    ///
+    /// Points are bucketed by the cell they fall in, and the cells around each bucket are
+    /// gathered once for all its points, instead of visiting the same cells for every point.
+    /// Returns one row per point, in the order of the `points`.
+    pub fn distance_matrix<Id>(&self, points: &[(F, F, F)], radius: F) -> DistanceMatrix<Id, F>
+    where
+        Id: DataIndex,
+        T: Entity<ID = Id> + Coordinate<Item = F>,
+    {
+        let mut buckets: BTreeMap<(usize, [i64; 2]), Vec<usize>> = BTreeMap::new();
+        for (i, &point) in points.iter().enumerate() {
+            let (_, _, floor) = self.get_cell_coordinates(self.clamp(point));
+            buckets
+                .entry((floor, self.world_cell(floor, point)))
+                .or_default()
+                .push(i);
+        }
+
+        let mut entries = Vec::new();
+        let mut candidates = Vec::new();
+
+        for indices in buckets.values() {
+            // Box enclosing every point of the bucket, grown by the radius
+            let (mut min, mut max) = (points[indices[0]], points[indices[0]]);
+            for &i in indices {
+                let p = points[i];
+                min = (min.0.min(p.0), min.1.min(p.1), min.2.min(p.2));
+                max = (max.0.max(p.0), max.1.max(p.1), max.2.max(p.2));
+            }
+            let min = (min.0 - radius, min.1 - radius, min.2 - radius);
+            let max = (max.0 + radius, max.1 + radius, max.2 + radius);
+
+            candidates.clear();
+            for (hashindex, floor) in self.cells_in_box(min, max) {
+                if let Some(bucket) = self.grids[floor].bucket(&hashindex.key()) {
+                    candidates.extend(bucket.iter().map(|(&e, filing)| (e, filing.position(e))));
+                }
+            }
+
+            // Mirrored cells and spanning entities may gather an entity more than once
+            candidates.sort_unstable_by_key(|(e, _)| *e as *const T as usize);
+            candidates.dedup_by_key(|(e, _)| *e as *const T as usize);
+
+            for &i in indices {
+                entries.extend(candidates.iter().filter_map(|&(e, position)| {
+                    let d = distance(points[i], position);
+                    (d <= radius).then(|| (i, e.id(), d))
+                }));
+            }
+        }
+
+        entries.sort_unstable_by(|a, b| a.0.cmp(&b.0).then(a.2.partial_cmp(&b.2).unwrap()));
+
+        let mut offsets = Vec::with_capacity(points.len() + 1);
+        let mut row = 0;
+        offsets.push(0);
+        for (n, &(i, _, _)) in entries.iter().enumerate() {
+            while row < i {
+                offsets.push(n);
+                row += 1;
+            }
+        }
+        offsets.resize(points.len() + 1, entries.len());
+
+        let (ids, distances) = entries.into_iter().map(|(_, id, d)| (id, d)).unzip();
+
+        DistanceMatrix {
+            offsets,
+            ids,
+            distances,
+        }
+    }
+}
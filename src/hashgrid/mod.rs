@@ -1,21 +1,56 @@
+use crate::error::SpatialError;
+use crate::geometry::Geometry;
+use alloc::vec::Vec;
 use core::fmt;
-use grid::DataRef;
-use num_traits::{Float, FromPrimitive, One, PrimInt, ToPrimitive, Unsigned, Zero};
-use std::{
+use core::{
     fmt::{Debug, Display},
     hash::Hash,
     ops::Div,
 };
+use grid::{DataRef, Filing};
+use num_traits::{Float, FromPrimitive, One, PrimInt, ToPrimitive, Unsigned, Zero};
 
-pub use grid::HashGrid;
-
+pub use cells::CellStorage;
+pub use cellspace::CellId;
+pub use diff::GridDiff;
+pub use flow::FlowField;
+pub use grid::{DefaultHasher, HashGrid};
+pub use group::EntityGroup;
+pub use ingest::{Ingest, IngestProgress};
+pub use matrix::DistanceMatrix;
+pub use neighbours::Neighbourhood;
+pub use order::{CellOrder, CellRef};
+pub use page::Page;
+pub use region::{Hotspot, RegionStats};
+
+mod blocked;
+mod cells;
+mod cellspace;
+mod clusters;
+mod diff;
+mod export;
+mod flow;
 mod grid;
+mod group;
+mod ingest;
+mod matrix;
+mod nearest;
+mod neighbours;
+mod order;
+mod page;
+mod path;
+mod pathfind;
+mod rank;
+mod region;
+mod spawn;
+mod weighted;
 
 /// ### Cells per Axis
 ///
 /// Stores the total number of cells on each axis defined by the user at the time of
 /// grid construction
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CellsPerAxis {
     xcells: u32,
     ycells: u32,
@@ -34,6 +69,22 @@ impl CellsPerAxis {
             floors,
         }
     }
+
+    /// Same as [`CellsPerAxis::from`], except that a wrong number of components is reported
+    /// as a [`SpatialError::DimensionMismatch`] instead of panicking
+    pub fn try_from(cells: &[u32], floors: usize) -> Result<Self, SpatialError> {
+        match *cells {
+            [xcells, ycells] => Ok(Self {
+                xcells,
+                ycells,
+                floors,
+            }),
+            _ => Err(SpatialError::DimensionMismatch {
+                expected: 2,
+                found: cells.len(),
+            }),
+        }
+    }
 }
 
 /// ### Cell Sizes
@@ -41,6 +92,7 @@ impl CellsPerAxis {
 /// Holds the cell size for every individual cell on each axis, These sizes are calculated
 /// during the grid initialization depending upon the [`CellsPerAxis`] and grid bounds
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CellSizes<F> {
     x_size: F,
     y_size: F,
@@ -52,6 +104,7 @@ pub struct CellSizes<F> {
 /// Defines the dimensions of bounding `rectangle(2D)`/`box(3D)` for the grid, which acts as
 /// the grid boundary. This type implements the [`Boundary`] trait
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GridBoundary<F> {
     pub center: [F; 3],
     pub size: [F; 3],
@@ -71,6 +124,7 @@ impl<F: Float + FromPrimitive + ToPrimitive> Boundary for GridBoundary<F> {
 
 /// Stores the grid information regarding the cell sizes and number of cells per axis
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GridParameters<F> {
     pub cell_per_axis: CellsPerAxis,
     pub cell_sizes: CellSizes<F>,
@@ -84,13 +138,14 @@ pub struct GridParameters<F> {
 ///
 /// `QueryType` is one of the major constituent of the main [`Query`]
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum QueryType<Id> {
     Find(Id),
     Relevant,
 }
 
 impl<Id: Display> fmt::Display for QueryType<Id> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             QueryType::Find(id) => write!(f, "Find({})", id),
             QueryType::Relevant => write!(f, "Relevant"),
@@ -98,6 +153,21 @@ impl<Id: Display> fmt::Display for QueryType<Id> {
     }
 }
 
+/// Location of the query point relative to the grid bounds, reported by [`QueryResult::status`]
+///
+/// * `InBounds:` The query point lies within the grid bounds
+/// * `Clamped:` The query point lies outside the bounds of a grid with wrap enabled, and has
+///   been clamped onto the nearest boundary cell, the same way entities are wrapped on insertion
+/// * `OutOfBounds:` The query point lies outside the bounds of a grid without wrap, the result
+///   only holds the entities within the query radius reaching into the grid, if any
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum QueryStatus {
+    InBounds,
+    Clamped,
+    OutOfBounds,
+}
+
 /// Used for querying the [`HashGrid`].
 ///
 /// Query provides the parameters to inquire the hashgrid flexibly. It parameterized over:
@@ -160,11 +230,8 @@ impl<Id: Display> fmt::Display for QueryType<Id> {
 ///     size: (100.0, 100.0, 100.0)
 /// };
 ///
-/// // Creating the Hashgrid with f32 as the base float and object as the base data type
-/// // Object type must implements the HashGrid::{Entity, Coordinate} traits
-/// let mut hashgrid = HashGrid::<f32, Object>::new([2,2], 0, &bounds, false);
-///
-/// // Creating two objects at different locations
+/// // Creating two objects at different locations, declared before the grid since the grid
+/// // borrows them
 /// let obj1 = Object {
 ///     id: 0,
 ///     position: (22.0, 30.0)
@@ -175,6 +242,10 @@ impl<Id: Display> fmt::Display for QueryType<Id> {
 ///     position: (15.0, 45.0)
 /// };
 ///
+/// // Creating the Hashgrid with f32 as the base float and object as the base data type
+/// // Object type must implements the HashGrid::{Entity, Coordinate} traits
+/// let mut hashgrid = HashGrid::<f32, Object>::new([2,2], 0, &bounds, false);
+///
 /// // Inserting the objects into the hashgrid
 /// hashgrid.insert(&obj1);
 /// hashgrid.insert(&obj2);
@@ -192,7 +263,10 @@ impl<Id: Display> fmt::Display for QueryType<Id> {
 ///
 /// Querying the hashgrid returns the [`QueryResult`] as response.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Query<F, Id> {
+    /// Radius of the query in world units, like every other query API of the crate. Use
+    /// [`Relevance::to_world_radius`] to convert a radius normalized to the grid size
     pub radius: F,
     pub ty: QueryType<Id>,
     pub coordinates: (F, F, F),
@@ -203,7 +277,7 @@ where
     F: Float + FromPrimitive + ToPrimitive + Display,
     Id: DataIndex + Display,
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
             "Query [\n  Type: {}\n  Radius: {}\n  Coordinates: (x= {}, y= {}, z= {})\n]",
@@ -217,6 +291,8 @@ where
     F: Float + FromPrimitive + ToPrimitive,
     Id: DataIndex,
 {
+    /// Creates a query at the coordinates `cords`, visiting the cells within the `radius`
+    /// given in world units. The cells next to the one of the query point are always visited.
     pub fn from(cords: (F, F, F), query_type: QueryType<Id>, radius: F) -> Self {
         Self {
             radius,
@@ -245,16 +321,81 @@ where
     }
 }
 
+/// Location of the origin of the cell coordinates, see [`CoordinateFrame`]
+///
+/// * `Centre:` Cells are counted from the world origin, mirrored on both of its sides
+/// * `Corner:` Cells are counted from the minimum corner of the grid bounds, as for worlds
+///   spanning from `(0, 0)` to their size, or cell indices meant to match tile indices
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Origin {
+    #[default]
+    Centre,
+    Corner,
+}
+
+/// Direction of the y-axis, see [`CoordinateFrame`]
+///
+/// The direction only affects how the grid is exported, such as by
+/// [`HashGrid::to_geojson`]. Cells are mirrored around the origin of the frame, so the cell
+/// math is the same whichever way the y coordinates grow.
+///
+/// * `Up:` The y coordinates grow upwards, as in world space and GIS tools
+/// * `Down:` The y coordinates grow downwards, as in screen space
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum YAxis {
+    #[default]
+    Up,
+    Down,
+}
+
+/// Coordinate frame of the entities indexed by a [`HashGrid`], see [`HashGrid::with_frame`].
+///
+/// The `origin` decides how the coordinates map onto the cells, while the `y_axis` decides
+/// how the grid is presented to tools expecting y-up coordinates, such as the GeoJSON export.
+/// The default frame is centred and y-up.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CoordinateFrame {
+    pub origin: Origin,
+    pub y_axis: YAxis,
+}
+
+/// Relevance is a radius normalized to the size of the grid, `1.0` spanning the whole grid
+/// along its largest horizontal axis.
+///
+/// Queries take their radius in world units, the relevance is converted through
+/// [`Relevance::to_world_radius`].
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Relevance<F>(pub F);
+
+impl<F: Float> Relevance<F> {
+    /// Converts the relevance into a radius in world units for a grid within the `bounds`
+    pub fn to_world_radius<B>(&self, bounds: &B) -> F
+    where
+        B: Boundary<Item = F>,
+    {
+        let size = bounds.size();
+        self.0 * size[0].abs().max(size[1].abs())
+    }
+}
+
 /// QueryResult is the return type for [`Query`]. When we query the hashgrid, hashgrid returns
 /// a response in `QueryResult`.
-/// 
+///
 /// It contains the original query made to hashgrid, and the list of immutable references to the data
 /// collected as the response. To access the data isnside the QueryResult, use method [`QueryResult::data`]
 /// and to see the original query use [`QueryResult::query`]
 #[derive(Debug)]
 pub struct QueryResult<'a, F, Id, T> {
     query: Query<F, Id>,
+    status: QueryStatus,
     data: Vec<DataRef<'a, T>>,
+    /// Filing of each entity of the `data`, telling where it is filed in the grid
+    filings: Vec<Filing<F>>,
+    generation: u64,
 }
 
 impl<'a, F, Id, T> QueryResult<'a, F, Id, T>
@@ -269,6 +410,39 @@ where
     pub fn data(&self) -> &[DataRef<'a, T>] {
         &self.data
     }
+
+    /// Returns whether the query point was inside the grid bounds, telling an empty area
+    /// apart from a query made outside of the world
+    pub fn status(&self) -> QueryStatus {
+        self.status
+    }
+
+    /// Generation of the grid when the query was made, see [`HashGrid::generation`]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Keeps only the entities whose extent overlaps the `geometry`.
+    ///
+    /// Relevance queries return every entity of the cells around the query point, this narrows
+    /// them down to the ones actually reaching into the area of interest. The extent is tested
+    /// where the entity is filed, so entities moved with [`HashGrid::relocate`] are tested at
+    /// the coordinates they were relocated to, like [`HashGrid::query_extents`] does.
+    pub fn retain_overlapping(&mut self, geometry: &Geometry<F>)
+    where
+        T: ExtentEntity<Item = F>,
+    {
+        let (data, filings) = self
+            .data
+            .iter()
+            .zip(&self.filings)
+            .filter(|&(&e, filing)| filing.overlaps(geometry, e))
+            .map(|(&e, &filing)| (e, filing))
+            .unzip();
+
+        self.data = data;
+        self.filings = filings;
+    }
 }
 
 impl<'a, F, Id, T> fmt::Display for QueryResult<'a, F, Id, T>
@@ -277,7 +451,7 @@ where
     Id: DataIndex + Display,
     T: Debug,
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
             "QueryResult [\n  {}\n  Data: {:?}\n]",
@@ -289,15 +463,15 @@ where
 
 /// Type used as unique cell indices or the cell hash for identifying the grid cell
 /// to insert or retreive the data.
-/// 
+///
 /// `HashIndex` is generic over the type to be used as hash index and is passed through
 /// the `HashGrid` initialization. If there is no type passed for the hashindex, then
 /// it defaults to the type `u64`
-/// 
+///
 /// # Example
-/// 
+///
 /// This is how we can pass the hashindex type at the time of [`HashGrid`] initialization
-/// 
+///
 /// ```rust
 /// # use spatial::hashgrid::{HashGrid, Boundary};
 /// # struct Bounds {
@@ -347,7 +521,7 @@ where
 }
 
 /// `Entity` trait obligates the data object to have a unique id
-/// 
+///
 /// This is a trait bound imposed by the hashgrid to must implement for data type for which
 /// the hashgrid is being created.
 pub trait Entity {
@@ -359,7 +533,7 @@ pub trait Entity {
 
 /// `Coordinate` trait obligates the data object to have spatial coordinates components. This
 /// trait can be implemented on the 2D object types as well.
-/// 
+///
 /// This is a trait bound imposed by the hashgrid to must implement for data type for which
 /// the hashgrid is being created.
 pub trait Coordinate {
@@ -378,17 +552,47 @@ pub trait Coordinate {
     }
 }
 
+/// `ExtentEntity` trait describes the space occupied by data objects which are not simple
+/// points, such as a unit with a collision radius or a building with a footprint.
+///
+/// The hashgrid still files these objects by their [`Coordinate`], the extent is only used to
+/// test them exactly against the queries, see [`HashGrid::query_extents`].
+pub trait ExtentEntity: Coordinate {
+    /// Mendatory method to return the shape occupied by the data, usually a
+    /// [`Geometry::Rect`] or [`Geometry::Radius`] centred at its coordinates
+    fn extent(&self) -> Geometry<Self::Item>;
+}
+
+/// `WeightedEntity` trait gives data objects a weight, such as the cost of replicating them
+/// or of rendering them, spent from the budget of [`HashGrid::query_weighted`].
+pub trait WeightedEntity: Coordinate {
+    /// Mendatory method to return the weight of the data, weights which are negative or
+    /// not a number counting as zero
+    fn weight(&self) -> Self::Item;
+}
+
+/// `Boundary` trait describes an axis aligned box through its centre and its size on each
+/// axis. 2D boundaries simply have a zero size on the z-axis.
+///
+/// Sizes are expected to be positive, negative sizes are treated as their absolute value.
 pub trait Boundary {
     type Item: Float + FromPrimitive + ToPrimitive;
 
     fn centre(&self) -> [Self::Item; 3];
     fn size(&self) -> [Self::Item; 3];
 
+    /// Tests whether the point lies inside the boundary, edges included
     fn is_inside(&self, point: (Self::Item, Self::Item, Self::Item)) -> bool {
         let half_size = [
-            self.size()[0].div(Self::Item::one() + Self::Item::one()),
-            self.size()[1].div(Self::Item::one() + Self::Item::one()),
-            self.size()[2].div(Self::Item::one() + Self::Item::one()),
+            self.size()[0]
+                .abs()
+                .div(Self::Item::one() + Self::Item::one()),
+            self.size()[1]
+                .abs()
+                .div(Self::Item::one() + Self::Item::one()),
+            self.size()[2]
+                .abs()
+                .div(Self::Item::one() + Self::Item::one()),
         ];
 
         let dx = (point.0 - self.centre()[0]).abs();
@@ -400,9 +604,15 @@ pub trait Boundary {
 
     fn max(&self) -> [Self::Item; 3] {
         let half_size = [
-            self.size()[0].div(Self::Item::one() + Self::Item::one()),
-            self.size()[1].div(Self::Item::one() + Self::Item::one()),
-            self.size()[2].div(Self::Item::one() + Self::Item::one()),
+            self.size()[0]
+                .abs()
+                .div(Self::Item::one() + Self::Item::one()),
+            self.size()[1]
+                .abs()
+                .div(Self::Item::one() + Self::Item::one()),
+            self.size()[2]
+                .abs()
+                .div(Self::Item::one() + Self::Item::one()),
         ];
 
         [
@@ -414,9 +624,15 @@ pub trait Boundary {
 
     fn min(&self) -> [Self::Item; 3] {
         let half_size = [
-            self.size()[0].div(Self::Item::one() + Self::Item::one()),
-            self.size()[1].div(Self::Item::one() + Self::Item::one()),
-            self.size()[2].div(Self::Item::one() + Self::Item::one()),
+            self.size()[0]
+                .abs()
+                .div(Self::Item::one() + Self::Item::one()),
+            self.size()[1]
+                .abs()
+                .div(Self::Item::one() + Self::Item::one()),
+            self.size()[2]
+                .abs()
+                .div(Self::Item::one() + Self::Item::one()),
         ];
 
         [
@@ -425,6 +641,74 @@ pub trait Boundary {
             self.centre()[2] - half_size[2],
         ]
     }
+
+    /// Tests whether the two boundaries share any point, touching edges included
+    fn intersects<B>(&self, other: &B) -> bool
+    where
+        B: Boundary<Item = Self::Item>,
+    {
+        let (min, max) = (self.min(), self.max());
+        let (other_min, other_max) = (other.min(), other.max());
+
+        (0..3).all(|axis| min[axis] <= other_max[axis] && other_min[axis] <= max[axis])
+    }
+
+    /// Returns the boundary covered by both boundaries, or `None` if they do not intersect
+    fn overlap<B>(&self, other: &B) -> Option<GridBoundary<Self::Item>>
+    where
+        B: Boundary<Item = Self::Item>,
+    {
+        if !self.intersects(other) {
+            return None;
+        }
+
+        let (min, max) = (self.min(), self.max());
+        let (other_min, other_max) = (other.min(), other.max());
+
+        let two = Self::Item::one() + Self::Item::one();
+        let mut center = [Self::Item::zero(); 3];
+        let mut size = [Self::Item::zero(); 3];
+        for axis in 0..3 {
+            let lo = min[axis].max(other_min[axis]);
+            let hi = max[axis].min(other_max[axis]);
+            center[axis] = (lo + hi) / two;
+            size[axis] = hi - lo;
+        }
+
+        Some(GridBoundary { center, size })
+    }
+
+    /// Volume of the boundary, or its area for 2D boundaries with a zero size on the z-axis
+    fn volume(&self) -> Self::Item {
+        let size = self.size().map(|s| s.abs());
+        let area = size[0] * size[1];
+
+        if size[2].is_zero() {
+            area
+        } else {
+            area * size[2]
+        }
+    }
+
+    /// Returns the boundary grown by the `margin` on every side. Axes with a zero size are
+    /// kept flat, so 2D boundaries remain 2D. Negative margins shrink the boundary, down to a
+    /// zero size.
+    fn expand(&self, margin: Self::Item) -> GridBoundary<Self::Item> {
+        let two = Self::Item::one() + Self::Item::one();
+        let size = self.size().map(|s| {
+            let s = s.abs();
+            if s.is_zero() {
+                s
+            } else {
+                (s + margin * two).max(Self::Item::zero())
+            }
+        });
+
+        GridBoundary {
+            center: self.centre(),
+            size,
+        }
+    }
 }
 
 // pub type DefaultDx = usize;
@@ -0,0 +1,94 @@
+use core::hash::{BuildHasher, Hash};
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+
+use super::grid::DataRef;
+use super::{Boundary, Coordinate, HashGrid};
+use crate::math::distance;
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Finds the entity closest to the `point`, along with its distance.
+    ///
+    /// Cells are visited in rings spiralling outwards from the cell of the point, floor by
+    /// floor. Once a ring is complete, every entity left on the floor lies at least as far as
+    /// the inner edge of the next ring, so the search of the floor stops as soon as the closest
+    /// entity found is nearer than that. Points outside the grid bounds start from the closest
+    /// cell within the bounds.
+    ///
+    /// Returns `None` if the grid is empty.
+    pub fn nearest(&self, point: (F, F, F)) -> Option<(DataRef<'a, T>, F)>
+    where
+        T: Coordinate<Item = F>,
+    {
+        let (min, max) = (self.bounds.min(), self.bounds.max());
+
+        let clamped = (
+            point.0.max(min[0]).min(max[0]),
+            point.1.max(min[1]).min(max[1]),
+            point.2,
+        );
+
+        // Entities outside of a ring are at least that far from the clamped point, the
+        // offset accounting for the distance between the point and its clamped position
+        let offset = distance(point, clamped);
+
+        // Signed world cell coordinates, the cells of the grid being mirrored around the origin
+        // of the frame
+        let origin = self.origin();
+        let cell = |v: F, o: F, size: F| ((v - o) / size).floor().to_i64().unwrap_or(0);
+
+        let mut best: Option<(DataRef<'a, T>, F)> = None;
+
+        // Floors may have their own resolution, so each one is searched with its own rings
+        for (floor, grid) in self.grids.iter().enumerate() {
+            if grid.is_empty() {
+                continue;
+            }
+
+            let (sx, sy) = self.floor_cell_sizes(floor);
+
+            let (imin, imax) = (cell(min[0], origin.0, sx), cell(max[0], origin.0, sx));
+            let (jmin, jmax) = (cell(min[1], origin.1, sy), cell(max[1], origin.1, sy));
+            let (ci, cj) = (cell(clamped.0, origin.0, sx), cell(clamped.1, origin.1, sy));
+
+            let ring_width = sx.min(sy);
+            let last_ring = (ci - imin)
+                .max(imax - ci)
+                .max(cj - jmin)
+                .max(jmax - cj)
+                .max(0);
+
+            for ring in 0..=last_ring {
+                let ring_cells = (ci - ring..=ci + ring)
+                    .flat_map(|i| (cj - ring..=cj + ring).map(move |j| (i, j)))
+                    .filter(|&(i, j)| (i - ci).abs() == ring || (j - cj).abs() == ring)
+                    .filter(|&(i, j)| (imin..=imax).contains(&i) && (jmin..=jmax).contains(&j));
+
+                for (i, j) in ring_cells {
+                    let key = self.key(i.unsigned_abs() as u32, j.unsigned_abs() as u32);
+
+                    for (&entity, filing) in
+                        grid.bucket(&key.key()).into_iter().flat_map(|b| b.iter())
+                    {
+                        let d = distance(point, filing.position(entity));
+                        if best.is_none_or(|(_, best_d)| d < best_d) {
+                            best = Some((entity, d));
+                        }
+                    }
+                }
+
+                let bound = F::from_i64(ring).unwrap() * ring_width - offset;
+                if best.is_some_and(|(_, best_d)| best_d <= bound) {
+                    break;
+                }
+            }
+        }
+
+        best
+    }
+}
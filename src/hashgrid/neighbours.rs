@@ -0,0 +1,111 @@
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+use core::ops::RangeInclusive;
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+
+use super::{CellRef, HashGrid};
+
+/// Cells considered adjacent by [`HashGrid::neighbours_of_cell`]
+///
+/// * `VonNeumann:` Cells sharing a face with the cell, 4 on a floor and 6 across floors
+/// * `Moore:` Cells sharing a face, an edge or a corner with the cell, 8 on a floor and 26
+///   across floors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Neighbourhood {
+    VonNeumann,
+    Moore,
+}
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Returns the occupied cells adjacent to the `cell`, as a building block for cellular
+    /// automata or local averaging running on the grid.
+    ///
+    /// The `cell` is given as the `x`, `y` cell coordinates and the floor, as returned by
+    /// [`HashGrid::get_cell_coordinates`]. Neighbours on the floors above and below are the
+    /// cells of their own resolution, see [`HashGrid::with_floor_cells`], overlapping the
+    /// column of the cell, and with [`Neighbourhood::Moore`] the columns of its neighbours on
+    /// its floor. Floors sharing the resolution therefore contribute the same cell
+    /// coordinates. Neighbours beyond the first cell or floor are skipped, and the cell itself
+    /// is not included.
+    pub fn neighbours_of_cell(
+        &self,
+        cell: (u32, u32, usize),
+        pattern: Neighbourhood,
+    ) -> Vec<CellRef<'_, 'a, T>> {
+        let (cx, cy, floor) = (cell.0 as i64, cell.1 as i64, cell.2 as i64);
+        let floors = self.floors() as i64;
+        let [xcells, ycells] = self.floor_cells(cell.2).map(i64::from);
+
+        // Columns reached on the floors above and below, before mapping them onto the cells
+        // of those floors
+        let reach = match pattern {
+            Neighbourhood::VonNeumann => 0,
+            Neighbourhood::Moore => 1,
+        };
+        let (xs, ys) = (
+            (cx - reach).max(0)..=cx + reach,
+            (cy - reach).max(0)..=cy + reach,
+        );
+
+        // Cells of a floor with `to` cells along an axis overlapping the cells `range` of a
+        // floor with `from` cells along the same axis
+        let overlapping = |range: &RangeInclusive<i64>, from: i64, to: i64| {
+            let lo = range.start() * to / from;
+            let hi = ((range.end() + 1) * to + from - 1) / from - 1;
+            lo..=hi
+        };
+
+        let mut neighbours = Vec::new();
+        let mut push = |z: usize, x: i64, y: i64| {
+            let (x, y) = (x as u32, y as u32);
+            if let Some(data) = self.grids[z].get(&self.key(x, y).key()) {
+                neighbours.push(CellRef {
+                    floor: z,
+                    coordinates: [x, y],
+                    data,
+                });
+            }
+        };
+
+        for dz in -1..=1_i64 {
+            let z = floor + dz;
+            if !(0..floors).contains(&z) {
+                continue;
+            }
+
+            if dz == 0 {
+                for dy in -1..=1_i64 {
+                    for dx in -1..=1_i64 {
+                        let steps = dx.abs() + dy.abs();
+                        let adjacent = match pattern {
+                            Neighbourhood::VonNeumann => steps == 1,
+                            Neighbourhood::Moore => steps > 0,
+                        };
+
+                        let (x, y) = (cx + dx, cy + dy);
+                        if adjacent && x >= 0 && y >= 0 {
+                            push(z as usize, x, y);
+                        }
+                    }
+                }
+                continue;
+            }
+
+            let [to_x, to_y] = self.floor_cells(z as usize).map(i64::from);
+            for y in overlapping(&ys, ycells, to_y) {
+                for x in overlapping(&xs, xcells, to_x) {
+                    push(z as usize, x, y);
+                }
+            }
+        }
+
+        neighbours
+    }
+}
@@ -0,0 +1,140 @@
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+
+use super::grid::DataRef;
+use super::HashGrid;
+
+/// Order in which [`HashGrid::cells_in_order`] visits the occupied cells of a floor.
+///
+/// * `RowMajor:` Cells sorted by their `y` and then `x` cell coordinate
+/// * `Morton:` Cells following a Z-order curve, cheap to compute with a good locality
+/// * `Hilbert:` Cells following a Hilbert curve, where consecutive cells are always
+///   neighbours, giving the best locality at a slightly higher cost
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CellOrder {
+    RowMajor,
+    Morton,
+    Hilbert,
+}
+
+/// Occupied cell of a [`HashGrid`] along with its location
+#[derive(Debug)]
+pub struct CellRef<'g, 'a, T> {
+    pub floor: usize,
+    /// Cell coordinates on the x and y axis, as used to compute the hash index of the cell
+    pub coordinates: [u32; 2],
+    pub data: &'g [DataRef<'a, T>],
+}
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Returns the occupied cells of the grid, floor by floor, with the cells of each floor
+    /// sorted along the curve of the [`CellOrder`].
+    ///
+    /// Systems processing every cell each frame, such as crowd or fluid simulations, can use
+    /// the Morton or Hilbert orders to visit neighbouring cells one after another, instead of
+    /// the arbitrary order of the HashMap.
+    pub fn cells_in_order(&self, order: CellOrder) -> Vec<CellRef<'_, 'a, T>> {
+        let mut cells = Vec::new();
+
+        for (floor, grid) in self.grids.iter().enumerate() {
+            let mut floor_cells = grid
+                .iter()
+                .map(|(key, data)| CellRef {
+                    floor,
+                    coordinates: unpair(key.to_u64().unwrap()),
+                    data: data.items(),
+                })
+                .collect::<Vec<_>>();
+
+            match order {
+                CellOrder::RowMajor => {
+                    floor_cells.sort_by_key(|c| [c.coordinates[1], c.coordinates[0]])
+                }
+                CellOrder::Morton => floor_cells.sort_by_key(|c| morton(c.coordinates)),
+                CellOrder::Hilbert => {
+                    // The curve has to cover the largest cell coordinate on the floor
+                    let side = floor_cells
+                        .iter()
+                        .map(|c| c.coordinates[0].max(c.coordinates[1]))
+                        .max()
+                        .unwrap_or(0) as u64
+                        + 1;
+                    let side = side.next_power_of_two();
+
+                    floor_cells.sort_by_key(|c| hilbert(c.coordinates, side));
+                }
+            }
+
+            cells.extend(floor_cells);
+        }
+
+        cells
+    }
+}
+
+/// Inverts the cantor pairing of [`HashGrid::key`] back into the cell coordinates
+pub(super) fn unpair(key: u64) -> [u32; 2] {
+    // Largest w with w * (w + 1) / 2 <= key, starting from the float estimate and
+    // correcting its rounding
+    let mut w = ((((8 * key as u128 + 1) as f64).sqrt() - 1.0) / 2.0) as u64;
+    while w * (w + 1) / 2 > key {
+        w -= 1;
+    }
+    while (w + 1) * (w + 2) / 2 <= key {
+        w += 1;
+    }
+
+    let k2 = key - w * (w + 1) / 2;
+    let k1 = w - k2;
+
+    [k1 as u32, k2 as u32]
+}
+
+/// Morton code of the cell coordinates, interleaving the bits of `x` and `y`
+fn morton(coordinates: [u32; 2]) -> u64 {
+    fn spread(v: u32) -> u64 {
+        let mut v = v as u64;
+        v = (v | (v << 16)) & 0x0000_FFFF_0000_FFFF;
+        v = (v | (v << 8)) & 0x00FF_00FF_00FF_00FF;
+        v = (v | (v << 4)) & 0x0F0F_0F0F_0F0F_0F0F;
+        v = (v | (v << 2)) & 0x3333_3333_3333_3333;
+        v = (v | (v << 1)) & 0x5555_5555_5555_5555;
+        v
+    }
+
+    spread(coordinates[0]) | (spread(coordinates[1]) << 1)
+}
+
+/// Distance of the cell coordinates along the Hilbert curve covering a square of `side`
+/// cells, where `side` is a power of two
+fn hilbert(coordinates: [u32; 2], side: u64) -> u128 {
+    let (mut x, mut y) = (coordinates[0] as u64, coordinates[1] as u64);
+    let mut d = 0u128;
+
+    let mut s = side / 2;
+    while s > 0 {
+        let rx = ((x & s) > 0) as u64;
+        let ry = ((y & s) > 0) as u64;
+        d += (s as u128) * (s as u128) * ((3 * rx) ^ ry) as u128;
+
+        // Rotating the quadrant so the curve stays continuous
+        if ry == 0 {
+            if rx == 1 {
+                x = side - 1 - x;
+                y = side - 1 - y;
+            }
+            core::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+
+    d
+}
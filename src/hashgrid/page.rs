@@ -0,0 +1,66 @@
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+
+use super::grid::DataRef;
+use super::{Coordinate, DataIndex, Entity, HashGrid};
+use crate::geometry::Geometry;
+
+/// Page of the results of a query, returned by [`HashGrid::query_page`]
+///
+/// * `items:` Entities of the page, sorted by id
+/// * `next:` Cursor to pass to get the following page, `None` on the last page
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<'a, T, Id> {
+    pub items: Vec<DataRef<'a, T>>,
+    pub next: Option<Id>,
+}
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Queries the grid for the entities located inside the [`Geometry`] one page at a time,
+    /// such as for web APIs listing the objects near a location.
+    ///
+    /// Entities are ordered by id, the `cursor` being the id of the last entity of the previous
+    /// page, or `None` for the first page. Pages stay consistent while the grid changes between
+    /// the calls, entities are neither skipped nor repeated unless they move across the cursor.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `page_size` is zero.
+    pub fn query_page<Id>(
+        &self,
+        geometry: &Geometry<F>,
+        cursor: Option<Id>,
+        page_size: usize,
+    ) -> Page<'a, T, Id>
+    where
+        Id: DataIndex,
+        T: Coordinate<Item = F> + Entity<ID = Id>,
+    {
+        assert!(page_size > 0, "The page size must be positive");
+
+        let mut items = self.query_geometry(geometry);
+        if let Some(cursor) = cursor {
+            items.retain(|e| e.id() > cursor);
+        }
+
+        // Only the entities of the page have to be sorted
+        let more = items.len() > page_size;
+        if more {
+            items.select_nth_unstable_by_key(page_size, |e| e.id());
+            items.truncate(page_size);
+        }
+        items.sort_unstable_by_key(|e| e.id());
+
+        Page {
+            next: more.then(|| items[page_size - 1].id()),
+            items,
+        }
+    }
+}
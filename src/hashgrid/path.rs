@@ -0,0 +1,153 @@
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+
+use super::grid::{DataRef, Spanning};
+use super::{Coordinate, ExtentEntity, HashGrid};
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Queries the grid for every entity within the corridor of the given `width` centred on
+    /// the polyline through the `points`, such as the hazards along a route.
+    ///
+    /// The corridor lies in the x/y plane and spans every floor. Each cell overlapping the
+    /// corridor is visited once, even where the bounding boxes of consecutive segments overlap,
+    /// and entities spanning several floors are kept once, so every entity is returned at most
+    /// once. A single point yields a circle of the width.
+    pub fn query_along_path(&self, points: &[(F, F)], width: F) -> Vec<DataRef<'a, T>>
+    where
+        T: Coordinate<Item = F>,
+    {
+        let half = width / (F::one() + F::one());
+        let (inf, neg_inf) = (F::infinity(), F::neg_infinity());
+
+        let segments: Vec<_> = match points {
+            [] => return Vec::new(),
+            [point] => Vec::from([(*point, *point)]),
+            _ => points.windows(2).map(|w| (w[0], w[1])).collect(),
+        };
+
+        let mut cells: Vec<_> = segments
+            .iter()
+            .flat_map(|&(a, b)| {
+                let min = (a.0.min(b.0) - half, a.1.min(b.1) - half, neg_inf);
+                let max = (a.0.max(b.0) + half, a.1.max(b.1) + half, inf);
+                self.cells_in_box(min, max)
+            })
+            .map(|(hashindex, floor)| (floor, hashindex.key()))
+            .collect();
+        cells.sort_unstable();
+        cells.dedup();
+
+        let half_sq = half * half;
+        let mut results = Vec::new();
+
+        // Entities spanning several floors are found once per floor visited
+        let mut spanning = Spanning::new();
+
+        for (floor, key) in cells {
+            if let Some(bucket) = self.grids[floor].bucket(&key) {
+                results.extend(
+                    bucket
+                        .iter()
+                        .filter(|&(&e, filing)| {
+                            let (x, y, _) = filing.position(e);
+                            segments
+                                .iter()
+                                .any(|&(a, b)| segment_distance_sq((x, y), a, b) <= half_sq)
+                                && spanning.first(e, filing)
+                        })
+                        .map(|(&e, _)| e),
+                );
+            }
+        }
+
+        results
+    }
+
+    /// Tests the line of sight from the point `from` to the point `to`, which is blocked by
+    /// every entity matching `is_blocker` whose extent touches the segment between them, such
+    /// as walls or closed doors.
+    ///
+    /// Only the cells along the segment are visited, widened by the `reach` of the extents as
+    /// for [`HashGrid::query_extents`]. An entity standing at one of the points, such as the
+    /// viewer itself, should not be matched by `is_blocker`.
+    pub fn visible<B>(&self, from: (F, F, F), to: (F, F, F), reach: F, is_blocker: B) -> bool
+    where
+        T: ExtentEntity<Item = F>,
+        B: Fn(&T) -> bool,
+    {
+        // Splitting the segment into pieces about as long as a cell, so that only the cells
+        // around the segment are visited rather than its whole bounding box
+        let (size_x, size_y) = (0..self.floors())
+            .map(|floor| self.floor_cell_sizes(floor))
+            .fold((F::infinity(), F::infinity()), |(x, y), (sx, sy)| {
+                (x.min(sx), y.min(sy))
+            });
+        let length = ((to.0 - from.0).powi(2) + (to.1 - from.1).powi(2)).sqrt();
+        let pieces = (length / size_x.min(size_y))
+            .ceil()
+            .to_usize()
+            .unwrap_or(1)
+            .max(1);
+
+        let at = |i: usize| {
+            let t = F::from_usize(i).unwrap() / F::from_usize(pieces).unwrap();
+            (
+                from.0 + (to.0 - from.0) * t,
+                from.1 + (to.1 - from.1) * t,
+                from.2 + (to.2 - from.2) * t,
+            )
+        };
+
+        let mut cells: Vec<_> = (0..pieces)
+            .flat_map(|i| {
+                let (a, b) = (at(i), at(i + 1));
+                let min = (
+                    a.0.min(b.0) - reach,
+                    a.1.min(b.1) - reach,
+                    a.2.min(b.2) - reach,
+                );
+                let max = (
+                    a.0.max(b.0) + reach,
+                    a.1.max(b.1) + reach,
+                    a.2.max(b.2) + reach,
+                );
+                self.cells_in_box(min, max)
+            })
+            .map(|(hashindex, floor)| (floor, hashindex.key()))
+            .collect();
+        cells.sort_unstable();
+        cells.dedup();
+
+        !cells.into_iter().any(|(floor, key)| {
+            self.grids[floor].bucket(&key).is_some_and(|bucket| {
+                bucket.iter().any(|(e, filing)| {
+                    is_blocker(e) && filing.extent(*e).intersects_segment(from, to)
+                })
+            })
+        })
+    }
+}
+
+/// Squared distance between the point `p` and the segment from `a` to `b`
+fn segment_distance_sq<F: Float>(p: (F, F), a: (F, F), b: (F, F)) -> F {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dy * dy;
+
+    // Projection of the point onto the segment, clamped onto its ends
+    let t = match length_sq > F::zero() {
+        true => (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_sq)
+            .max(F::zero())
+            .min(F::one()),
+        false => F::zero(),
+    };
+
+    let (cx, cy) = (a.0 + t * dx - p.0, a.1 + t * dy - p.1);
+    cx * cx + cy * cy
+}
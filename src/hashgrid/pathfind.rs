@@ -0,0 +1,320 @@
+use alloc::collections::{BTreeMap, BinaryHeap};
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::hash::{BuildHasher, Hash};
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+
+use super::grid::DataRef;
+use super::{Boundary, Coordinate, HashGrid};
+
+/// Cost of a straight and of a diagonal step between cells, scaled to stay integral
+const STRAIGHT: u64 = 10;
+const DIAGONAL: u64 = 14;
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Finds the shortest path of cells from the cell of the point `start` to the cell of the
+    /// point `goal` with A*, on the floor of the `start`.
+    ///
+    /// Paths move between the 8 neighbouring cells, without cutting the corners of blocked
+    /// cells. Cells are given as signed coordinates counted from the origin of the frame, unlike
+    /// the mirrored coordinates of the hash cells. `is_blocked` decides whether a cell can be
+    /// crossed from its coordinates and the entities located inside it, the cell of the `start`
+    /// being always crossable so that the entity moving does not block itself. Cells outside
+    /// the grid bounds and cells marked with [`HashGrid::mark_blocked`] are blocked.
+    ///
+    /// Returns the cells of the path from the `start` to the `goal` included, or `None` if the
+    /// goal cannot be reached. See [`HashGrid::pull_string`] to turn it into waypoints.
+    pub fn pathfind<B>(
+        &self,
+        start: (F, F, F),
+        goal: (F, F, F),
+        is_blocked: B,
+    ) -> Option<Vec<[i64; 2]>>
+    where
+        T: Coordinate<Item = F>,
+        B: Fn([i64; 2], &[DataRef<'a, T>]) -> bool,
+    {
+        let (_, _, floor) = self.get_cell_coordinates(self.clamp(start));
+        let (from, to) = (self.world_cell(floor, start), self.world_cell(floor, goal));
+
+        let mut blocked = CellCache::new(self, floor, &is_blocked);
+        if from != to && blocked.blocked(to) {
+            return None;
+        }
+
+        // Octile distance, the exact cost over free space
+        let heuristic = |cell: [i64; 2]| {
+            let (dx, dy) = (
+                (cell[0] - to[0]).unsigned_abs(),
+                (cell[1] - to[1]).unsigned_abs(),
+            );
+            STRAIGHT * dx.max(dy) + (DIAGONAL - STRAIGHT) * dx.min(dy)
+        };
+
+        let mut costs = BTreeMap::from([(from, 0)]);
+        let mut parents = BTreeMap::new();
+        let mut open = BinaryHeap::from([Reverse((heuristic(from), from))]);
+
+        while let Some(Reverse((_, cell))) = open.pop() {
+            if cell == to {
+                let mut path = Vec::from([cell]);
+                while let Some(&parent) = parents.get(path.last().unwrap()) {
+                    path.push(parent);
+                }
+                path.reverse();
+
+                return Some(path);
+            }
+
+            let cost = costs[&cell];
+
+            for (dx, dy) in NEIGHBOURS {
+                let next = [cell[0] + dx, cell[1] + dy];
+                if blocked.blocked(next) {
+                    continue;
+                }
+
+                let step = match dx != 0 && dy != 0 {
+                    // Diagonal steps may not cut the corner of a blocked cell
+                    true if blocked.blocked([cell[0] + dx, cell[1]])
+                        || blocked.blocked([cell[0], cell[1] + dy]) =>
+                    {
+                        continue
+                    }
+                    true => DIAGONAL,
+                    false => STRAIGHT,
+                };
+
+                let next_cost = cost + step;
+                if costs.get(&next).is_none_or(|&c| next_cost < c) {
+                    costs.insert(next, next_cost);
+                    parents.insert(next, cell);
+                    open.push(Reverse((next_cost + heuristic(next), next)));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Turns a path of cells found by [`HashGrid::pathfind`] on the `floor` into waypoints,
+    /// skipping every intermediate cell which can be reached in a straight line.
+    ///
+    /// A straight line between two cells is clear when none of the cells it crosses is blocked
+    /// according to `is_blocked`. Returns the world coordinates of the centres of the first
+    /// cell, the cells where the path turns, and the last cell.
+    pub fn pull_string<B>(&self, path: &[[i64; 2]], floor: usize, is_blocked: B) -> Vec<(F, F)>
+    where
+        T: Coordinate<Item = F>,
+        B: Fn([i64; 2], &[DataRef<'a, T>]) -> bool,
+    {
+        let mut blocked = CellCache::new(self, floor, &is_blocked);
+        let Some((&first, _)) = path.split_first() else {
+            return Vec::new();
+        };
+
+        let mut waypoints = Vec::from([first]);
+        let mut anchor = 0;
+
+        for i in 2..path.len() {
+            let clear = supercover(path[anchor], path[i])
+                .into_iter()
+                .skip(1)
+                .all(|cell| !blocked.blocked(cell));
+
+            if !clear {
+                anchor = i - 1;
+                waypoints.push(path[anchor]);
+            }
+        }
+
+        if path.len() > 1 {
+            waypoints.push(path[path.len() - 1]);
+        }
+
+        waypoints
+            .into_iter()
+            .map(|cell| self.cell_centre(floor, cell))
+            .collect()
+    }
+
+    /// Returns the world coordinates of the centre of the `cell` on the `floor`, the cell being
+    /// given as signed coordinates as in [`HashGrid::pathfind`]
+    pub fn cell_centre(&self, floor: usize, cell: [i64; 2]) -> (F, F) {
+        let (size_x, size_y) = self.floor_cell_sizes(floor);
+        let origin = self.origin();
+        let half = F::from_f64(0.5).unwrap();
+
+        (
+            origin.0 + (F::from_i64(cell[0]).unwrap() + half) * size_x,
+            origin.1 + (F::from_i64(cell[1]).unwrap() + half) * size_y,
+        )
+    }
+
+    /// Signed coordinates of the cell containing the point on the `floor`, the point being
+    /// clamped into the bounds first
+    pub(super) fn world_cell(&self, floor: usize, point: (F, F, F)) -> [i64; 2] {
+        let (size_x, size_y) = self.floor_cell_sizes(floor);
+        let (origin, point) = (self.origin(), self.clamp(point));
+
+        [
+            ((point.0 - origin.0) / size_x)
+                .floor()
+                .to_i64()
+                .unwrap_or(0),
+            ((point.1 - origin.1) / size_y)
+                .floor()
+                .to_i64()
+                .unwrap_or(0),
+        ]
+    }
+
+    /// Clamps the point into the grid bounds, the same way entities are wrapped on insertion
+    pub(super) fn clamp(&self, point: (F, F, F)) -> (F, F, F) {
+        let (min, max) = (self.bounds.min(), self.bounds.max());
+
+        (
+            point.0.max(min[0]).min(max[0]),
+            point.1.max(min[1]).min(max[1]),
+            point.2.max(min[2]).min(max[2]),
+        )
+    }
+}
+
+/// Offsets of the 8 neighbouring cells
+pub(super) const NEIGHBOURS: [(i64, i64); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+/// Values given by a callback to the cells of a floor, evaluated once per cell
+pub(super) struct CellCache<'g, 'a, F, T, Hx, S, B, R> {
+    grid: &'g HashGrid<'a, F, T, Hx, S>,
+    floor: usize,
+    evaluate: &'g B,
+    range: [[i64; 2]; 2],
+    cache: BTreeMap<[i64; 2], R>,
+    entities: Vec<DataRef<'a, T>>,
+}
+
+impl<'g, 'a, F, T, Hx, S, B, R> CellCache<'g, 'a, F, T, Hx, S, B, R>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+    T: Coordinate<Item = F>,
+    B: Fn([i64; 2], &[DataRef<'a, T>]) -> R,
+    R: Copy,
+{
+    pub(super) fn new(grid: &'g HashGrid<'a, F, T, Hx, S>, floor: usize, evaluate: &'g B) -> Self {
+        Self {
+            grid,
+            floor,
+            evaluate,
+            range: grid.floor_range(floor),
+            cache: BTreeMap::new(),
+            entities: Vec::new(),
+        }
+    }
+
+    /// Range of the signed cell coordinates within the grid bounds, both ends included
+    pub(super) fn range(&self) -> [[i64; 2]; 2] {
+        self.range
+    }
+
+    /// Value of the `cell`, or `None` if it lies outside the grid bounds or is blocked by
+    /// static terrain, see [`HashGrid::mark_blocked`]
+    pub(super) fn get(&mut self, cell: [i64; 2]) -> Option<R> {
+        let [lo, hi] = self.range;
+        if !(lo[0]..=hi[0]).contains(&cell[0])
+            || !(lo[1]..=hi[1]).contains(&cell[1])
+            || self.grid.cell_blocked(self.floor, cell)
+        {
+            return None;
+        }
+
+        if let Some(&value) = self.cache.get(&cell) {
+            return Some(value);
+        }
+
+        // Hash cells are mirrored around the origin, so only the entities located in this
+        // side of the hash cell are handed over
+        let grid = self.grid;
+        let key = grid.key(cell[0].unsigned_abs() as u32, cell[1].unsigned_abs() as u32);
+
+        self.entities.clear();
+        if let Some(bucket) = grid.grids[self.floor].bucket(&key.key()) {
+            self.entities.extend(
+                bucket
+                    .iter()
+                    .filter(|(&e, filing)| grid.world_cell(self.floor, filing.position(e)) == cell)
+                    .map(|(&e, _)| e),
+            );
+        }
+
+        let value = (self.evaluate)(cell, &self.entities);
+        self.cache.insert(cell, value);
+        Some(value)
+    }
+}
+
+impl<'a, F, T, Hx, S, B> CellCache<'_, 'a, F, T, Hx, S, B, bool>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+    T: Coordinate<Item = F>,
+    B: Fn([i64; 2], &[DataRef<'a, T>]) -> bool,
+{
+    /// Whether the `cell` is blocked, cells outside the grid bounds or marked blocked always
+    /// being blocked
+    fn blocked(&mut self, cell: [i64; 2]) -> bool {
+        self.get(cell).unwrap_or(true)
+    }
+}
+
+/// Every cell crossed by the straight line between the centres of the cells `a` and `b`, in
+/// order. Lines passing exactly through a corner cover both cells around it.
+fn supercover(a: [i64; 2], b: [i64; 2]) -> Vec<[i64; 2]> {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let (nx, ny) = (dx.abs(), dy.abs());
+    let (sx, sy) = (dx.signum(), dy.signum());
+
+    let mut cell = a;
+    let mut cells = Vec::from([cell]);
+    let (mut ix, mut iy) = (0, 0);
+
+    while ix < nx || iy < ny {
+        let decision = (1 + 2 * ix) * ny - (1 + 2 * iy) * nx;
+
+        if decision == 0 {
+            cells.push([cell[0] + sx, cell[1]]);
+            cells.push([cell[0], cell[1] + sy]);
+            cell = [cell[0] + sx, cell[1] + sy];
+            ix += 1;
+            iy += 1;
+        } else if decision < 0 {
+            cell[0] += sx;
+            ix += 1;
+        } else {
+            cell[1] += sy;
+            iy += 1;
+        }
+
+        cells.push(cell);
+    }
+
+    cells
+}
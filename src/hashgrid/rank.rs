@@ -0,0 +1,109 @@
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+use core::cmp::{Ordering, Reverse};
+use core::hash::{BuildHasher, Hash};
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+
+use super::grid::{DataRef, Spanning};
+use super::{Coordinate, HashGrid};
+use crate::geometry::Geometry;
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Finds the `top_n` entities inside the [`Geometry`] with the highest `score`, such as
+    /// the best target in range, along with their scores in descending order.
+    ///
+    /// Only the best entities found so far are kept in a bounded heap while the cells are
+    /// visited, instead of collecting every entity of the geometry and sorting them. Entities
+    /// scoring the same are ranked in the order the cells are visited in, and entities with
+    /// a `NaN` score are left out.
+    pub fn query_ranked<R>(
+        &self,
+        geometry: &Geometry<F>,
+        score: R,
+        top_n: usize,
+    ) -> Vec<(DataRef<'a, T>, f64)>
+    where
+        T: Coordinate<Item = F>,
+        R: Fn(&T) -> f64,
+    {
+        if top_n == 0 {
+            return Vec::new();
+        }
+
+        let (min, max) = geometry.bounding_box();
+
+        // Worst of the kept entities on top, to be replaced by better ones
+        let mut best = BinaryHeap::with_capacity(top_n + 1);
+        let mut spanning = Spanning::new();
+        let mut order = 0;
+
+        for (hashindex, floor) in self.cells_in_box(min, max) {
+            let Some(bucket) = self.grids[floor].bucket(&hashindex.key()) else {
+                continue;
+            };
+
+            for (&entity, filing) in bucket.iter() {
+                if !filing.inside(geometry, filing.position(entity))
+                    || !spanning.first(entity, filing)
+                {
+                    continue;
+                }
+
+                let score = score(entity);
+                if score.is_nan() {
+                    continue;
+                }
+
+                best.push(Reverse(Ranked {
+                    score,
+                    order,
+                    entity,
+                }));
+                if best.len() > top_n {
+                    best.pop();
+                }
+                order += 1;
+            }
+        }
+
+        best.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(ranked)| (ranked.entity, ranked.score))
+            .collect()
+    }
+}
+
+/// Entity ranked by its score, the earliest found coming first among equal scores
+struct Ranked<'a, T> {
+    score: f64,
+    order: usize,
+    entity: DataRef<'a, T>,
+}
+
+impl<T> PartialEq for Ranked<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Ranked<'_, T> {}
+
+impl<T> PartialOrd for Ranked<'_, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Ranked<'_, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .total_cmp(&other.score)
+            .then(other.order.cmp(&self.order))
+    }
+}
@@ -0,0 +1,170 @@
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::hash::{BuildHasher, Hash};
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+
+use super::grid::Spanning;
+use super::{CellId, Coordinate, HashGrid};
+use crate::geometry::Geometry;
+
+/// Population summary of a region, returned by [`HashGrid::region_stats`]
+///
+/// * `count:` Number of entities located inside the region
+/// * `centroid:` Mean position of the entities, weighted by their weights
+/// * `spread:` Weighted root mean square distance of the entities to the centroid
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegionStats<F> {
+    pub count: usize,
+    pub centroid: (F, F, F),
+    pub spread: F,
+}
+
+/// Densely populated cell, returned by [`HashGrid::hotspots`]
+///
+/// * `cell:` Cell in world space, see [`CellId`]
+/// * `bounds:` Area covered by the cell, see [`HashGrid::cell_bounds`]
+/// * `count:` Number of entities located inside the cell
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hotspot<F> {
+    pub cell: CellId,
+    pub bounds: Geometry<F>,
+    pub count: usize,
+}
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Summarizes the entities located inside the [`Geometry`], such as for an AI director
+    /// balancing the population of the world.
+    ///
+    /// The statistics are accumulated while visiting the cells, without collecting the
+    /// entities. Returns `None` if no entity lies inside the geometry.
+    pub fn region_stats(&self, region: &Geometry<F>) -> Option<RegionStats<F>>
+    where
+        T: Coordinate<Item = F>,
+    {
+        self.region_stats_by(region, |_| F::one())
+    }
+
+    /// Same as [`HashGrid::region_stats`], except that every entity weighs in on the centroid
+    /// and the spread according to its `weight`, such as its threat level.
+    ///
+    /// Returns `None` if the total weight of the entities inside the geometry is not positive.
+    pub fn region_stats_by<W>(&self, region: &Geometry<F>, mut weight: W) -> Option<RegionStats<F>>
+    where
+        T: Coordinate<Item = F>,
+        W: FnMut(&T) -> F,
+    {
+        let (min, max) = region.bounding_box();
+
+        // Running weighted mean and sum of squared distances to it, following the weighted
+        // variant of Welford's algorithm which stays accurate far away from the origin
+        let mut count = 0;
+        let mut total = F::zero();
+        let mut mean = (F::zero(), F::zero(), F::zero());
+        let mut m2 = F::zero();
+
+        // Entities spanning several floors are found once per floor visited
+        let mut spanning = Spanning::new();
+
+        for (hashindex, floor) in self.cells_in_box(min, max) {
+            let Some(bucket) = self.grids[floor].bucket(&hashindex.key()) else {
+                continue;
+            };
+
+            for (&e, filing) in bucket.iter() {
+                let (x, y, z) = filing.position(e);
+                if !filing.inside(region, (x, y, z)) || !spanning.first(e, filing) {
+                    continue;
+                }
+
+                count += 1;
+
+                let w = weight(e);
+                if w <= F::zero() {
+                    continue;
+                }
+                total = total + w;
+
+                let delta = (x - mean.0, y - mean.1, z - mean.2);
+                let ratio = w / total;
+                mean = (
+                    mean.0 + delta.0 * ratio,
+                    mean.1 + delta.1 * ratio,
+                    mean.2 + delta.2 * ratio,
+                );
+                m2 = m2
+                    + w * (delta.0 * (x - mean.0)
+                        + delta.1 * (y - mean.1)
+                        + delta.2 * (z - mean.2));
+            }
+        }
+
+        if total <= F::zero() {
+            return None;
+        }
+
+        Some(RegionStats {
+            count,
+            centroid: mean,
+            spread: (m2 / total).max(F::zero()).sqrt(),
+        })
+    }
+
+    /// Finds the `k` most populated cells of the grid along with their bounds and number of
+    /// entities, such as for matchmaking or to decide where to split the load of a server.
+    ///
+    /// Cells are counted in world space, the entities of a hash cell being split among the
+    /// mirrored cells they are located in. Cells holding as many entities are ranked by their
+    /// [`CellId`], so the result does not depend on the iteration order of the cells.
+    pub fn hotspots(&self, k: usize) -> Vec<Hotspot<F>>
+    where
+        T: Coordinate<Item = F>,
+    {
+        if k == 0 {
+            return Vec::new();
+        }
+
+        let mut cells = Vec::new();
+        for (floor, grid) in self.grids.iter().enumerate() {
+            for bucket in grid.values() {
+                // A hash cell covers at most four mirrored cells
+                let start = cells.len();
+                for (e, filing) in bucket.iter() {
+                    let [x, y] = self.world_cell(floor, filing.position(*e));
+                    let cell = CellId { floor, x, y };
+
+                    match cells[start..].iter_mut().find(|(c, _)| *c == cell) {
+                        Some((_, count)) => *count += 1,
+                        None => cells.push((cell, 1)),
+                    }
+                }
+            }
+        }
+
+        let densest = |a: &(CellId, usize), b: &(CellId, usize)| -> Ordering {
+            b.1.cmp(&a.1).then(a.0.cmp(&b.0))
+        };
+
+        if k < cells.len() {
+            cells.select_nth_unstable_by(k - 1, densest);
+            cells.truncate(k);
+        }
+        cells.sort_unstable_by(densest);
+
+        cells
+            .into_iter()
+            .map(|(cell, count)| Hotspot {
+                cell,
+                bounds: self.cell_bounds(cell.x, cell.y, cell.floor),
+                count,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,165 @@
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+
+use super::{Boundary, Coordinate, HashGrid};
+use crate::geometry::Geometry;
+use crate::math::distance;
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Finds up to `count` positions inside the `region` lying at least `min_separation` away
+    /// from the entities of the grid and from each other, such as spawn points avoiding overlaps.
+    ///
+    /// The part of the region within the grid bounds is split into square strata of side
+    /// `min_separation`, each giving at most one position at its centre. Strata are visited
+    /// row by row, and every candidate is checked against the entities of the cells around
+    /// it. Cells marked with [`HashGrid::mark_blocked`] give no position. Positions are taken
+    /// in the `x`, `y` plane of the centre of the region.
+    ///
+    /// Returns fewer positions than `count` if the region is too crowded, and none if the
+    /// region is invalid or the separation is not positive.
+    pub fn find_free_positions(
+        &self,
+        region: &Geometry<F>,
+        count: usize,
+        min_separation: F,
+    ) -> Vec<(F, F, F)>
+    where
+        T: Coordinate<Item = F>,
+    {
+        let Some((min, [nx, ny])) = self.strata(region, min_separation) else {
+            return Vec::new();
+        };
+
+        let half = min_separation / (F::one() + F::one());
+        let candidates = (0..ny)
+            .flat_map(|j| (0..nx).map(move |i| (i, j)))
+            .map(|(i, j)| {
+                (
+                    min.0 + F::from_u64(i).unwrap() * min_separation + half,
+                    min.1 + F::from_u64(j).unwrap() * min_separation + half,
+                )
+            });
+
+        self.free_positions(region, count, min_separation, candidates)
+    }
+
+    /// Same as [`HashGrid::find_free_positions`], except that the strata are visited in a
+    /// random order and each position is jittered anywhere within its stratum, so that
+    /// successive spawns do not line up.
+    ///
+    /// The order of every stratum is drawn up front, so the separation should stay coarse
+    /// compared to the region.
+    #[cfg(feature = "rand")]
+    pub fn find_free_positions_jittered<R: rand::Rng + ?Sized>(
+        &self,
+        region: &Geometry<F>,
+        count: usize,
+        min_separation: F,
+        rng: &mut R,
+    ) -> Vec<(F, F, F)>
+    where
+        T: Coordinate<Item = F>,
+    {
+        use rand::seq::SliceRandom;
+
+        let Some((min, [nx, ny])) = self.strata(region, min_separation) else {
+            return Vec::new();
+        };
+
+        let mut strata = (0..ny)
+            .flat_map(|j| (0..nx).map(move |i| (i, j)))
+            .collect::<Vec<_>>();
+        strata.shuffle(rng);
+
+        let mut offset = || F::from_f64(rng.gen::<f64>()).unwrap() * min_separation;
+        let candidates = strata.into_iter().map(|(i, j)| {
+            (
+                min.0 + F::from_u64(i).unwrap() * min_separation + offset(),
+                min.1 + F::from_u64(j).unwrap() * min_separation + offset(),
+            )
+        });
+
+        self.free_positions(region, count, min_separation, candidates)
+    }
+
+    /// Lower corner of the strata covering the part of the `region` within the grid bounds,
+    /// along with their number along the x and y axis, or `None` if there is nothing to cover
+    fn strata(&self, region: &Geometry<F>, min_separation: F) -> Option<((F, F), [u64; 2])> {
+        if !region.is_valid() || !min_separation.is_finite() || min_separation <= F::zero() {
+            return None;
+        }
+
+        let (rmin, rmax) = region.bounding_box();
+        let (bmin, bmax) = (self.bounds.min(), self.bounds.max());
+        let min = (rmin.0.max(bmin[0]), rmin.1.max(bmin[1]));
+        let max = (rmax.0.min(bmax[0]), rmax.1.min(bmax[1]));
+
+        if min.0 > max.0 || min.1 > max.1 {
+            return None;
+        }
+
+        let strata = |extent: F| {
+            (extent / min_separation)
+                .ceil()
+                .to_u64()
+                .unwrap_or(0)
+                .max(1)
+        };
+        Some((min, [strata(max.0 - min.0), strata(max.1 - min.1)]))
+    }
+
+    /// Keeps the `candidates` inside the `region` and clear of the entities and of the
+    /// positions kept before them, until `count` positions are found
+    fn free_positions<I>(
+        &self,
+        region: &Geometry<F>,
+        count: usize,
+        min_separation: F,
+        candidates: I,
+    ) -> Vec<(F, F, F)>
+    where
+        T: Coordinate<Item = F>,
+        I: Iterator<Item = (F, F)>,
+    {
+        let z = region.centre().2;
+        let (bmin, bmax) = (self.bounds.min(), self.bounds.max());
+        let within = |x: F, y: F| x >= bmin[0] && x <= bmax[0] && y >= bmin[1] && y <= bmax[1];
+
+        let mut positions: Vec<(F, F, F)> = Vec::new();
+
+        for (x, y) in candidates {
+            if positions.len() >= count {
+                break;
+            }
+
+            let point = (x, y, z);
+            if !within(x, y)
+                || !region.contains(point)
+                || self.is_blocked(point)
+                || positions
+                    .iter()
+                    .any(|&p| distance(p, point) < min_separation)
+            {
+                continue;
+            }
+
+            let crowded = self
+                .query_positioned(&Geometry::radius(point, min_separation))
+                .into_iter()
+                .any(|(_, position)| distance(position, point) < min_separation);
+
+            if !crowded {
+                positions.push(point);
+            }
+        }
+
+        positions
+    }
+}
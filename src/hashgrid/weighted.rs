@@ -0,0 +1,53 @@
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::hash::{BuildHasher, Hash};
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+
+use super::grid::DataRef;
+use super::{HashGrid, WeightedEntity};
+use crate::geometry::Geometry;
+use crate::math::distance;
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Queries the entities inside the [`Geometry`] nearest first, until their total
+    /// [`WeightedEntity::weight`] would exceed `max_total_weight`, such as the level of detail
+    /// affordable around a camera or the entities fitting in a network payload.
+    ///
+    /// Entities are ordered by their distance to the centre of the geometry, and the query
+    /// stops at the first entity which does not fit in the remaining budget, so that no entity
+    /// is returned while a nearer one is left out.
+    pub fn query_weighted(&self, geometry: &Geometry<F>, max_total_weight: F) -> Vec<DataRef<'a, T>>
+    where
+        T: WeightedEntity<Item = F>,
+    {
+        let centre = geometry.centre();
+
+        let mut candidates = self
+            .query_positioned(geometry)
+            .into_iter()
+            .map(|(e, position)| (distance(centre, position), e))
+            .collect::<Vec<_>>();
+        candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        let mut total = F::zero();
+        let mut results = Vec::new();
+        for (_, entity) in candidates {
+            // Negative and NaN weights count as zero
+            let weight = entity.weight().max(F::zero());
+            if total + weight > max_total_weight {
+                break;
+            }
+
+            total = total + weight;
+            results.push(entity);
+        }
+
+        results
+    }
+}
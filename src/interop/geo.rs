@@ -0,0 +1,146 @@
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+
+use geo_types::{Coord, CoordFloat, LineString, Point, Polygon, Rect};
+use num_traits::{FromPrimitive, PrimInt, ToPrimitive};
+
+use crate::geometry::Geometry;
+use crate::hashgrid::{Boundary, Coordinate, HashGrid};
+
+impl<T: CoordFloat> Coordinate for Coord<T> {
+    type Item = T;
+
+    fn x(&self) -> Self::Item {
+        self.x
+    }
+
+    fn y(&self) -> Self::Item {
+        self.y
+    }
+}
+
+impl<T: CoordFloat> Coordinate for Point<T> {
+    type Item = T;
+
+    fn x(&self) -> Self::Item {
+        self.0.x
+    }
+
+    fn y(&self) -> Self::Item {
+        self.0.y
+    }
+}
+
+impl<T: CoordFloat> From<Point<T>> for Geometry<T> {
+    fn from(point: Point<T>) -> Self {
+        Geometry::Point((point.x(), point.y(), T::zero()))
+    }
+}
+
+impl<T: CoordFloat> From<Rect<T>> for Geometry<T> {
+    fn from(rect: Rect<T>) -> Self {
+        let (min, max) = (rect.min(), rect.max());
+        Geometry::rect_from_corners((min.x, min.y, T::zero()), (max.x, max.y, T::zero()))
+    }
+}
+
+impl<'a, F, T, Hx, S> HashGrid<'a, F, T, Hx, S>
+where
+    F: CoordFloat + FromPrimitive + ToPrimitive,
+    Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+    S: BuildHasher,
+{
+    /// Queries the grid for every entity located inside the polygon, boundaries included.
+    ///
+    /// The cells overlapping the bounding rectangle of the polygon are visited and every
+    /// candidate is then tested against the exterior ring and the holes of the polygon. Only
+    /// the `x` and `y` components of the entities are taken into account.
+    pub fn query_polygon(&self, polygon: &Polygon<F>) -> Vec<&'a T>
+    where
+        T: Coordinate<Item = F>,
+    {
+        let Some(bounds) = bounding_rect(polygon.exterior()) else {
+            return Vec::new();
+        };
+
+        // Spanning every floor of the grid, since polygons are planar
+        let (min, max) = (bounds.min(), bounds.max());
+        let floor_min = self.bounds.min()[2];
+        let floor_max = self.bounds.max()[2];
+
+        let area =
+            Geometry::rect_from_corners((min.x, min.y, floor_min), (max.x, max.y, floor_max));
+
+        self.query_positioned(&area)
+            .into_iter()
+            .filter(|&(_, (x, y, _))| polygon_contains(polygon, x, y))
+            .map(|(e, _)| e)
+            .collect()
+    }
+}
+
+/// Bounding rectangle of the ring, `None` if the ring is empty
+fn bounding_rect<F: CoordFloat>(ring: &LineString<F>) -> Option<Rect<F>> {
+    let mut coords = ring.coords();
+    let first = *coords.next()?;
+
+    let (min, max) = coords.fold((first, first), |(min, max), c| {
+        (
+            Coord {
+                x: min.x.min(c.x),
+                y: min.y.min(c.y),
+            },
+            Coord {
+                x: max.x.max(c.x),
+                y: max.y.max(c.y),
+            },
+        )
+    });
+
+    Some(Rect::new(min, max))
+}
+
+/// Tests whether the point lies inside the exterior ring of the polygon and outside of
+/// all its holes
+fn polygon_contains<F: CoordFloat>(polygon: &Polygon<F>, x: F, y: F) -> bool {
+    ring_contains(polygon.exterior(), x, y)
+        && !polygon
+            .interiors()
+            .iter()
+            .any(|hole| ring_contains(hole, x, y) && !on_ring(hole, x, y))
+}
+
+/// Even-odd ray casting test of the point against a closed ring, points lying on the ring
+/// itself are considered inside
+fn ring_contains<F: CoordFloat>(ring: &LineString<F>, x: F, y: F) -> bool {
+    if on_ring(ring, x, y) {
+        return true;
+    }
+
+    let mut inside = false;
+    for line in ring.lines() {
+        let (a, b) = (line.start, line.end);
+        if (a.y > y) != (b.y > y) {
+            let cross_x = a.x + (y - a.y) * (b.x - a.x) / (b.y - a.y);
+            if x < cross_x {
+                inside = !inside;
+            }
+        }
+    }
+
+    inside
+}
+
+/// Tests whether the point lies on one of the segments of the ring
+fn on_ring<F: CoordFloat>(ring: &LineString<F>, x: F, y: F) -> bool {
+    ring.lines().any(|line| {
+        let (a, b) = (line.start, line.end);
+        let cross = (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x);
+
+        cross == F::zero()
+            && x >= a.x.min(b.x)
+            && x <= a.x.max(b.x)
+            && y >= a.y.min(b.y)
+            && y <= a.y.max(b.y)
+    })
+}
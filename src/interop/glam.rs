@@ -0,0 +1,78 @@
+use glam::{DVec2, DVec3, Vec2, Vec3};
+
+use crate::geometry::Geometry;
+use crate::hashgrid::{Boundary, Coordinate};
+
+macro_rules! impl_glam_vectors(
+    ( $( $vec2:ident, $vec3:ident => $float:ident ),* ) => {
+        $(
+            impl Coordinate for $vec2 {
+                type Item = $float;
+
+                fn x(&self) -> Self::Item {
+                    self.x
+                }
+
+                fn y(&self) -> Self::Item {
+                    self.y
+                }
+            }
+
+            impl Coordinate for $vec3 {
+                type Item = $float;
+
+                fn x(&self) -> Self::Item {
+                    self.x
+                }
+
+                fn y(&self) -> Self::Item {
+                    self.y
+                }
+
+                fn z(&self) -> Self::Item {
+                    self.z
+                }
+            }
+
+            impl From<$vec2> for Geometry<$float> {
+                fn from(point: $vec2) -> Self {
+                    Geometry::Point((point.x, point.y, 0.0))
+                }
+            }
+
+            impl From<$vec3> for Geometry<$float> {
+                fn from(point: $vec3) -> Self {
+                    Geometry::Point(point.into())
+                }
+            }
+
+            /// Grid bounds given as the `(centre, size)` pair of vectors
+            impl Boundary for ($vec2, $vec2) {
+                type Item = $float;
+
+                fn centre(&self) -> [Self::Item; 3] {
+                    [self.0.x, self.0.y, 0.0]
+                }
+
+                fn size(&self) -> [Self::Item; 3] {
+                    [self.1.x, self.1.y, 0.0]
+                }
+            }
+
+            /// Grid bounds given as the `(centre, size)` pair of vectors
+            impl Boundary for ($vec3, $vec3) {
+                type Item = $float;
+
+                fn centre(&self) -> [Self::Item; 3] {
+                    self.0.to_array()
+                }
+
+                fn size(&self) -> [Self::Item; 3] {
+                    self.1.to_array()
+                }
+            }
+        )*
+    };
+);
+
+impl_glam_vectors!(Vec2, Vec3 => f32, DVec2, DVec3 => f64);
@@ -0,0 +1,11 @@
+//! Integrations with third party math and geometry crates, each one gated behind the
+//! cargo feature of the same name.
+
+#[cfg(feature = "glam")]
+mod glam;
+
+#[cfg(feature = "nalgebra")]
+mod nalgebra;
+
+#[cfg(feature = "geo")]
+mod geo;
@@ -0,0 +1,92 @@
+use nalgebra::{Point2, Point3, Scalar, Vector2, Vector3};
+use num_traits::{Float, FromPrimitive, ToPrimitive};
+
+use crate::geometry::Geometry;
+use crate::hashgrid::{Boundary, Coordinate};
+
+macro_rules! impl_nalgebra_2d(
+    ( $( $ty:ident ),* ) => {
+        $(
+            impl<T: Scalar + Float> Coordinate for $ty<T> {
+                type Item = T;
+
+                fn x(&self) -> Self::Item {
+                    self.x
+                }
+
+                fn y(&self) -> Self::Item {
+                    self.y
+                }
+            }
+
+            impl<T: Scalar + Float> From<$ty<T>> for Geometry<T> {
+                fn from(point: $ty<T>) -> Self {
+                    Geometry::Point((point.x, point.y, T::zero()))
+                }
+            }
+
+            /// Grid bounds given as the `(centre, size)` pair
+            impl<T> Boundary for ($ty<T>, Vector2<T>)
+            where
+                T: Scalar + Float + FromPrimitive + ToPrimitive,
+            {
+                type Item = T;
+
+                fn centre(&self) -> [Self::Item; 3] {
+                    [self.0.x, self.0.y, T::zero()]
+                }
+
+                fn size(&self) -> [Self::Item; 3] {
+                    [self.1.x, self.1.y, T::zero()]
+                }
+            }
+        )*
+    };
+);
+
+macro_rules! impl_nalgebra_3d(
+    ( $( $ty:ident ),* ) => {
+        $(
+            impl<T: Scalar + Float> Coordinate for $ty<T> {
+                type Item = T;
+
+                fn x(&self) -> Self::Item {
+                    self.x
+                }
+
+                fn y(&self) -> Self::Item {
+                    self.y
+                }
+
+                fn z(&self) -> Self::Item {
+                    self.z
+                }
+            }
+
+            impl<T: Scalar + Float> From<$ty<T>> for Geometry<T> {
+                fn from(point: $ty<T>) -> Self {
+                    Geometry::Point((point.x, point.y, point.z))
+                }
+            }
+
+            /// Grid bounds given as the `(centre, size)` pair
+            impl<T> Boundary for ($ty<T>, Vector3<T>)
+            where
+                T: Scalar + Float + FromPrimitive + ToPrimitive,
+            {
+                type Item = T;
+
+                fn centre(&self) -> [Self::Item; 3] {
+                    [self.0.x, self.0.y, self.0.z]
+                }
+
+                fn size(&self) -> [Self::Item; 3] {
+                    [self.1.x, self.1.y, self.1.z]
+                }
+            }
+        )*
+    };
+);
+
+impl_nalgebra_2d!(Point2, Vector2);
+impl_nalgebra_3d!(Point3, Vector3);
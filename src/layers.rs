@@ -0,0 +1,58 @@
+use core::ops::{BitAnd, BitOr, Not};
+
+/// Bitmask of up to 32 layers an entity belongs to, such as the collision layers of a
+/// physics engine (players, projectiles, terrain, ..).
+///
+/// Entities are inserted with a mask through `insert_with_layers`, and layered queries only
+/// return the entities sharing at least one layer with the mask of the query. Entities
+/// inserted without a mask belong to every layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayerMask(pub u32);
+
+impl LayerMask {
+    /// Mask of every layer
+    pub const ALL: Self = Self(u32::MAX);
+
+    /// Mask without any layer, matching nothing
+    pub const NONE: Self = Self(0);
+
+    /// Mask of the single layer at the `index`, in the range `0..32`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `index` is 32 or above, or fails to compile when evaluated in a constant.
+    pub const fn layer(index: u32) -> Self {
+        assert!(index < 32, "The layer index must be in the range 0..32");
+        Self(1 << index)
+    }
+
+    /// Tests whether the two masks share at least one layer
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for LayerMask {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for LayerMask {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl Not for LayerMask {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        Self(!self.0)
+    }
+}
@@ -1,4 +1,52 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub use aggregate::Entities;
+pub use composite::GeometryGroup;
+pub use entity::SpatialEntity;
+pub use error::SpatialError;
+pub use field::ScalarField;
+pub use geofence::{Geofence, GeofenceEvent};
+pub use geometry::Geometry;
 pub use hashgrid::{Boundary, DataIndex, HashGrid, HashIndex};
+pub use layers::LayerMask;
+pub use linear::LinearIndex;
+pub use manager::{Backend, InterestEvent, InterestManager, Subscriber};
+pub use memory::MemoryFootprint;
+pub use observer::{Observed, Observer};
+pub use partition::{SpatialInsertion, SpatialQuery, SpatialRemoval, SpatialUpdate};
+pub use relevance::{Falloff, Priority, RelevanceProfile, Tiers};
+pub use shards::GridShards;
+#[cfg(feature = "stats")]
+pub use stats::QueryStats;
+pub use tiered::TieredIndex;
+pub use trajectory::TrajectoryIndex;
 
+pub mod aggregate;
+#[cfg(feature = "bench-utils")]
+pub mod bench_utils;
+pub mod composite;
+pub mod entity;
+pub mod error;
+pub mod field;
+pub mod geofence;
+pub mod geometry;
 pub mod hashgrid;
+mod interop;
+pub mod layers;
+pub mod linear;
+pub mod manager;
+pub mod math;
+pub mod memory;
+pub mod observer;
+pub mod partition;
+pub mod relevance;
+pub mod shards;
+#[cfg(feature = "stats")]
+pub mod stats;
+#[cfg(feature = "testing")]
+pub mod testing;
 mod tests;
+pub mod tiered;
+pub mod trajectory;
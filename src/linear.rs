@@ -0,0 +1,315 @@
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+use core::{marker::PhantomData, mem::size_of, ptr};
+
+use core::hash::{BuildHasher, Hash};
+
+use num_traits::{Float, FromPrimitive, PrimInt, ToPrimitive};
+
+use crate::geometry::Geometry;
+use crate::hashgrid::{CellOrder, Coordinate, DataIndex, Entity, HashGrid};
+use crate::memory::MemoryFootprint;
+use crate::partition::{Positioned, SpatialInsertion, SpatialQuery, SpatialRemoval, SpatialUpdate};
+#[cfg(feature = "stats")]
+use crate::stats::QueryStats;
+#[cfg(feature = "stats")]
+use core::cell::Cell;
+
+/// # Linear Index
+///
+/// The simplest spatial structure possible: a flat list of references to the data, where every
+/// query tests every item. It has no partitioning overhead at all, which makes it the fastest
+/// backend for very small worlds, and an exact reference for validating the other structures.
+///
+/// LinearIndex is parameterized over:
+///
+/// * `F (Float type):` Defines the base float type such as `f32` or `f64` for spatial components
+/// * `T (generic data type):` Defines the data type to insert into the index, data must live as long as the index lives
+#[derive(Debug)]
+pub struct LinearIndex<'a, F, T> {
+    data: Vec<&'a T>,
+    /// Coordinates each entity was moved to, overriding the ones it reports, see
+    /// [`LinearIndex::relocate`]
+    positions: Vec<Option<(F, F, F)>>,
+    /// Whether any entity was relocated, queries test the reported coordinates in bulk until then
+    relocated: bool,
+    _float: PhantomData<F>,
+    #[cfg(feature = "stats")]
+    last_stats: Cell<QueryStats>,
+}
+
+impl<F, T> Default for LinearIndex<'_, F, T> {
+    fn default() -> Self {
+        Self {
+            data: Vec::new(),
+            positions: Vec::new(),
+            relocated: false,
+            _float: PhantomData,
+            #[cfg(feature = "stats")]
+            last_stats: Cell::new(QueryStats::default()),
+        }
+    }
+}
+
+impl<'a, F, T> LinearIndex<'a, F, T>
+where
+    F: Float,
+{
+    /// Creates an empty [`LinearIndex`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a [`LinearIndex`] holding every entity of the `grid`, e.g. to switch a world
+    /// which emptied out over to the cheaper backend at runtime.
+    ///
+    /// Entities are listed cell by cell in row major order, so the result does not depend on
+    /// the iteration order of the grid cells. Entities moved with [`HashGrid::relocate`] keep
+    /// the coordinates they were relocated to.
+    pub fn from_hashgrid<Hx, S>(grid: &HashGrid<'a, F, T, Hx, S>) -> Self
+    where
+        F: FromPrimitive + ToPrimitive,
+        Hx: PrimInt + FromPrimitive + ToPrimitive + Hash,
+        S: BuildHasher,
+    {
+        let relocations: BTreeMap<*const T, (F, F, F)> = grid
+            .relocations()
+            .map(|(entity, position)| (ptr::from_ref(entity), position))
+            .collect();
+
+        let mut index = Self::new();
+        for cell in grid.cells_in_order(CellOrder::RowMajor) {
+            for &entity in cell.data {
+                index.data.push(entity);
+                index
+                    .positions
+                    .push(relocations.get(&ptr::from_ref(entity)).copied());
+            }
+        }
+        index.relocated = !relocations.is_empty();
+        index
+    }
+
+    /// Appends the reference to the data into the index
+    pub fn insert(&mut self, entity: &'a T) {
+        self.data.push(entity);
+        self.positions.push(None);
+    }
+
+    /// Removes the data matching the `id` from the index.
+    ///
+    /// Returns `true` if the entity was found and removed.
+    pub fn remove<Id>(&mut self, id: Id) -> bool
+    where
+        Id: DataIndex,
+        T: Entity<ID = Id>,
+    {
+        match self.data.iter().position(|d| d.id() == id) {
+            Some(index) => {
+                self.data.remove(index);
+                self.positions.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves the data matching the `id` to the `coordinates`, which the queries then test in
+    /// place of the ones it reports through [`Coordinate`]. The relocated coordinates persist
+    /// until the entity is relocated again or removed.
+    ///
+    /// Returns `true` if the entity was found and relocated, coordinates with a non-finite
+    /// component are rejected.
+    pub fn relocate<Id>(&mut self, id: Id, coordinates: (F, F, F)) -> bool
+    where
+        Id: DataIndex,
+        T: Entity<ID = Id>,
+    {
+        if !(coordinates.0.is_finite() && coordinates.1.is_finite() && coordinates.2.is_finite()) {
+            return false;
+        }
+
+        match self.data.iter().position(|d| d.id() == id) {
+            Some(index) => {
+                self.positions[index] = Some(coordinates);
+                self.relocated = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Iterates over every entity along with the coordinates the queries test it at, the ones
+    /// it was relocated to if any, in insertion order
+    pub fn iter_all(&self) -> impl Iterator<Item = (&'a T, (F, F, F))> + '_
+    where
+        T: Coordinate<Item = F>,
+    {
+        self.data
+            .iter()
+            .zip(&self.positions)
+            .map(|(&e, position)| (e, position.unwrap_or((e.x(), e.y(), e.z()))))
+    }
+
+    /// Returns every entity located inside the [`Geometry`], in insertion order
+    pub fn query_geometry(&self, geometry: &Geometry<F>) -> Vec<&'a T>
+    where
+        T: Coordinate<Item = F>,
+    {
+        let mut results = Vec::new();
+        self.query_into(geometry, &mut results);
+        results
+    }
+
+    /// Same as [`LinearIndex::query_geometry`], except that the entities are written into the
+    /// `results` buffer, which is cleared first
+    pub fn query_into(&self, geometry: &Geometry<F>, results: &mut Vec<&'a T>)
+    where
+        T: Coordinate<Item = F>,
+    {
+        results.clear();
+
+        #[cfg(feature = "stats")]
+        let candidates_tested = self.data.len();
+
+        if !self.relocated {
+            #[cfg(feature = "simd")]
+            crate::geometry::extend_contained(geometry, &self.data, results);
+
+            #[cfg(not(feature = "simd"))]
+            results.extend(
+                self.data
+                    .iter()
+                    .filter(|e| geometry.contains((e.x(), e.y(), e.z())))
+                    .copied(),
+            );
+        } else {
+            results.extend(
+                self.iter_all()
+                    .filter(|&(_, position)| geometry.contains(position))
+                    .map(|(e, _)| e),
+            );
+        }
+
+        #[cfg(feature = "stats")]
+        self.last_stats.set(QueryStats {
+            cells_visited: 1,
+            cells_occupied: usize::from(candidates_tested > 0),
+            candidates_tested,
+            results: results.len(),
+        });
+    }
+
+    /// Returns the counters collected by the latest geometry query on the index, where the
+    /// whole index counts as a single cell
+    #[cfg(feature = "stats")]
+    pub fn last_query_stats(&self) -> QueryStats {
+        self.last_stats.get()
+    }
+
+    /// Returns every entity located inside the `include` geometry but outside the `exclude`
+    /// one, in insertion order
+    pub fn query_difference(&self, include: &Geometry<F>, exclude: &Geometry<F>) -> Vec<&'a T>
+    where
+        T: Coordinate<Item = F>,
+    {
+        self.iter_all()
+            .filter(|&(_, point)| include.contains(point) && !exclude.contains(point))
+            .map(|(e, _)| e)
+            .collect()
+    }
+
+    /// Returns the references to every entity of the index, in insertion order
+    pub fn data(&self) -> &[&'a T] {
+        &self.data
+    }
+
+    /// Coordinates each entity of [`LinearIndex::data`] was relocated to, if any
+    pub(crate) fn positions(&self) -> &[Option<(F, F, F)>] {
+        &self.positions
+    }
+
+    /// Total number of entities in the index
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Reports the memory used by the index, see [`MemoryFootprint`] for the breakdown
+    pub fn memory_footprint(&self) -> MemoryFootprint {
+        MemoryFootprint {
+            structure: size_of::<Self>(),
+            buckets: 0,
+            items: self.data.capacity() * size_of::<&'a T>()
+                + self.positions.capacity() * size_of::<Option<(F, F, F)>>(),
+        }
+    }
+}
+
+impl<'a, F, T> SpatialInsertion for LinearIndex<'a, F, T>
+where
+    F: Float,
+{
+    type Item = &'a T;
+
+    fn insert(&mut self, item: Self::Item) {
+        LinearIndex::insert(self, item)
+    }
+}
+
+impl<'a, F, T> SpatialQuery for LinearIndex<'a, F, T>
+where
+    F: Float,
+    T: Coordinate<Item = F>,
+{
+    type Item = &'a T;
+    type Scalar = F;
+    type Results = vec::IntoIter<&'a T>;
+
+    fn query(&self, geometry: &Geometry<F>) -> Self::Results {
+        self.query_geometry(geometry).into_iter()
+    }
+
+    fn query_into(&self, geometry: &Geometry<F>, results: &mut Vec<Self::Item>) {
+        LinearIndex::query_into(self, geometry, results)
+    }
+
+    fn query_positioned_into(
+        &self,
+        geometry: &Geometry<F>,
+        results: &mut Vec<Positioned<Self::Item, F>>,
+    ) {
+        results.clear();
+        results.extend(
+            self.iter_all()
+                .filter(|&(_, position)| geometry.contains(position)),
+        );
+    }
+}
+
+impl<F, T> SpatialRemoval for LinearIndex<'_, F, T>
+where
+    F: Float,
+    T: Entity,
+{
+    type Id = T::ID;
+
+    fn remove(&mut self, id: Self::Id) -> bool {
+        LinearIndex::remove(self, id)
+    }
+}
+
+impl<F, T> SpatialUpdate for LinearIndex<'_, F, T>
+where
+    F: Float,
+    T: Entity,
+{
+    type Id = T::ID;
+    type Position = (F, F, F);
+
+    fn relocate(&mut self, id: Self::Id, position: Self::Position) -> bool {
+        LinearIndex::relocate(self, id, position)
+    }
+}
@@ -0,0 +1,515 @@
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
+use alloc::vec::Vec;
+
+use num_traits::{Float, FromPrimitive, ToPrimitive};
+
+use crate::geometry::Geometry;
+use crate::hashgrid::{Boundary, Coordinate, DataIndex, Entity, GridBoundary, HashGrid};
+use crate::linear::LinearIndex;
+use crate::math::distance_sq;
+use crate::partition::{SpatialInsertion, SpatialQuery};
+
+/// Relevant entities of a subscriber along with the changes of the latest update
+#[derive(Debug)]
+struct Interest<Id> {
+    relevant: BTreeSet<Id>,
+    entered: Vec<Id>,
+    exited: Vec<Id>,
+}
+
+impl<Id: DataIndex> Interest<Id> {
+    fn new() -> Self {
+        Self {
+            relevant: BTreeSet::new(),
+            entered: Vec::new(),
+            exited: Vec::new(),
+        }
+    }
+
+    /// Diffs the newly relevant entities against the current ones and stores the result
+    fn apply(&mut self, relevant: BTreeSet<Id>) {
+        self.entered = relevant.difference(&self.relevant).copied().collect();
+        self.exited = self.relevant.difference(&relevant).copied().collect();
+        self.relevant = relevant;
+    }
+
+    fn as_set(&self, subscriber: Subscriber<Id>) -> InterestSet<'_, Id> {
+        InterestSet {
+            subscriber,
+            entered: &self.entered,
+            exited: &self.exited,
+            relevant: &self.relevant,
+        }
+    }
+}
+
+/// State kept by the [`InterestManager`] for every subscribed observer
+#[derive(Debug)]
+struct Observer<F, Id> {
+    position: (F, F, F),
+    radius: F,
+    interest: Interest<Id>,
+    /// Distances to the relevant entities as of the latest update
+    distances: BTreeMap<Id, F>,
+    /// Number of updates since each relevant entity was last replicated
+    ages: BTreeMap<Id, u64>,
+}
+
+/// State kept by the [`InterestManager`] for every subscribed static region
+#[derive(Debug)]
+struct Region<F, Id> {
+    geometry: Geometry<F>,
+    interest: Interest<Id>,
+}
+
+/// Subscriber of the [`InterestManager`] an [`InterestSet`] belongs to.
+///
+/// Observers and regions are registered separately and may share ids, the variant tells
+/// them apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Subscriber<Id> {
+    /// Observer registered through [`InterestManager::subscribe`]
+    Observer(Id),
+    /// Static region registered through [`InterestManager::subscribe_region`]
+    Region(Id),
+}
+
+impl<Id: Copy> Subscriber<Id> {
+    /// Returns the id the subscriber was registered with
+    pub fn id(&self) -> Id {
+        match *self {
+            Subscriber::Observer(id) | Subscriber::Region(id) => id,
+        }
+    }
+}
+
+/// Interest of a single subscriber as computed by the latest [`InterestManager::update`].
+///
+/// It lists the entities which became relevant (`entered`) or stopped being relevant
+/// (`exited`) for the subscriber during the last update, along with the complete set of
+/// entities currently relevant to it.
+#[derive(Debug, Clone, Copy)]
+pub struct InterestSet<'m, Id> {
+    pub subscriber: Subscriber<Id>,
+    pub entered: &'m [Id],
+    pub exited: &'m [Id],
+    pub relevant: &'m BTreeSet<Id>,
+}
+
+/// Change in the interest of an observer, queued by [`InterestManager::update`] and
+/// consumed through [`InterestManager::poll_events`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InterestEvent<Id> {
+    /// The entity became relevant to the observer
+    Entered { observer: Id, entity: Id },
+    /// The entity is no longer relevant to the observer
+    Exited { observer: Id, entity: Id },
+    /// The entity moved inside the region
+    RegionEntered { region: Id, entity: Id },
+    /// The entity moved out of the region
+    RegionExited { region: Id, entity: Id },
+}
+
+/// Spatial structure used by the [`InterestManager`] to index the entities on every update.
+///
+/// * `HashGrid:` Indexes the entities in a [`HashGrid`] with the given cells and floors, best
+///   suited for large and populated worlds
+/// * `Linear:` Tests every entity against every subscriber through a [`LinearIndex`], which
+///   has no partitioning overhead and wins for very small worlds
+///
+/// Structures maintained outside of the manager can be used as well through
+/// [`InterestManager::update_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Backend {
+    HashGrid {
+        cells: [u32; 2],
+        floors: usize,
+        wrap: bool,
+    },
+    Linear,
+}
+
+/// # Interest Manager
+///
+/// Relevance filtering for multiplayer worlds: given a set of observers (players, cameras, ..)
+/// and the entities of the world, the manager keeps track of which entities are relevant
+/// to which observer.
+///
+/// Observers are registered with [`InterestManager::subscribe`] along with their position
+/// and the radius of their interest in world units. Every tick, the entities of the world
+/// are fed into [`InterestManager::update`], which indexes them in the configured [`Backend`]
+/// and diffs the relevant entities of every observer against the previous tick. The resulting
+/// enter and exit changes are available through [`InterestManager::interest_sets`].
+///
+/// The same changes are also queued as [`InterestEvent`]s, which can be drained with
+/// [`InterestManager::poll_events`]. A hysteresis margin can be configured through
+/// [`InterestManager::set_hysteresis`] so that entities oscillating on the edge of an
+/// observer's radius do not flood the queue with enter/exit pairs.
+///
+/// Static zones (cities, capture points, ..) can be registered with
+/// [`InterestManager::subscribe_region`] and are notified about the entities crossing them
+/// through the same diffing machinery. Region ids live in their own namespace, separate
+/// from the observer ids.
+///
+/// An observer never receives itself as a relevant entity, so players can be registered
+/// as observers with the same id they have as entities.
+#[derive(Debug)]
+pub struct InterestManager<F, Id> {
+    backend: Backend,
+    bounds: GridBoundary<F>,
+    hysteresis: F,
+    observers: BTreeMap<Id, Observer<F, Id>>,
+    regions: BTreeMap<Id, Region<F, Id>>,
+    events: VecDeque<InterestEvent<Id>>,
+}
+
+impl<F, Id> InterestManager<F, Id>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    Id: DataIndex,
+{
+    /// Creates a new [`InterestManager`] without any observers, backed by a [`HashGrid`].
+    ///
+    /// The parameters are the same as for [`HashGrid::new`] and define the grid used to index
+    /// the entities on every update.
+    pub fn new<B>(cells: [u32; 2], floors: usize, bounds: &B, wrap: bool) -> Self
+    where
+        B: Boundary<Item = F>,
+    {
+        Self::with_backend(
+            Backend::HashGrid {
+                cells,
+                floors,
+                wrap,
+            },
+            bounds,
+        )
+    }
+
+    /// Creates a new [`InterestManager`] without any observers, indexing the entities of
+    /// the world within the `bounds` with the given [`Backend`].
+    pub fn with_backend<B>(backend: Backend, bounds: &B) -> Self
+    where
+        B: Boundary<Item = F>,
+    {
+        Self {
+            backend,
+            bounds: GridBoundary {
+                center: bounds.centre(),
+                size: bounds.size(),
+            },
+            hysteresis: F::zero(),
+            observers: BTreeMap::new(),
+            regions: BTreeMap::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Sets the hysteresis margin in world units.
+    ///
+    /// An entity enters the interest of an observer once it is within the observer's radius,
+    /// but only exits once it is further than the radius plus the margin. Negative margins
+    /// are treated as zero.
+    pub fn set_hysteresis(&mut self, margin: F) {
+        self.hysteresis = margin.max(F::zero());
+    }
+
+    /// Returns the hysteresis margin in world units
+    pub fn hysteresis(&self) -> F {
+        self.hysteresis
+    }
+
+    /// Returns the backend used to index the entities on every update
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Switches the manager over to another [`Backend`], e.g. when the density of the world
+    /// changes at runtime.
+    ///
+    /// The manager keeps no index of the entities between updates, so nothing is rebuilt: the
+    /// next update simply indexes the world with the new backend. Observers, regions and their
+    /// current interest are carried across unchanged, so that update only reports the changes
+    /// which actually happened in the world, and not the migration itself.
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+    }
+
+    /// Registers an observer at the `position` interested in every entity within the
+    /// `radius`, defined in world units.
+    ///
+    /// Subscribing an already registered observer only updates its position and radius,
+    /// its current interest is kept.
+    pub fn subscribe(&mut self, observer: Id, position: (F, F, F), radius: F) {
+        self.observers
+            .entry(observer)
+            .and_modify(|o| {
+                o.position = position;
+                o.radius = radius;
+            })
+            .or_insert_with(|| Observer {
+                position,
+                radius,
+                interest: Interest::new(),
+                distances: BTreeMap::new(),
+                ages: BTreeMap::new(),
+            });
+    }
+
+    /// Removes the observer from the manager.
+    ///
+    /// Returns `true` if the observer was subscribed.
+    pub fn unsubscribe(&mut self, observer: Id) -> bool {
+        self.observers.remove(&observer).is_some()
+    }
+
+    /// Moves an already subscribed observer to a new position, the change is taken into
+    /// account on the next update.
+    ///
+    /// Returns `true` if the observer is subscribed.
+    pub fn move_observer(&mut self, observer: Id, position: (F, F, F)) -> bool {
+        match self.observers.get_mut(&observer) {
+            Some(o) => {
+                o.position = position;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns `true` if the observer is subscribed to the manager
+    pub fn is_subscribed(&self, observer: Id) -> bool {
+        self.observers.contains_key(&observer)
+    }
+
+    /// Registers a static region notified about every entity located inside its geometry.
+    ///
+    /// Subscribing an already registered region only replaces its geometry, its current
+    /// interest is kept.
+    pub fn subscribe_region(&mut self, region: Id, geometry: Geometry<F>) {
+        self.regions
+            .entry(region)
+            .and_modify(|r| r.geometry = geometry)
+            .or_insert_with(|| Region {
+                geometry,
+                interest: Interest::new(),
+            });
+    }
+
+    /// Removes the region from the manager.
+    ///
+    /// Returns `true` if the region was subscribed.
+    pub fn unsubscribe_region(&mut self, region: Id) -> bool {
+        self.regions.remove(&region).is_some()
+    }
+
+    /// Recomputes the interest of every observer from the current state of the world.
+    ///
+    /// The entities are indexed in the configured [`Backend`], then for every subscriber the
+    /// entities within its radius or region are diffed against the interest computed in the
+    /// previous update. Every change is queued as an [`InterestEvent`].
+    pub fn update<T>(&mut self, entities: &[T])
+    where
+        T: Coordinate<Item = F> + Entity<ID = Id>,
+    {
+        match self.backend {
+            Backend::HashGrid {
+                cells,
+                floors,
+                wrap,
+            } => {
+                let mut grid = HashGrid::<F, T>::new(cells, floors, &self.bounds, wrap);
+                grid.update(entities);
+                self.update_with(&grid);
+            }
+            Backend::Linear => {
+                let mut index = LinearIndex::<F, T>::new();
+                index.insert_all(entities);
+                self.update_with(&index);
+            }
+        }
+    }
+
+    /// Recomputes the interest of every observer from a spatial structure maintained outside
+    /// of the manager, such as a long lived [`HashGrid`] updated incrementally.
+    ///
+    /// This works the same way as [`InterestManager::update`], except that the configured
+    /// [`Backend`] is bypassed.
+    pub fn update_with<'e, S, T>(&mut self, structure: &S)
+    where
+        S: SpatialQuery<Item = &'e T, Scalar = F>,
+        T: Coordinate<Item = F> + Entity<ID = Id> + 'e,
+    {
+        // Candidates buffer shared by every query of the update
+        let mut candidates = Vec::new();
+
+        for (&id, observer) in self.observers.iter_mut() {
+            // Entities which are already relevant are kept until they leave the
+            // radius extended by the hysteresis margin
+            let enter_radius = observer.radius;
+            let exit_radius = observer.radius + self.hysteresis;
+
+            let enter_sq = enter_radius * enter_radius;
+            let exit_sq = exit_radius * exit_radius;
+            // Entities are measured at the position the structure tested them at, which
+            // differs from their reported coordinates once they were relocated
+            structure.query_positioned_into(
+                &Geometry::radius(observer.position, exit_radius),
+                &mut candidates,
+            );
+            let relevant = candidates
+                .iter()
+                .filter(|(e, _)| e.id() != id)
+                .filter(|&&(e, position)| {
+                    let limit = if observer.interest.relevant.contains(&e.id()) {
+                        exit_sq
+                    } else {
+                        enter_sq
+                    };
+                    distance_sq(observer.position, position) <= limit
+                })
+                .map(|&(e, position)| (e.id(), distance_sq(observer.position, position).sqrt()))
+                .collect::<BTreeMap<_, _>>();
+
+            observer.interest.apply(relevant.keys().copied().collect());
+            observer.distances = relevant;
+
+            // Every relevant entity ages by one update, while the replication
+            // history of the exited ones is dropped
+            let interest = &observer.interest;
+            for entity in interest.exited.iter() {
+                observer.ages.remove(entity);
+            }
+            for &entity in interest.relevant.iter() {
+                *observer.ages.entry(entity).or_insert(0) += 1;
+            }
+
+            self.events
+                .extend(interest.exited.iter().map(|&entity| InterestEvent::Exited {
+                    observer: id,
+                    entity,
+                }));
+            self.events.extend(
+                interest
+                    .entered
+                    .iter()
+                    .map(|&entity| InterestEvent::Entered {
+                        observer: id,
+                        entity,
+                    }),
+            );
+        }
+
+        for (&id, region) in self.regions.iter_mut() {
+            structure.query_positioned_into(&region.geometry, &mut candidates);
+            let relevant = candidates
+                .iter()
+                .map(|(e, _)| e.id())
+                .collect::<BTreeSet<_>>();
+
+            region.interest.apply(relevant);
+
+            let interest = &region.interest;
+            self.events.extend(
+                interest
+                    .exited
+                    .iter()
+                    .map(|&entity| InterestEvent::RegionExited { region: id, entity }),
+            );
+            self.events.extend(
+                interest
+                    .entered
+                    .iter()
+                    .map(|&entity| InterestEvent::RegionEntered { region: id, entity }),
+            );
+        }
+    }
+
+    /// Returns up to `budget` entities relevant to the observer, ordered by their replication
+    /// priority, for servers enforcing a bandwidth cap per client.
+    ///
+    /// The priority of an entity grows with the number of updates elapsed since it was last
+    /// returned by this method, and grows up to twice as fast for the entities closest to the
+    /// observer. This way close entities are replicated more often while distant ones are
+    /// never starved. The returned entities are marked as replicated.
+    pub fn replication_list(&mut self, observer: Id, budget: usize) -> Vec<Id> {
+        let Some(o) = self.observers.get_mut(&observer) else {
+            return Vec::new();
+        };
+
+        let radius = o.radius;
+
+        let mut scored = o
+            .distances
+            .iter()
+            .map(|(&entity, &distance)| {
+                let closeness = if radius > F::zero() {
+                    (F::one() - distance / radius).max(F::zero())
+                } else {
+                    F::one()
+                };
+
+                let age = o.ages.get(&entity).copied().unwrap_or(0);
+                let age = F::from_u64(age).unwrap_or_else(F::max_value);
+
+                (entity, (F::one() + closeness) * age)
+            })
+            .collect::<Vec<_>>();
+
+        scored.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(core::cmp::Ordering::Equal)
+                .then(a.0.cmp(&b.0))
+        });
+
+        scored
+            .into_iter()
+            .take(budget)
+            .map(|(entity, _)| {
+                o.ages.insert(entity, 0);
+                entity
+            })
+            .collect()
+    }
+
+    /// Drains the queued interest events in the order they were produced.
+    ///
+    /// Events accumulate across updates until they are polled, so systems which do not
+    /// consume them every tick still receive every change.
+    pub fn poll_events(&mut self) -> impl Iterator<Item = InterestEvent<Id>> + '_ {
+        self.events.drain(..)
+    }
+
+    /// Returns the interest of every observer as computed by the latest update, ordered
+    /// by the observer id
+    pub fn interest_sets(&self) -> impl Iterator<Item = InterestSet<'_, Id>> {
+        self.observers
+            .iter()
+            .map(|(&id, o)| o.interest.as_set(Subscriber::Observer(id)))
+    }
+
+    /// Returns the interest of a single observer as computed by the latest update
+    pub fn interest_set(&self, observer: Id) -> Option<InterestSet<'_, Id>> {
+        self.observers
+            .get(&observer)
+            .map(|o| o.interest.as_set(Subscriber::Observer(observer)))
+    }
+
+    /// Returns the entities inside every region as computed by the latest update, ordered
+    /// by the region id
+    pub fn region_sets(&self) -> impl Iterator<Item = InterestSet<'_, Id>> {
+        self.regions
+            .iter()
+            .map(|(&id, r)| r.interest.as_set(Subscriber::Region(id)))
+    }
+
+    /// Returns the entities inside a single region as computed by the latest update
+    pub fn region_set(&self, region: Id) -> Option<InterestSet<'_, Id>> {
+        self.regions
+            .get(&region)
+            .map(|r| r.interest.as_set(Subscriber::Region(region)))
+    }
+}
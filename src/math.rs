@@ -0,0 +1,125 @@
+use num_traits::{Float, FloatConst};
+
+/// Wraps an angle in radians into the half-open range `(-π, π]`.
+///
+/// Useful after adding or subtracting headings, where the raw result can drift
+/// outside of a single turn.
+pub fn wrap_angle<F: Float + FloatConst>(angle: F) -> F {
+    let two_pi = F::TAU();
+
+    // Bringing the angle into [0, 2π) first, and then shifting the upper half
+    // of the turn to the negative side
+    let mut wrapped = angle % two_pi;
+    if wrapped < F::zero() {
+        wrapped = wrapped + two_pi;
+    }
+
+    if wrapped > F::PI() {
+        wrapped - two_pi
+    } else {
+        wrapped
+    }
+}
+
+/// Wraps an angle in degrees into the half-open range `[0, 360)`.
+pub fn wrap_degrees<F: Float>(degrees: F) -> F {
+    let full_turn = F::from(360.0).unwrap();
+
+    let wrapped = degrees % full_turn;
+    if wrapped < F::zero() {
+        wrapped + full_turn
+    } else {
+        wrapped
+    }
+}
+
+/// Heading of the vector pointing from `from` towards `to`, in radians.
+///
+/// The heading is measured counter-clockwise from the positive x-axis and lies
+/// in the range `(-π, π]`.
+pub fn heading<F: Float>(from: (F, F), to: (F, F)) -> F {
+    (to.1 - from.1).atan2(to.0 - from.0)
+}
+
+/// Unsigned angle between the two direction vectors `a` and `b`, in radians.
+///
+/// Returns a value in the range `[0, π]`. If either of the vectors has a zero length
+/// the angle is undefined and zero is returned.
+pub fn angle_between<F: Float>(a: (F, F), b: (F, F)) -> F {
+    let cross = a.0 * b.1 - a.1 * b.0;
+    let dot = a.0 * b.0 + a.1 * b.1;
+
+    // atan2 of the cross and dot products is numerically stable for both
+    // nearly parallel and nearly opposite vectors
+    cross.abs().atan2(dot)
+}
+
+/// Signed angle to rotate the direction vector `a` onto `b`, in radians.
+///
+/// Positive values are counter-clockwise rotations. Returns a value in the
+/// range `(-π, π]`.
+pub fn signed_angle_between<F: Float>(a: (F, F), b: (F, F)) -> F {
+    let cross = a.0 * b.1 - a.1 * b.0;
+    let dot = a.0 * b.0 + a.1 * b.1;
+
+    cross.atan2(dot)
+}
+
+/// Compass bearing from `from` towards `to`, in degrees.
+///
+/// Bearings are measured clockwise from north, where north is the positive y-axis,
+/// and lie in the range `[0, 360)`.
+pub fn bearing<F: Float + FloatConst>(from: (F, F), to: (F, F)) -> F {
+    angle_to_bearing(heading(from, to))
+}
+
+/// Converts a mathematical angle in radians (counter-clockwise from the positive
+/// x-axis) into a compass bearing in degrees (clockwise from the positive y-axis).
+pub fn angle_to_bearing<F: Float + FloatConst>(angle: F) -> F {
+    let quarter_turn = F::from(90.0).unwrap();
+
+    wrap_degrees(quarter_turn - angle.to_degrees())
+}
+
+/// Converts a compass bearing in degrees (clockwise from the positive y-axis) into
+/// a mathematical angle in radians (counter-clockwise from the positive x-axis).
+///
+/// The returned angle lies in the range `(-π, π]`.
+pub fn bearing_to_angle<F: Float + FloatConst>(bearing: F) -> F {
+    let quarter_turn = F::from(90.0).unwrap();
+
+    wrap_angle((quarter_turn - bearing).to_radians())
+}
+
+/// Smallest signed difference `to - from` between two bearings, in degrees.
+///
+/// Returns a value in the range `(-180, 180]`, positive when turning clockwise
+/// from `from` reaches `to` the quickest.
+pub fn bearing_difference<F: Float>(from: F, to: F) -> F {
+    let half_turn = F::from(180.0).unwrap();
+    let full_turn = F::from(360.0).unwrap();
+
+    let diff = wrap_degrees(to - from);
+    if diff > half_turn {
+        diff - full_turn
+    } else {
+        diff
+    }
+}
+
+/// Squared euclidean distance between the points `a` and `b`.
+///
+/// Prefer comparing squared distances when only the ordering matters, as it avoids the
+/// square root.
+pub fn distance_sq<F: Float>(a: (F, F, F), b: (F, F, F)) -> F {
+    let dx = b.0 - a.0;
+    let dy = b.1 - a.1;
+    let dz = b.2 - a.2;
+
+    dx * dx + dy * dy + dz * dz
+}
+
+/// Euclidean distance between the points `a` and `b`
+pub fn distance<F: Float>(a: (F, F, F), b: (F, F, F)) -> F {
+    distance_sq(a, b).sqrt()
+}
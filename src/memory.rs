@@ -0,0 +1,60 @@
+use core::mem::size_of;
+use core::ops::Add;
+
+/// Heap and inline memory used by a spatial structure, in bytes.
+///
+/// The figures are computed from the capacities of the underlying collections, so they
+/// account for the memory reserved by the structure rather than only the part in use.
+/// Hash tables are estimated from their capacity the same way the std HashMap sizes its
+/// buckets, including one control byte per bucket.
+///
+/// * `structure:` Size of the structure itself along with its per-floor bookkeeping
+/// * `buckets:` Memory reserved by the hash tables mapping the cells to their data
+/// * `items:` Memory reserved by the lists of references to the data
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryFootprint {
+    pub structure: usize,
+    pub buckets: usize,
+    pub items: usize,
+}
+
+impl MemoryFootprint {
+    /// Total number of bytes used by the structure
+    pub fn total(&self) -> usize {
+        self.structure + self.buckets + self.items
+    }
+}
+
+impl Add for MemoryFootprint {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            structure: self.structure + rhs.structure,
+            buckets: self.buckets + rhs.buckets,
+            items: self.items + rhs.items,
+        }
+    }
+}
+
+/// Estimated number of bytes reserved by a hash table with the `capacity`, for entries of
+/// type `E`
+pub(crate) fn hash_table_bytes<E>(capacity: usize) -> usize {
+    if capacity == 0 {
+        return 0;
+    }
+
+    // Tables keep an eighth of their buckets empty, except for the smallest ones which only
+    // keep a single bucket free
+    let buckets = if capacity < 8 {
+        (capacity + 1).next_power_of_two()
+    } else {
+        (capacity * 8 / 7).next_power_of_two()
+    };
+
+    // One control byte per bucket, plus a trailing group of control bytes
+    const GROUP_WIDTH: usize = 16;
+
+    buckets * size_of::<E>() + buckets + GROUP_WIDTH
+}
@@ -0,0 +1,147 @@
+use alloc::vec::Vec;
+
+use crate::geometry::Geometry;
+use crate::hashgrid::Coordinate;
+use crate::partition::{Positioned, SpatialInsertion, SpatialQuery, SpatialRemoval, SpatialUpdate};
+
+/// `Observer` is notified of the mutations applied to a spatial structure storing items of
+/// type `Item`, through the [`Observed`] wrapper.
+///
+/// Every hook does nothing by default, so observers only implement the ones they care about.
+pub trait Observer<Item> {
+    /// Type of the unique id identifying the items of the structure
+    type Id;
+
+    /// Spatial position understood by the structure
+    type Position;
+
+    /// Called for every item inserted into the structure
+    fn on_insert(&mut self, _item: &Item) {}
+
+    /// Called once the item matching the `id` has been removed from the structure
+    fn on_remove(&mut self, _id: &Self::Id) {}
+
+    /// Called once the item matching the `id` has been moved to the `position`
+    fn on_relocate(&mut self, _id: &Self::Id, _position: &Self::Position) {}
+}
+
+/// # Observed
+///
+/// Wraps a spatial structure to notify an [`Observer`] of the insertions, removals and
+/// relocations applied to it, so adjacent systems such as audio or replication can react to
+/// spatial changes without polling difference queries.
+///
+/// The wrapper implements the partition traits of the wrapped structure, mutations go through
+/// them while queries are forwarded untouched. Insertions are reported as they are fed into the
+/// structure, removals and relocations only when they succeed.
+#[derive(Debug)]
+pub struct Observed<S, O> {
+    structure: S,
+    observer: O,
+}
+
+impl<S, O> Observed<S, O> {
+    /// Wraps the `structure`, reporting its mutations to the `observer`
+    pub fn new(structure: S, observer: O) -> Self {
+        Self {
+            structure,
+            observer,
+        }
+    }
+
+    /// Returns the wrapped structure
+    pub fn structure(&self) -> &S {
+        &self.structure
+    }
+
+    /// Returns the observer
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    /// Returns the observer mutably, e.g. to drain the changes it collected
+    pub fn observer_mut(&mut self) -> &mut O {
+        &mut self.observer
+    }
+
+    /// Unwraps the structure and the observer
+    pub fn into_inner(self) -> (S, O) {
+        (self.structure, self.observer)
+    }
+}
+
+impl<S, O> SpatialInsertion for Observed<S, O>
+where
+    S: SpatialInsertion,
+    O: Observer<S::Item>,
+{
+    type Item = S::Item;
+
+    fn insert(&mut self, item: Self::Item) {
+        self.observer.on_insert(&item);
+        self.structure.insert(item);
+    }
+}
+
+impl<S, O> SpatialQuery for Observed<S, O>
+where
+    S: SpatialQuery,
+{
+    type Item = S::Item;
+    type Scalar = S::Scalar;
+    type Results = S::Results;
+
+    fn query(&self, geometry: &Geometry<Self::Scalar>) -> Self::Results {
+        self.structure.query(geometry)
+    }
+
+    fn query_into(&self, geometry: &Geometry<Self::Scalar>, results: &mut Vec<Self::Item>) {
+        self.structure.query_into(geometry, results);
+    }
+
+    fn query_positioned_into(
+        &self,
+        geometry: &Geometry<Self::Scalar>,
+        results: &mut Vec<Positioned<Self::Item, Self::Scalar>>,
+    ) where
+        Self::Item: Coordinate<Item = Self::Scalar>,
+    {
+        self.structure.query_positioned_into(geometry, results);
+    }
+}
+
+impl<S, O> SpatialRemoval for Observed<S, O>
+where
+    S: SpatialInsertion + SpatialRemoval,
+    S::Id: Clone,
+    O: Observer<S::Item, Id = S::Id>,
+{
+    type Id = S::Id;
+
+    fn remove(&mut self, id: Self::Id) -> bool {
+        let removed = self.structure.remove(id.clone());
+        if removed {
+            self.observer.on_remove(&id);
+        }
+        removed
+    }
+}
+
+impl<S, O> SpatialUpdate for Observed<S, O>
+where
+    S: SpatialInsertion + SpatialUpdate,
+    <S as SpatialUpdate>::Id: Clone,
+    S::Position: Clone,
+    O: Observer<S::Item, Id = <S as SpatialUpdate>::Id, Position = S::Position>,
+{
+    type Id = <S as SpatialUpdate>::Id;
+    type Position = S::Position;
+
+    fn relocate(&mut self, id: Self::Id, position: Self::Position) -> bool {
+        let relocated = self.structure.relocate(id.clone(), position.clone());
+        if relocated {
+            self.observer.on_relocate(&id, &position);
+        }
+        relocated
+    }
+}
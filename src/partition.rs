@@ -0,0 +1,107 @@
+use alloc::vec::Vec;
+
+use num_traits::Float;
+
+use crate::geometry::Geometry;
+use crate::hashgrid::Coordinate;
+
+/// Item of a structure along with the coordinates it was tested at, see
+/// [`SpatialQuery::query_positioned_into`]
+pub type Positioned<I, F> = (I, (F, F, F));
+
+/// `SpatialInsertion` is the common insertion interface for the spatial partitioning
+/// structures of this crate.
+///
+/// Systems which only need to feed data into a structure can be written against this
+/// trait instead of a concrete backend such as [`HashGrid`](crate::HashGrid).
+pub trait SpatialInsertion {
+    /// Type of the item accepted by the structure
+    type Item;
+
+    /// Inserts the item into the structure according to its spatial coordinates
+    fn insert(&mut self, item: Self::Item);
+
+    /// Inserts every item from the iterator into the structure
+    fn insert_all<I>(&mut self, items: I)
+    where
+        I: IntoIterator<Item = Self::Item>,
+    {
+        for item in items {
+            self.insert(item);
+        }
+    }
+}
+
+/// `SpatialQuery` is the common query interface for the spatial partitioning structures
+/// of this crate.
+///
+/// Queries are expressed as a [`Geometry`], so callers can write backend agnostic range
+/// queries and run them on either structure interchangeably.
+pub trait SpatialQuery {
+    /// Type of the items returned by the structure
+    type Item;
+
+    /// Base float type of the structure's spatial components
+    type Scalar: Float;
+
+    /// Iterator over the items matching a query
+    type Results: Iterator<Item = Self::Item>;
+
+    /// Returns every item of the structure located inside the geometry
+    fn query(&self, geometry: &Geometry<Self::Scalar>) -> Self::Results;
+
+    /// Clears the `results` and fills them with every item located inside the geometry.
+    ///
+    /// Reusing the same buffer across queries, e.g. once per frame, keeps the query loop
+    /// free of allocations once the buffer has grown to the size of the largest result.
+    fn query_into(&self, geometry: &Geometry<Self::Scalar>, results: &mut Vec<Self::Item>) {
+        results.clear();
+        results.extend(self.query(geometry));
+    }
+
+    /// Same as [`SpatialQuery::query_into`], except that every item comes along with the
+    /// coordinates the structure tested it at.
+    ///
+    /// Those are the coordinates the item reports, unless it was moved through
+    /// [`SpatialUpdate::relocate`], in which case they are the coordinates it was relocated to.
+    fn query_positioned_into(
+        &self,
+        geometry: &Geometry<Self::Scalar>,
+        results: &mut Vec<Positioned<Self::Item, Self::Scalar>>,
+    ) where
+        Self::Item: Coordinate<Item = Self::Scalar>,
+    {
+        results.clear();
+        results.extend(self.query(geometry).map(|item| {
+            let position = (item.x(), item.y(), item.z());
+            (item, position)
+        }));
+    }
+}
+
+/// `SpatialRemoval` is the common removal interface for the spatial partitioning
+/// structures of this crate.
+pub trait SpatialRemoval {
+    /// Type of the unique id identifying the items of the structure
+    type Id;
+
+    /// Removes the item matching the `id` from the structure.
+    ///
+    /// Returns `true` if the item was present and has been removed.
+    fn remove(&mut self, id: Self::Id) -> bool;
+}
+
+/// `SpatialUpdate` is the common relocation interface for the spatial partitioning
+/// structures of this crate.
+pub trait SpatialUpdate {
+    /// Type of the unique id identifying the items of the structure
+    type Id;
+
+    /// Spatial position understood by the structure
+    type Position;
+
+    /// Moves the item matching the `id` to the new position within the structure.
+    ///
+    /// Returns `true` if the item was present and has been relocated.
+    fn relocate(&mut self, id: Self::Id, position: Self::Position) -> bool;
+}
@@ -0,0 +1,183 @@
+use alloc::vec::Vec;
+
+use num_traits::Float;
+
+/// Describes how the relevance of an entity decays with its distance to the point of
+/// interest, within the outermost ring of a [`RelevanceProfile`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Falloff {
+    /// Full relevance everywhere inside the outer ring
+    Step,
+    /// Relevance decreases linearly from the centre down to zero at the outer ring
+    Linear,
+    /// Full relevance inside the high ring, then decreasing with the square of the distance
+    InverseSquare,
+}
+
+/// Priority bucket of an entity, defined by the ring of the [`Tiers`] it falls into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Priority {
+    High,
+    Medium,
+    Low,
+}
+
+/// Radii in world units of the high, medium and low interest rings around the point of
+/// interest. The rings are expected to be ordered as `high <= medium <= low`, where the
+/// `low` ring is the outermost radius of interest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tiers<F> {
+    pub high: F,
+    pub medium: F,
+    pub low: F,
+}
+
+impl<F: Float> Tiers<F> {
+    /// Single ring tiers where everything within the `radius` has the high priority
+    pub fn uniform(radius: F) -> Self {
+        Self {
+            high: radius,
+            medium: radius,
+            low: radius,
+        }
+    }
+
+    /// Returns the priority for the distance, or `None` if it lies outside the low ring
+    pub fn priority(&self, distance: F) -> Option<Priority> {
+        if distance <= self.high {
+            Some(Priority::High)
+        } else if distance <= self.medium {
+            Some(Priority::Medium)
+        } else if distance <= self.low {
+            Some(Priority::Low)
+        } else {
+            None
+        }
+    }
+}
+
+/// # Relevance Profile
+///
+/// Combines the interest rings of [`Tiers`] with a distance [`Falloff`] to score the relevance
+/// of an entity in the range `[0, 1]` and assign it a [`Priority`]. Structures use the profile
+/// to return query results bucketed by priority, e.g. for bandwidth-aware replication.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RelevanceProfile<F> {
+    pub tiers: Tiers<F>,
+    pub falloff: Falloff,
+}
+
+impl<F: Float> RelevanceProfile<F> {
+    pub fn new(tiers: Tiers<F>, falloff: Falloff) -> Self {
+        Self { tiers, falloff }
+    }
+
+    /// Outermost radius of interest, in world units
+    pub fn radius(&self) -> F {
+        self.tiers.low
+    }
+
+    /// Relevance score in the range `[0, 1]` for an entity at the `distance` from the point
+    /// of interest. Entities outside the low ring always score zero.
+    pub fn score(&self, distance: F) -> F {
+        let radius = self.radius();
+        if distance > radius {
+            return F::zero();
+        }
+
+        match self.falloff {
+            Falloff::Step => F::one(),
+            Falloff::Linear => {
+                if radius > F::zero() {
+                    F::one() - distance / radius
+                } else {
+                    F::one()
+                }
+            }
+            Falloff::InverseSquare => {
+                if distance <= self.tiers.high {
+                    F::one()
+                } else {
+                    let ratio = self.tiers.high / distance;
+                    ratio * ratio
+                }
+            }
+        }
+    }
+
+    /// Returns the priority for an entity at the `distance` from the point of interest
+    pub fn priority(&self, distance: F) -> Option<Priority> {
+        self.tiers.priority(distance)
+    }
+}
+
+/// Query results bucketed by [`Priority`], each entry paired with its relevance score.
+///
+/// Entries of every bucket are sorted by descending score.
+#[derive(Debug, Clone)]
+pub struct Prioritized<E, F> {
+    pub high: Vec<(E, F)>,
+    pub medium: Vec<(E, F)>,
+    pub low: Vec<(E, F)>,
+}
+
+impl<E, F: Float> Prioritized<E, F> {
+    /// Buckets the items according to their distances to the point of interest. Items outside
+    /// the low ring of the profile are discarded.
+    pub fn from_distances<I>(profile: &RelevanceProfile<F>, items: I) -> Self
+    where
+        I: IntoIterator<Item = (E, F)>,
+    {
+        let mut buckets = Self {
+            high: Vec::new(),
+            medium: Vec::new(),
+            low: Vec::new(),
+        };
+
+        for (item, distance) in items {
+            let bucket = match profile.priority(distance) {
+                Some(Priority::High) => &mut buckets.high,
+                Some(Priority::Medium) => &mut buckets.medium,
+                Some(Priority::Low) => &mut buckets.low,
+                None => continue,
+            };
+            bucket.push((item, profile.score(distance)));
+        }
+
+        for bucket in [&mut buckets.high, &mut buckets.medium, &mut buckets.low] {
+            bucket.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(core::cmp::Ordering::Equal));
+        }
+
+        buckets
+    }
+
+    /// Returns the bucket for the priority
+    pub fn bucket(&self, priority: Priority) -> &[(E, F)] {
+        match priority {
+            Priority::High => &self.high,
+            Priority::Medium => &self.medium,
+            Priority::Low => &self.low,
+        }
+    }
+
+    /// Total number of entries across all buckets
+    pub fn len(&self) -> usize {
+        self.high.len() + self.medium.len() + self.low.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterates over all entries from the high to the low priority bucket
+    pub fn iter(&self) -> impl Iterator<Item = &(E, F)> {
+        self.high
+            .iter()
+            .chain(self.medium.iter())
+            .chain(self.low.iter())
+    }
+}
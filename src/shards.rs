@@ -0,0 +1,358 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use num_traits::{Float, FromPrimitive, ToPrimitive};
+
+use crate::error::SpatialError;
+use crate::geometry::Geometry;
+use crate::hashgrid::{
+    Coordinate, CoordinateFrame, DataIndex, Entity, GridBoundary, HashGrid, Origin,
+};
+use crate::partition::{Positioned, SpatialInsertion, SpatialQuery, SpatialRemoval, SpatialUpdate};
+
+/// Key of a shard, the coordinates of its region along the x and y axis
+pub type ShardKey = (i64, i64);
+
+/// # Grid Shards
+///
+/// Splits a world too large for a single grid into square regions of equal size, each one
+/// indexed by its own independent [`HashGrid`]. Shards are created lazily, the first time an
+/// entity is inserted into their region, so the world itself has no bounds.
+///
+/// Queries fan out to every shard touched by the query geometry and merge their results.
+/// Individual shards can also be borrowed through [`GridShards::shards`], e.g. to process
+/// them on separate worker threads.
+///
+/// GridShards is parameterized over:
+///
+/// * `F (Float type):` Defines the base float type such as `f32` or `f64` for spatial components
+/// * `T (generic data type):` Defines the data type to insert into the shards, data must live as long as the shards live
+#[derive(Debug)]
+pub struct GridShards<'a, F, T> {
+    shard_size: [F; 3],
+    cells: [u32; 2],
+    floors: usize,
+    shards: BTreeMap<ShardKey, HashGrid<'a, F, T>>,
+}
+
+impl<'a, F, T> GridShards<'a, F, T>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+{
+    /// Creates the container without any shards.
+    ///
+    /// Parameters to this function specifies:
+    ///
+    /// - `shard_size:` Size of the region of a shard along the x and y axis, every shard spanning
+    ///   the size along the z-axis centred at zero (zero for 2D worlds)
+    /// - `cells:` Number of cells in x and y directions of the grid of each shard
+    /// - `floors:` Number of floors of the grid of each shard
+    pub fn new(shard_size: [F; 3], cells: [u32; 2], floors: usize) -> Self {
+        Self {
+            shard_size,
+            cells,
+            floors,
+            shards: BTreeMap::new(),
+        }
+    }
+
+    /// Key of the shard whose region contains the `coordinates`.
+    ///
+    /// Infinite coordinates, such as the unbounded sides of a slab, saturate to the first or
+    /// last possible key.
+    pub fn shard_key(&self, coordinates: (F, F, F)) -> ShardKey {
+        let axis_key = |v: F, size: F| {
+            (v / size)
+                .floor()
+                .to_i64()
+                .unwrap_or(match v.is_infinite() {
+                    true if v.is_sign_negative() => i64::MIN,
+                    true => i64::MAX,
+                    false => 0,
+                })
+        };
+
+        (
+            axis_key(coordinates.0, self.shard_size[0]),
+            axis_key(coordinates.1, self.shard_size[1]),
+        )
+    }
+
+    /// World region covered by the shard with the `key`
+    pub fn shard_bounds(&self, key: ShardKey) -> GridBoundary<F> {
+        let half = F::from_f64(0.5).unwrap();
+
+        GridBoundary {
+            center: [
+                (F::from_i64(key.0).unwrap() + half) * self.shard_size[0],
+                (F::from_i64(key.1).unwrap() + half) * self.shard_size[1],
+                F::zero(),
+            ],
+            size: self.shard_size,
+        }
+    }
+
+    /// Inserts the reference to the data into the shard of its region, creating the shard if
+    /// needed. Entities with non-finite coordinates, or beyond the z extent of the shards, are
+    /// not inserted.
+    pub fn insert(&mut self, entity: &'a T)
+    where
+        T: Coordinate<Item = F>,
+    {
+        let _: Result<_, SpatialError> = self.file(entity);
+    }
+
+    /// Same as [`GridShards::insert`], except that the reason for not inserting the entity is
+    /// reported as a [`SpatialError`], an id already in any of the shards included
+    pub fn try_insert<Id>(&mut self, entity: &'a T) -> Result<(), SpatialError<Id>>
+    where
+        Id: DataIndex,
+        T: Coordinate<Item = F> + Entity<ID = Id>,
+    {
+        for grid in self.shards.values() {
+            grid.reject_duplicate(entity.id())?;
+        }
+        self.file(entity)
+    }
+
+    /// Inserts the entity into the grid of its shard, see [`GridShards::try_insert`]
+    fn file<Id>(&mut self, entity: &'a T) -> Result<(), SpatialError<Id>>
+    where
+        T: Coordinate<Item = F>,
+    {
+        let key = self.shard_key((entity.x(), entity.y(), entity.z()));
+        let bounds = self.shard_bounds(key);
+        let (cells, floors) = (self.cells, self.floors);
+
+        self.shards
+            .entry(key)
+            .or_insert_with(|| Self::new_shard(cells, floors, &bounds))
+            .file(entity)
+    }
+
+    /// Creates the grid of a shard within the `bounds`, counting its cells from the corner of
+    /// the shard so that the cell indices stay small however far the shard is from the origin
+    fn new_shard(cells: [u32; 2], floors: usize, bounds: &GridBoundary<F>) -> HashGrid<'a, F, T>
+    where
+        T: Coordinate<Item = F>,
+    {
+        HashGrid::new(cells, floors, bounds, false).with_frame(CoordinateFrame {
+            origin: Origin::Corner,
+            ..CoordinateFrame::default()
+        })
+    }
+
+    /// Inserts the references to every data of the list, see [`GridShards::insert`]
+    pub fn update(&mut self, data: &'a [T])
+    where
+        T: Coordinate<Item = F>,
+    {
+        for entity in data {
+            self.insert(entity);
+        }
+    }
+
+    /// Removes the data matching the `id` from its shard. Shards left empty are kept, so
+    /// they do not have to be created again when entities move back into their region.
+    ///
+    /// Returns `true` if the entity was found and removed.
+    pub fn remove<Id>(&mut self, id: Id) -> bool
+    where
+        Id: DataIndex,
+        T: Entity<ID = Id>,
+    {
+        self.shards.values_mut().any(|shard| shard.remove(id))
+    }
+
+    /// Moves the data matching the `id` to the `coordinates`, handing it over to the shard of
+    /// their region if it crosses a border, see [`HashGrid::relocate`]. The shard is created if
+    /// needed, and the shard left behind is kept like for [`GridShards::remove`].
+    ///
+    /// Returns `true` if the entity was found and relocated. Coordinates which cannot be
+    /// inserted, see [`GridShards::insert`], are rejected and the entity stays where it was.
+    pub fn relocate<Id>(&mut self, id: Id, coordinates: (F, F, F)) -> bool
+    where
+        Id: DataIndex,
+        T: Coordinate<Item = F> + Entity<ID = Id>,
+    {
+        self.try_relocate(id, coordinates).is_ok()
+    }
+
+    /// Same as [`GridShards::relocate`], except that the reason for not relocating the entity
+    /// is reported as a [`SpatialError`]
+    pub fn try_relocate<Id>(
+        &mut self,
+        id: Id,
+        coordinates: (F, F, F),
+    ) -> Result<(), SpatialError<Id>>
+    where
+        Id: DataIndex,
+        T: Coordinate<Item = F> + Entity<ID = Id>,
+    {
+        let source = self
+            .shards
+            .iter()
+            .find(|(_, shard)| shard.holds(id))
+            .map(|(&key, _)| key)
+            .ok_or(SpatialError::UnknownEntity(id))?;
+
+        let key = self.shard_key(coordinates);
+        if key == source {
+            return self
+                .shards
+                .get_mut(&key)
+                .unwrap()
+                .try_relocate(id, coordinates);
+        }
+
+        // The shard of the destination is only kept if the entity made it there
+        let existed = self.shards.contains_key(&key);
+        let bounds = self.shard_bounds(key);
+        let (cells, floors) = (self.cells, self.floors);
+        let mut target = self
+            .shards
+            .remove(&key)
+            .unwrap_or_else(|| Self::new_shard(cells, floors, &bounds));
+
+        let moved =
+            self.shards
+                .get_mut(&source)
+                .unwrap()
+                .try_relocate_into(id, coordinates, &mut target);
+
+        if existed || moved.is_ok() {
+            self.shards.insert(key, target);
+        }
+        moved
+    }
+
+    /// Queries every shard touched by the [`Geometry`], and returns the entities located
+    /// inside it, shard by shard
+    pub fn query_geometry(&self, geometry: &Geometry<F>) -> Vec<&'a T>
+    where
+        T: Coordinate<Item = F>,
+    {
+        let mut results = Vec::new();
+        self.query_into(geometry, &mut results);
+        results
+    }
+
+    /// Same as [`GridShards::query_geometry`], except that the entities are written into the
+    /// `results` buffer, which is cleared first
+    pub fn query_into(&self, geometry: &Geometry<F>, results: &mut Vec<&'a T>)
+    where
+        T: Coordinate<Item = F>,
+    {
+        results.clear();
+
+        let mut shard_results = Vec::new();
+        for (_, shard) in self.touched(geometry) {
+            shard.query_into(geometry, &mut shard_results);
+            results.append(&mut shard_results);
+        }
+    }
+
+    /// Iterates over the shards touched by the bounding box of the `geometry`, in ascending
+    /// key order
+    fn touched(
+        &self,
+        geometry: &Geometry<F>,
+    ) -> impl Iterator<Item = (&ShardKey, &HashGrid<'a, F, T>)> + '_ {
+        let (min, max) = geometry.bounding_box();
+        let (lo, hi) = (self.shard_key(min), self.shard_key(max));
+
+        self.shards
+            .range((lo.0, i64::MIN)..=(hi.0, i64::MAX))
+            .filter(move |(key, _)| (lo.1..=hi.1).contains(&key.1))
+    }
+
+    /// Returns the shard with the `key`, if any entity has ever been inserted into its region
+    pub fn shard(&self, key: ShardKey) -> Option<&HashGrid<'a, F, T>> {
+        self.shards.get(&key)
+    }
+
+    /// Iterates over the shards along with their keys, in ascending key order
+    pub fn shards(&self) -> impl Iterator<Item = (ShardKey, &HashGrid<'a, F, T>)> {
+        self.shards.iter().map(|(&key, shard)| (key, shard))
+    }
+
+    /// Iterates mutably over the shards along with their keys, in ascending key order
+    pub fn shards_mut(&mut self) -> impl Iterator<Item = (ShardKey, &mut HashGrid<'a, F, T>)> {
+        self.shards.iter_mut().map(|(&key, shard)| (key, shard))
+    }
+
+    /// Number of shards created so far
+    pub fn len(&self) -> usize {
+        self.shards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards.is_empty()
+    }
+}
+
+impl<'a, F, T> SpatialInsertion for GridShards<'a, F, T>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    T: Coordinate<Item = F>,
+{
+    type Item = &'a T;
+
+    fn insert(&mut self, item: Self::Item) {
+        GridShards::insert(self, item)
+    }
+}
+
+impl<'a, F, T> SpatialQuery for GridShards<'a, F, T>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    T: Coordinate<Item = F>,
+{
+    type Item = &'a T;
+    type Scalar = F;
+    type Results = alloc::vec::IntoIter<&'a T>;
+
+    fn query(&self, geometry: &Geometry<F>) -> Self::Results {
+        self.query_geometry(geometry).into_iter()
+    }
+
+    fn query_into(&self, geometry: &Geometry<F>, results: &mut Vec<Self::Item>) {
+        GridShards::query_into(self, geometry, results)
+    }
+
+    fn query_positioned_into(
+        &self,
+        geometry: &Geometry<F>,
+        results: &mut Vec<Positioned<Self::Item, F>>,
+    ) {
+        results.clear();
+        for (_, shard) in self.touched(geometry) {
+            shard.query_positioned_into(geometry, results);
+        }
+    }
+}
+
+impl<F, T> SpatialRemoval for GridShards<'_, F, T>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    T: Entity,
+{
+    type Id = T::ID;
+
+    fn remove(&mut self, id: Self::Id) -> bool {
+        GridShards::remove(self, id)
+    }
+}
+
+impl<F, T> SpatialUpdate for GridShards<'_, F, T>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    T: Coordinate<Item = F> + Entity,
+{
+    type Id = T::ID;
+    type Position = (F, F, F);
+
+    fn relocate(&mut self, id: Self::Id, position: Self::Position) -> bool {
+        GridShards::relocate(self, id, position)
+    }
+}
@@ -0,0 +1,18 @@
+/// Counters collected while running a single geometry query, available through the
+/// `last_query_stats` method of the structures when the `stats` feature is enabled.
+///
+/// * `cells_visited:` Cells (or lists, for structures without cells) looked up by the query
+/// * `cells_occupied:` Visited cells which held at least one entity
+/// * `candidates_tested:` Entities tested against the exact shape of the query
+/// * `results:` Entities returned by the query
+///
+/// The ratio of `results` to `candidates_tested` tells how well the structure culls the
+/// entities for a query, a low ratio hinting at oversized cells for the query.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QueryStats {
+    pub cells_visited: usize,
+    pub cells_occupied: usize,
+    pub candidates_tested: usize,
+    pub results: usize,
+}
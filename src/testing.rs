@@ -0,0 +1,136 @@
+//! Support for validating spatial structures, and the [`Entity`] and [`Coordinate`]
+//! implementations fed into them, against a brute-force oracle.
+//!
+//! The oracle is a [`LinearIndex`], which tests every entity against every query and is
+//! therefore exact. Any structure implementing [`SpatialQuery`] can be compared with it over
+//! the [`SyntheticWorld`]s and random geometries generated here.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use crate::bench_utils::SplitMix64;
+use crate::geometry::Geometry;
+use crate::hashgrid::{Boundary, Coordinate, Entity, GridBoundary};
+use crate::linear::LinearIndex;
+use crate::partition::SpatialQuery;
+
+pub use crate::bench_utils::{SyntheticEntity, SyntheticWorld};
+
+/// Difference between the results of a structure and the oracle for a single query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch<Id> {
+    /// Index of the query in the list of geometries
+    pub query: usize,
+    /// Entities returned by the oracle but not by the structure
+    pub missing: Vec<Id>,
+    /// Entities returned by the structure but not by the oracle, or more times than by it
+    pub unexpected: Vec<Id>,
+}
+
+/// Builds the brute-force oracle over the entities
+pub fn oracle<F, T>(entities: &[T]) -> LinearIndex<'_, F, T>
+where
+    F: num_traits::Float,
+{
+    let mut oracle = LinearIndex::new();
+    entities.iter().for_each(|e| oracle.insert(e));
+    oracle
+}
+
+/// Generates `count` random rectangles and circles within the `bounds`, with extents of up
+/// to `max_extent` world units
+pub fn random_geometries(
+    bounds: &GridBoundary<f32>,
+    count: usize,
+    max_extent: f32,
+    seed: u64,
+) -> Vec<Geometry<f32>> {
+    let mut rng = SplitMix64(seed);
+    let (min, max) = (bounds.min(), bounds.max());
+
+    (0..count)
+        .map(|i| {
+            let centre = (rng.range(min[0], max[0]), rng.range(min[1], max[1]), 0.0);
+
+            if i % 2 == 0 {
+                let size = (rng.range(0.0, max_extent), rng.range(0.0, max_extent), 0.0);
+                Geometry::rect(centre, size)
+            } else {
+                Geometry::radius(centre, rng.range(0.0, max_extent / 2.0))
+            }
+        })
+        .collect()
+}
+
+/// Runs every geometry on the structure and the oracle built over the same `entities`, and
+/// returns the queries for which their results differ, ignoring the order of the results
+pub fn compare_with_oracle<'e, S, F, T>(
+    structure: &S,
+    entities: &'e [T],
+    geometries: &[Geometry<F>],
+) -> Vec<Mismatch<T::ID>>
+where
+    S: SpatialQuery<Item = &'e T, Scalar = F>,
+    F: num_traits::Float,
+    T: Coordinate<Item = F> + Entity,
+{
+    let oracle = oracle(entities);
+
+    geometries
+        .iter()
+        .enumerate()
+        .filter_map(|(query, geometry)| {
+            // Counting the occurrences of every id, so duplicated results are caught as well
+            let mut counts = BTreeMap::<T::ID, isize>::new();
+            for e in oracle.query_geometry(geometry) {
+                *counts.entry(e.id()).or_default() += 1;
+            }
+            for e in structure.query(geometry) {
+                *counts.entry(e.id()).or_default() -= 1;
+            }
+
+            let missing = counts
+                .iter()
+                .filter(|(_, &c)| c > 0)
+                .map(|(&id, _)| id)
+                .collect::<Vec<_>>();
+            let unexpected = counts
+                .iter()
+                .filter(|(_, &c)| c < 0)
+                .map(|(&id, _)| id)
+                .collect::<Vec<_>>();
+
+            (!missing.is_empty() || !unexpected.is_empty()).then_some(Mismatch {
+                query,
+                missing,
+                unexpected,
+            })
+        })
+        .collect()
+}
+
+/// Same as [`compare_with_oracle`], panicking with the first mismatching query and its
+/// geometry if the structure disagrees with the oracle
+pub fn assert_matches_oracle<'e, S, F, T>(
+    structure: &S,
+    entities: &'e [T],
+    geometries: &[Geometry<F>],
+) where
+    S: SpatialQuery<Item = &'e T, Scalar = F>,
+    F: num_traits::Float + Debug,
+    T: Coordinate<Item = F> + Entity,
+{
+    let mismatches = compare_with_oracle(structure, entities, geometries);
+
+    if let Some(mismatch) = mismatches.first() {
+        panic!(
+            "{} of {} queries disagree with the oracle, first one is {:?}: missing {:?}, unexpected {:?}",
+            mismatches.len(),
+            geometries.len(),
+            geometries[mismatch.query],
+            mismatch.missing,
+            mismatch.unexpected
+        );
+    }
+}
@@ -0,0 +1,46 @@
+use super::grid::{Bounds, Player2D};
+use super::prelude::*;
+use crate::aggregate::Entities;
+use crate::geometry::Geometry;
+use crate::hashgrid::HashGrid;
+
+#[test]
+fn aggregate_shapes() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    // A square formation with a unit in its middle and one on an edge
+    let players = [
+        Player2D::new(0, [0.0, 0.0]),
+        Player2D::new(1, [10.0, 0.0]),
+        Player2D::new(2, [10.0, 10.0]),
+        Player2D::new(3, [0.0, 10.0]),
+        Player2D::new(4, [5.0, 5.0]),
+        Player2D::new(5, [5.0, 0.0]),
+    ];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    let formation = grid.query_geometry(&Geometry::rect((5.0, 5.0, 0.0), (20.0, 20.0, 0.0)));
+    assert_eq!(formation.len(), 6);
+
+    assert_eq!(
+        formation.convex_hull(),
+        vec![&players[0], &players[1], &players[2], &players[3]]
+    );
+    assert_eq!(
+        formation.bounding_rect(),
+        Some(Geometry::rect((5.0, 5.0, 0.0), (10.0, 10.0, 0.0)))
+    );
+    assert_eq!(
+        formation.bounding_circle(),
+        Some(Geometry::radius((5.0, 5.0, 0.0), 50_f32.sqrt()))
+    );
+
+    let pair = [&players[0], &players[0], &players[4]];
+    assert_eq!(pair.convex_hull(), vec![&players[0], &players[4]]);
+    assert_eq!(formation[..0].bounding_rect(), None);
+}
@@ -0,0 +1,137 @@
+use super::prelude::*;
+use alloc::rc::Rc;
+use alloc::sync::Arc;
+
+use super::grid::{Bounds, Player2D};
+use crate::entity::SpatialEntity;
+use crate::geometry::Geometry;
+use crate::hashgrid::{Boundary, HashGrid};
+use crate::linear::LinearIndex;
+use crate::shards::GridShards;
+
+#[derive(Debug, PartialEq)]
+struct Drone {
+    id: u16,
+    position: (f64, f64, f64),
+}
+
+impl SpatialEntity for Drone {
+    type Id = u16;
+    type Scalar = f64;
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn position(&self) -> (f64, f64, f64) {
+        self.position
+    }
+}
+
+struct Bounds64 {
+    centre: [f64; 3],
+    size: [f64; 3],
+}
+
+impl Boundary for Bounds64 {
+    type Item = f64;
+
+    fn centre(&self) -> [f64; 3] {
+        self.centre
+    }
+
+    fn size(&self) -> [f64; 3] {
+        self.size
+    }
+}
+
+#[test]
+fn single_entity_impl() {
+    let bounds = Bounds64 {
+        centre: [0.0, 0.0, 50.0],
+        size: [100.0, 100.0, 100.0],
+    };
+
+    let drones = [
+        Drone {
+            id: 0,
+            position: (5.0, 5.0, 10.0),
+        },
+        Drone {
+            id: 1,
+            position: (-20.0, 30.0, 80.0),
+        },
+    ];
+
+    // One impl serves every structure
+    let mut grid = HashGrid::<f64, Drone>::new([10, 10], 4, &bounds, false);
+    grid.update(&drones);
+
+    let mut linear = LinearIndex::new();
+    let mut shards = GridShards::new([50.0, 50.0, 200.0], [5, 5], 1);
+    for drone in &drones {
+        linear.insert(drone);
+        shards.insert(drone);
+    }
+
+    let low = Geometry::rect((0.0, 0.0, 10.0), (20.0, 20.0, 10.0));
+    assert_eq!(grid.query_geometry(&low), vec![&drones[0]]);
+    assert_eq!(linear.query_geometry(&low), vec![&drones[0]]);
+
+    assert!(grid.remove(1));
+    assert!(shards.remove(1));
+    assert_eq!(grid.iter_all().count(), 1);
+}
+
+#[test]
+fn shared_handles() {
+    let bounds = Bounds64 {
+        centre: [0.0, 0.0, 50.0],
+        size: [100.0, 100.0, 100.0],
+    };
+
+    // Handles to entities implementing the single trait
+    let drones = [
+        Rc::new(Drone {
+            id: 0,
+            position: (5.0, 5.0, 10.0),
+        }),
+        Rc::new(Drone {
+            id: 1,
+            position: (-20.0, 30.0, 80.0),
+        }),
+    ];
+
+    let mut grid = HashGrid::<f64, Rc<Drone>>::new([10, 10], 4, &bounds, false);
+    grid.update(&drones);
+
+    let low = Geometry::rect((0.0, 0.0, 10.0), (20.0, 20.0, 10.0));
+    assert_eq!(grid.query_geometry(&low), vec![&drones[0]]);
+    assert!(grid.remove(1));
+
+    // Handles to entities implementing the traits directly
+    let bounds_2d = Bounds {
+        centre: [0.0, 0.0, 0.0],
+        size: [100.0, 100.0, 0.0],
+    };
+    let players = [
+        Arc::new(Player2D::new(3, [5.0, 5.0])),
+        Arc::new(Player2D::new(7, [10.0, 10.0])),
+    ];
+    let boxed = [Box::new(Player2D::new(4, [-5.0, 5.0]))];
+    let borrowed = [&players[0], &players[1]];
+
+    let mut arcs = HashGrid::<f32, Arc<Player2D>>::new([10, 10], 0, &bounds_2d, false);
+    arcs.update(&players);
+    assert!(arcs.relocate(7, (30.0, 30.0, 0.0)));
+
+    let mut boxes = HashGrid::<f32, Box<Player2D>>::new([10, 10], 0, &bounds_2d, false);
+    boxes.update(&boxed);
+    assert_eq!(boxes.nearest((0.0, 0.0, 0.0)).unwrap().0.id, 4);
+
+    let mut refs = HashGrid::<f32, &Arc<Player2D>>::new([10, 10], 0, &bounds_2d, false);
+    refs.update(&borrowed);
+    let around = refs.query_geometry(&Geometry::radius((5.0, 5.0, 0.0), 2.0));
+    assert_eq!(around.len(), 1);
+    assert_eq!(around[0].id, 3);
+}
@@ -0,0 +1,42 @@
+use super::grid::{Bounds, Player2D};
+use crate::field::ScalarField;
+use crate::hashgrid::HashGrid;
+
+#[test]
+fn deposit_decay_sample() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    let mut field = ScalarField::from_grid(&grid, 0);
+    assert_eq!(field.cells(), [10, 10]);
+
+    // Cells are counted from the corner of the bounds
+    assert_eq!(field.cell_of((-50.0, -50.0)), Some([0, 0]));
+    assert_eq!(field.cell_of((50.0, 50.0)), Some([9, 9]));
+    assert_eq!(field.cell_of((51.0, 0.0)), None);
+
+    assert!(field.deposit((5.0, 5.0), 4.0));
+    assert!(field.deposit((6.0, 8.0), 4.0));
+    assert!(!field.deposit((80.0, 0.0), 1.0));
+    assert_eq!(field.value([5, 5]), Some(8.0));
+    assert_eq!(field.value([10, 0]), None);
+
+    // Exact at the centre of the cell, halfway to the centre of its neighbour
+    assert_eq!(field.sample((5.0, 5.0)), 8.0);
+    assert_eq!(field.sample((10.0, 5.0)), 4.0);
+    assert_eq!(field.sample((30.0, 5.0)), 0.0);
+
+    // Rising towards the deposit from the left, falling past it
+    assert_eq!(field.gradient((2.0, 5.0)).0, 0.8);
+    assert_eq!(field.gradient((8.0, 5.0)).0, -0.8);
+    assert_eq!(field.gradient((5.0, 8.0)).1, -0.8);
+
+    field.decay(2.0_f32.ln());
+    assert!((field.value([5, 5]).unwrap() - 4.0).abs() < 1e-5);
+
+    field.clear();
+    assert_eq!(field.sample((5.0, 5.0)), 0.0);
+}
@@ -0,0 +1,72 @@
+use super::grid::{Bounds, Player2D};
+use super::prelude::*;
+use crate::geofence::{Geofence, GeofenceEvent};
+use crate::geometry::Geometry;
+use crate::hashgrid::HashGrid;
+
+#[test]
+fn enter_dwell_exit() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let mut fences = Geofence::<f32, u32>::new();
+    fences.set_dwell_time(5.0);
+    fences.add_fence(0, Geometry::rect((0.0, 0.0, 0.0), (20.0, 20.0, 0.0)));
+    fences.add_fence(1, Geometry::radius((30.0, 0.0, 0.0), 5.0));
+
+    let mut players = vec![Player2D::new(0, [0.0, 0.0]), Player2D::new(1, [40.0, 40.0])];
+
+    for (now, x) in [(0.0, 0.0), (3.0, 5.0), (6.0, 8.0), (9.0, 30.0)] {
+        players[0].position = [x, 0.0];
+
+        let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+        grid.update(&players);
+        fences.update_with(&grid, now);
+    }
+
+    assert_eq!(
+        fences.poll_events().collect::<Vec<_>>(),
+        [
+            GeofenceEvent::Entered {
+                fence: 0,
+                entity: 0
+            },
+            GeofenceEvent::Dwelling {
+                fence: 0,
+                entity: 0,
+                duration: 6.0
+            },
+            GeofenceEvent::Exited {
+                fence: 0,
+                entity: 0
+            },
+            GeofenceEvent::Entered {
+                fence: 1,
+                entity: 0
+            },
+        ]
+    );
+    assert_eq!(fences.inside(1).collect::<Vec<_>>(), [0]);
+
+    // Single entity feeds and despawns
+    fences.update_entity(1, (1.0, 1.0, 0.0), 10.0);
+    fences.remove_entity(0);
+    assert_eq!(
+        fences.poll_events().collect::<Vec<_>>(),
+        [
+            GeofenceEvent::Entered {
+                fence: 0,
+                entity: 1
+            },
+            GeofenceEvent::Exited {
+                fence: 1,
+                entity: 0
+            },
+        ]
+    );
+    assert!(fences.inside(1).next().is_none());
+    assert!(fences.remove_fence(1));
+    assert!(!fences.remove_fence(1));
+}
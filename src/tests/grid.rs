@@ -1,8 +1,10 @@
+use super::prelude::*;
+use crate::geometry::Geometry;
 use crate::hashgrid::{Boundary, Coordinate, Entity, HashGrid, Query, QueryType};
 
-struct Bounds {
-    centre: [f32; 3],
-    size: [f32; 3],
+pub(super) struct Bounds {
+    pub(super) centre: [f32; 3],
+    pub(super) size: [f32; 3],
 }
 
 impl Boundary for Bounds {
@@ -18,13 +20,13 @@ impl Boundary for Bounds {
 }
 
 #[derive(Debug, PartialEq, PartialOrd)]
-struct Player2D {
-    id: u32,
-    position: [f32; 2],
+pub(super) struct Player2D {
+    pub(super) id: u32,
+    pub(super) position: [f32; 2],
 }
 
 impl Player2D {
-    fn new(id: u32, position: [f32; 2]) -> Self {
+    pub(super) fn new(id: u32, position: [f32; 2]) -> Self {
         Self { id, position }
     }
 }
@@ -47,6 +49,40 @@ impl Coordinate for Player2D {
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub(super) struct Player3D {
+    pub(super) id: u32,
+    pub(super) position: [f32; 3],
+}
+
+impl Player3D {
+    pub(super) fn new(id: u32, position: [f32; 3]) -> Self {
+        Self { id, position }
+    }
+}
+
+impl Entity for Player3D {
+    type ID = u32;
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+}
+
+impl Coordinate for Player3D {
+    type Item = f32;
+    fn x(&self) -> Self::Item {
+        self.position[0]
+    }
+
+    fn y(&self) -> Self::Item {
+        self.position[1]
+    }
+
+    fn z(&self) -> Self::Item {
+        self.position[2]
+    }
+}
+
 #[test]
 fn grid_2d_3d_initialization() {
     let bounds_3d = Bounds {
@@ -88,6 +124,7 @@ fn grid_2d_3d_initialization() {
     // println!("{hashgrid_2d}");
 }
 
+#[cfg(feature = "std")]
 #[test]
 fn data_insertion_2d() {
     let bounds_2d = Bounds {
@@ -125,3 +162,3058 @@ fn data_insertion_2d() {
 
     println!("{res}");
 }
+
+#[test]
+fn relevant_query_2d() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let player1 = Player2D::new(0, [22.5, 30.0]);
+    let player2 = Player2D::new(2, [15.5, 45.6]);
+
+    let mut hashgrid_2d = HashGrid::<f32, Player2D>::new([2, 2], 0, &bounds_2d, true);
+    hashgrid_2d.insert(&player1);
+    hashgrid_2d.insert(&player2);
+
+    let query = Query::from((10.0, 10.0, 0.0), QueryType::Relevant, 0.0);
+    assert_eq!(hashgrid_2d.query(query).data(), [&player1, &player2]);
+}
+
+#[test]
+fn partition_traits() {
+    use crate::partition::{SpatialInsertion, SpatialQuery};
+
+    // A backend agnostic system written only against the partition traits
+    fn populate<'a, S>(structure: &mut S, players: &'a [Player2D])
+    where
+        S: SpatialInsertion<Item = &'a Player2D>,
+    {
+        structure.insert_all(players.iter());
+    }
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [22.5, 30.0]),
+        Player2D::new(1, [15.5, 45.6]),
+    ];
+
+    let mut hashgrid_2d = HashGrid::<f32, Player2D>::new([2, 2], 0, &bounds_2d, true);
+    populate(&mut hashgrid_2d, &players);
+
+    // A backend agnostic range query expressed as a geometry
+    let circle = Geometry::radius((15.0, 45.0, 0.0), 5.0);
+    let res = SpatialQuery::query(&hashgrid_2d, &circle).collect::<Vec<_>>();
+
+    assert_eq!(res, vec![&players[1]]);
+}
+
+#[test]
+fn removal_and_relocation() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [22.5, 30.0]),
+        Player2D::new(1, [15.5, 45.6]),
+    ];
+
+    let mut hashgrid_2d = HashGrid::<f32, Player2D>::new([4, 4], 0, &bounds_2d, false);
+    hashgrid_2d.update(&players);
+
+    // relocating the second player into a far away cell
+    assert!(hashgrid_2d.relocate(1, (-40.0, -40.0, 0.0)));
+    // relocating outside the bounds is rejected when wrap is disabled
+    assert!(!hashgrid_2d.relocate(1, (400.0, 400.0, 0.0)));
+
+    let near = hashgrid_2d.query(Query::from((20.0, 35.0, 0.0), QueryType::Relevant, 0.0));
+    assert_eq!(near.data(), &[&players[0]]);
+
+    let far = hashgrid_2d.query(Query::from((-40.0, -40.0, 0.0), QueryType::Find(1), 0.0));
+    assert_eq!(far.data(), &[&players[1]]);
+
+    assert!(hashgrid_2d.remove(0));
+    assert!(!hashgrid_2d.remove(0));
+    assert!(!hashgrid_2d.relocate(0, (0.0, 0.0, 0.0)));
+
+    let near = hashgrid_2d.query(Query::from((20.0, 35.0, 0.0), QueryType::Relevant, 0.0));
+    assert!(near.data().is_empty());
+}
+
+#[test]
+fn relocated_geometry_queries() {
+    let bounds_3d = Bounds {
+        centre: [0_f32, 0_f32, 15_f32],
+        size: [100_f32, 100_f32, 30_f32],
+    };
+
+    let players = [
+        Player3D::new(0, [5.0, 5.0, 5.0]),
+        Player3D::new(1, [-20.0, 10.0, 15.0]),
+    ];
+
+    let mut grid = HashGrid::<f32, Player3D>::new([10, 10], 3, &bounds_3d, false);
+    grid.update(&players);
+
+    // The relocated player is found at its new position only
+    assert!(grid.relocate(0, (45.0, 45.0, 5.0)));
+    let moved = Geometry::radius((45.0, 45.0, 5.0), 3.0);
+    let reported = Geometry::radius((5.0, 5.0, 5.0), 3.0);
+    assert_eq!(grid.query_geometry(&moved), vec![&players[0]]);
+    assert!(grid.query_geometry(&reported).is_empty());
+
+    assert_eq!(grid.nearest((40.0, 40.0, 5.0)).unwrap().0, &players[0]);
+    assert_eq!(grid.query_around(1, 100.0), vec![&players[0]]);
+    assert!(grid
+        .iter_all()
+        .any(|(e, position)| e.id == 0 && position == (45.0, 45.0, 5.0)));
+
+    // Refiling the floors keeps the relocated position
+    grid.retune(0.5);
+    assert_eq!(grid.query_geometry(&moved), vec![&players[0]]);
+    assert!(grid.query_geometry(&reported).is_empty());
+}
+
+#[test]
+fn geometry_queries() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [-12.0, 3.0]),
+        Player2D::new(1, [8.0, -4.0]),
+        Player2D::new(2, [30.0, 30.0]),
+        Player2D::new(3, [-45.0, -45.0]),
+    ];
+
+    let mut hashgrid_2d = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    hashgrid_2d.update(&players);
+
+    let ids = |geometry: Geometry<f32>| {
+        let mut ids = hashgrid_2d
+            .query_geometry(&geometry)
+            .iter()
+            .map(|p| p.id)
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    };
+
+    // rectangles and circles crossing the origin of the grid
+    assert_eq!(
+        ids(Geometry::rect((0.0, 0.0, 0.0), (30.0, 30.0, 0.0))),
+        vec![0, 1]
+    );
+    assert_eq!(ids(Geometry::radius((0.0, 0.0, 0.0), 10.0)), vec![1]);
+    assert_eq!(
+        ids(Geometry::radius((0.0, 0.0, 0.0), 100.0)),
+        vec![0, 1, 2, 3]
+    );
+    assert_eq!(ids(Geometry::point((30.0, 30.0, 0.0))), vec![2]);
+    assert_eq!(
+        ids(Geometry::rect_from_corners(
+            (-50.0, -50.0, 0.0),
+            (-40.0, -40.0, 0.0)
+        )),
+        vec![3]
+    );
+}
+
+#[test]
+fn geojson_export() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [5.0, 5.0]),
+        Player2D::new(1, [6.0, 7.0]),
+        Player2D::new(2, [-5.0, 5.0]),
+    ];
+
+    let mut hashgrid_2d = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    hashgrid_2d.update(&players);
+
+    let geojson = hashgrid_2d.to_geojson();
+
+    assert!(geojson.starts_with(r#"{"type":"FeatureCollection","features":["#));
+    assert_eq!(geojson.matches(r#""type":"Polygon""#).count(), 2);
+    assert!(geojson.contains(r#"[[[0,0],[10,0],[10,10],[0,10],[0,0]]]"#));
+    assert!(geojson.contains(r#"[[[-10,0],[0,0],[0,10],[-10,10],[-10,0]]]"#));
+    assert!(geojson.contains(r#""properties":{"floor":0,"count":2}"#));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn custom_hasher() {
+    use crate::hashgrid::DefaultHasher;
+    use std::collections::hash_map::RandomState;
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [22.5, 30.0]),
+        Player2D::new(1, [15.5, 45.6]),
+    ];
+
+    let mut hashgrid_2d = HashGrid::<f32, Player2D, u64, RandomState>::with_hasher(
+        [4, 4],
+        0,
+        &bounds_2d,
+        false,
+        RandomState::new(),
+    );
+    hashgrid_2d.update(&players);
+
+    let rect = Geometry::rect_from_corners((0.0, 25.0, 0.0), (25.0, 50.0, 0.0));
+    let mut res = hashgrid_2d.query_geometry(&rect);
+    res.sort_by_key(|p| p.id);
+
+    assert_eq!(res, vec![&players[0], &players[1]]);
+
+    // The default hasher can be named to spell out the full grid type
+    let mut default_2d = HashGrid::<f32, Player2D, u64, DefaultHasher>::with_hasher(
+        [4, 4],
+        0,
+        &bounds_2d,
+        false,
+        DefaultHasher::default(),
+    );
+    default_2d.update(&players);
+    assert_eq!(default_2d.query_geometry(&rect).len(), 2);
+}
+
+#[test]
+fn query_into_scratch() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [22.5, 30.0]),
+        Player2D::new(1, [15.5, 45.6]),
+    ];
+
+    let mut hashgrid_2d = HashGrid::<f32, Player2D>::new([4, 4], 0, &bounds_2d, false);
+    hashgrid_2d.update(&players);
+
+    let mut scratch = Vec::with_capacity(8);
+
+    hashgrid_2d.query_into(&Geometry::radius((22.0, 30.0, 0.0), 2.0), &mut scratch);
+    assert_eq!(scratch, vec![&players[0]]);
+
+    // Previous results are cleared while the capacity is kept
+    crate::partition::SpatialQuery::query_into(
+        &hashgrid_2d,
+        &Geometry::radius((15.0, 45.0, 0.0), 2.0),
+        &mut scratch,
+    );
+    assert_eq!(scratch, vec![&players[1]]);
+    assert_eq!(scratch.capacity(), 8);
+}
+
+#[test]
+fn batched_containment() {
+    let points = [
+        (0.0, 0.0, 0.0),
+        (2.0, 0.0, 0.0),
+        (2.5, 1.0, 0.0),
+        (-1.0, -1.0, 0.0),
+        (3.0, 3.0, 0.0),
+    ];
+
+    for geometry in [
+        Geometry::point((2.0, 0.0, 0.0)),
+        Geometry::rect((0.0, 0.0, 0.0), (4.0, 4.0, 0.0)),
+        Geometry::radius((0.0, 0.0, 0.0), 2.0),
+    ] {
+        let inside = geometry.contains_lanes(points);
+
+        for (point, inside) in points.iter().zip(inside) {
+            assert_eq!(geometry.contains(*point), inside);
+        }
+    }
+}
+
+#[test]
+fn memory_footprint() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [22.5, 30.0]),
+        Player2D::new(1, [15.5, 45.6]),
+        Player2D::new(2, [-30.0, -30.0]),
+    ];
+
+    let mut hashgrid_2d = HashGrid::<f32, Player2D>::new([4, 4], 0, &bounds_2d, false);
+    let empty = hashgrid_2d.memory_footprint();
+    assert_eq!(empty.buckets, 0);
+    assert_eq!(empty.items, 0);
+
+    hashgrid_2d.update(&players);
+    let populated = hashgrid_2d.memory_footprint();
+
+    assert_eq!(populated.structure, empty.structure);
+    assert!(populated.buckets > 0);
+    assert!(populated.items >= players.len() * core::mem::size_of::<&Player2D>());
+    assert_eq!(
+        populated.total(),
+        populated.structure + populated.buckets + populated.items
+    );
+}
+
+#[test]
+fn ordered_cell_iteration() {
+    use crate::hashgrid::CellOrder;
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [30.0, 5.0]),
+        Player2D::new(1, [5.0, 30.0]),
+        Player2D::new(2, [30.0, 30.0]),
+        Player2D::new(3, [5.0, 5.0]),
+    ];
+
+    let mut hashgrid_2d = HashGrid::<f32, Player2D>::new([4, 4], 0, &bounds_2d, false);
+    hashgrid_2d.update(&players);
+
+    let coordinates = |order| {
+        hashgrid_2d
+            .cells_in_order(order)
+            .iter()
+            .map(|cell| cell.coordinates)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        coordinates(CellOrder::RowMajor),
+        vec![[0, 0], [1, 0], [0, 1], [1, 1]]
+    );
+    assert_eq!(
+        coordinates(CellOrder::Morton),
+        vec![[0, 0], [1, 0], [0, 1], [1, 1]]
+    );
+    assert_eq!(
+        coordinates(CellOrder::Hilbert),
+        vec![[0, 0], [0, 1], [1, 1], [1, 0]]
+    );
+
+    let cells = hashgrid_2d.cells_in_order(CellOrder::Hilbert);
+    assert_eq!(cells[1].data, &[&players[1]]);
+}
+
+#[cfg(feature = "stats")]
+#[test]
+fn query_stats() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [30.0, 5.0]),
+        Player2D::new(1, [5.0, 5.0]),
+        Player2D::new(2, [10.0, 10.0]),
+    ];
+
+    let mut hashgrid_2d = HashGrid::<f32, Player2D>::new([4, 4], 0, &bounds_2d, false);
+    hashgrid_2d.update(&players);
+
+    let res = hashgrid_2d.query_geometry(&Geometry::radius((5.0, 5.0, 0.0), 1.0));
+    let stats = hashgrid_2d.last_query_stats();
+
+    assert_eq!(res, vec![&players[1]]);
+    assert_eq!(stats.cells_visited, 1);
+    assert_eq!(stats.cells_occupied, 1);
+    assert_eq!(stats.candidates_tested, 2);
+    assert_eq!(stats.results, 1);
+}
+
+#[test]
+fn fallible_operations() {
+    use crate::error::SpatialError;
+    use crate::hashgrid::CellsPerAxis;
+    use crate::layers::LayerMask;
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [22.5, 30.0]),
+        Player2D::new(1, [80.0, 30.0]),
+        Player2D::new(2, [f32::NAN, 30.0]),
+    ];
+
+    let mut hashgrid_2d = HashGrid::<f32, Player2D>::new([4, 4], 0, &bounds_2d, false);
+
+    assert_eq!(hashgrid_2d.try_insert(&players[0]), Ok(()));
+    assert_eq!(
+        hashgrid_2d.try_insert(&players[0]),
+        Err(SpatialError::DuplicateId(0))
+    );
+    assert_eq!(
+        hashgrid_2d.try_insert_with_layers(&players[0], LayerMask::layer(1)),
+        Err(SpatialError::DuplicateId(0))
+    );
+    assert_eq!(
+        hashgrid_2d.try_insert(&players[1]),
+        Err(SpatialError::OutOfBounds([80.0, 30.0, 0.0]))
+    );
+    assert!(matches!(
+        hashgrid_2d.try_insert(&players[2]),
+        Err(SpatialError::InvalidCoordinates(_))
+    ));
+
+    assert_eq!(
+        hashgrid_2d.try_relocate(1, (10.0, 10.0, 0.0)),
+        Err(SpatialError::UnknownEntity(1))
+    );
+    assert_eq!(
+        hashgrid_2d.try_query_geometry(&Geometry::Radius {
+            centre: (0.0, 0.0, 0.0),
+            radius: -1.0
+        }),
+        Err(SpatialError::InvalidQuery)
+    );
+    assert_eq!(
+        Geometry::try_radius((0.0, 0.0, 0.0), -1.0),
+        Err(SpatialError::InvalidQuery)
+    );
+    assert_eq!(
+        Geometry::try_rect((0.0, 0.0, 0.0), (10.0, -1.0, 0.0)),
+        Err(SpatialError::InvalidQuery)
+    );
+    assert_eq!(
+        Geometry::try_rect((0.0, 0.0, 0.0), (10.0, 10.0, 0.0)),
+        Ok(Geometry::rect((0.0, 0.0, 0.0), (10.0, 10.0, 0.0)))
+    );
+    assert_eq!(
+        SpatialError::<u32>::UnknownEntity(1).to_string(),
+        "unknown entity with id 1"
+    );
+    assert_eq!(
+        CellsPerAxis::try_from(&[4, 4, 4], 1).err(),
+        Some(SpatialError::DimensionMismatch {
+            expected: 2,
+            found: 3
+        })
+    );
+    assert!(CellsPerAxis::try_from(&[4, 4], 1).is_ok());
+
+    assert_eq!(hashgrid_2d.try_remove(0), Ok(&players[0]));
+    assert_eq!(
+        hashgrid_2d.try_remove(0),
+        Err(SpatialError::UnknownEntity(0))
+    );
+
+    // Heights below the origin are inside the bounds of a grid centred on it
+    let bounds_3d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 30_f32],
+    };
+    let below = Player3D::new(3, [10.0, 10.0, -12.0]);
+    let beyond = Player3D::new(4, [10.0, 10.0, -16.0]);
+
+    let mut hashgrid_3d = HashGrid::<f32, Player3D>::new([4, 4], 3, &bounds_3d, false);
+    assert_eq!(
+        hashgrid_3d.get_cell_coordinates((10.0, 10.0, -12.0)),
+        (0, 0, 0)
+    );
+    assert_eq!(hashgrid_3d.try_insert(&below), Ok(()));
+    assert_eq!(
+        hashgrid_3d.try_query_geometry(&Geometry::radius((10.0, 10.0, -12.0), 1.0)),
+        Ok(vec![&below])
+    );
+
+    let query = Query::from((10.0, 10.0, -12.0), QueryType::Relevant, f32::INFINITY);
+    assert_eq!(hashgrid_3d.query(query).data(), [&below]);
+    assert_eq!(
+        hashgrid_3d.try_insert(&beyond),
+        Err(SpatialError::OutOfBounds([10.0, 10.0, -16.0]))
+    );
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn matches_brute_force_oracle() {
+    use crate::testing::{assert_matches_oracle, random_geometries, SyntheticWorld};
+
+    for world in [
+        SyntheticWorld::uniform(2_000, 1_000.0, 7),
+        SyntheticWorld::clustered(2_000, 1_000.0, 5, 60.0, 7),
+    ] {
+        let geometries = random_geometries(&world.bounds, 200, 150.0, 11);
+
+        for wrap in [false, true] {
+            let mut hashgrid = HashGrid::<f32, _>::new([16, 16], 0, &world.bounds, wrap);
+            hashgrid.update(&world.entities);
+
+            assert_matches_oracle(&hashgrid, &world.entities, &geometries);
+
+            for point in world.sample_points(50, 13) {
+                let point = (point.0 + 7.0, point.1 - 3.0, 0.0);
+                let expected = world
+                    .entities
+                    .iter()
+                    .map(|e| crate::math::distance(point, (e.x(), e.y(), e.z())))
+                    .fold(f32::INFINITY, f32::min);
+
+                assert_eq!(hashgrid.nearest(point).map(|(_, d)| d), Some(expected));
+            }
+        }
+    }
+}
+
+#[test]
+fn deterministic_results() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(2, [10.0, 10.0]),
+        Player2D::new(0, [12.0, 10.0]),
+        Player2D::new(1, [14.0, 10.0]),
+    ];
+
+    let mut hashgrid_2d = HashGrid::<f32, Player2D>::new([4, 4], 0, &bounds_2d, false);
+    hashgrid_2d.update(&players);
+
+    let query = Query::from((10.0, 10.0, 0.0), QueryType::Relevant, 0.0);
+    let ids = |res: &[&Player2D]| res.iter().map(|p| p.id).collect::<Vec<_>>();
+
+    // Insertion order by default
+    assert_eq!(ids(hashgrid_2d.query(query).data()), vec![2, 0, 1]);
+
+    hashgrid_2d.set_deterministic(true);
+    assert_eq!(ids(hashgrid_2d.query(query).data()), vec![0, 1, 2]);
+}
+
+#[test]
+fn deterministic_geometry_queries() {
+    use crate::partition::SpatialQuery;
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(2, [10.0, 10.0]),
+        Player2D::new(0, [12.0, 10.0]),
+        Player2D::new(3, [30.0, 10.0]),
+        Player2D::new(1, [14.0, 11.0]),
+    ];
+
+    // Both grids hold the same entities, inserted in opposite orders
+    let mut forward = HashGrid::<f32, Player2D>::new([4, 4], 0, &bounds_2d, false);
+    let mut backward = HashGrid::<f32, Player2D>::new([4, 4], 0, &bounds_2d, false);
+    forward.set_deterministic(true);
+    backward.set_deterministic(true);
+    players.iter().for_each(|p| forward.insert(p));
+    players.iter().rev().for_each(|p| backward.insert(p));
+
+    let ids = |res: &[&Player2D]| res.iter().map(|p| p.id).collect::<Vec<_>>();
+    let area = Geometry::rect((20.0, 20.0, 0.0), (40.0, 40.0, 0.0));
+    let hole = Geometry::radius((30.0, 10.0, 0.0), 1.0);
+
+    for grid in [&forward, &backward] {
+        assert_eq!(ids(&grid.query_geometry(&area)), vec![0, 1, 2, 3]);
+        assert_eq!(ids(&grid.query_difference(&area, &hole)), vec![0, 1, 2]);
+        assert_eq!(ids(&grid.query_budgeted(&area, 64).0), vec![0, 1, 2, 3]);
+
+        let mut results = Vec::new();
+        SpatialQuery::query_into(grid, &area, &mut results);
+        assert_eq!(ids(&results), vec![0, 1, 2, 3]);
+        assert_eq!(
+            ids(&SpatialQuery::query(grid, &area).collect::<Vec<_>>()),
+            vec![0, 1, 2, 3]
+        );
+    }
+}
+
+#[test]
+#[should_panic(expected = "The layer index must be in the range 0..32")]
+fn layer_index_out_of_range() {
+    let _ = crate::layers::LayerMask::layer(32);
+}
+
+#[test]
+fn layered_queries() {
+    use crate::layers::LayerMask;
+
+    const PLAYERS: LayerMask = LayerMask::layer(0);
+    const PROJECTILES: LayerMask = LayerMask::layer(1);
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let entities = [
+        Player2D::new(0, [10.0, 10.0]),
+        Player2D::new(1, [12.0, 10.0]),
+        Player2D::new(2, [14.0, 10.0]),
+    ];
+
+    let mut hashgrid_2d = HashGrid::<f32, Player2D>::new([4, 4], 0, &bounds_2d, false);
+    hashgrid_2d.insert_with_layers(&entities[0], PLAYERS);
+    hashgrid_2d.insert_with_layers(&entities[1], PROJECTILES);
+    hashgrid_2d.insert(&entities[2]);
+
+    let area = Geometry::radius((12.0, 10.0, 0.0), 5.0);
+    assert_eq!(
+        hashgrid_2d.query_geometry_with_layers(&area, PLAYERS),
+        vec![&entities[0], &entities[2]]
+    );
+    assert_eq!(
+        hashgrid_2d.query_geometry_with_layers(&area, PLAYERS | PROJECTILES),
+        vec![&entities[0], &entities[1], &entities[2]]
+    );
+
+    // Relocated entities keep their layers
+    assert!(hashgrid_2d.relocate(1, (40.0, 40.0, 0.0)));
+    let query = Query::from((40.0, 40.0, 0.0), QueryType::Find(1), 0.0);
+    assert_eq!(
+        hashgrid_2d.query_with_layers(query, PROJECTILES).data(),
+        &[&entities[1]]
+    );
+    assert!(hashgrid_2d
+        .query_with_layers(query, PLAYERS)
+        .data()
+        .is_empty());
+
+    // Layers are looked up by id, entities inserted without a mask being in every layer
+    assert_eq!(hashgrid_2d.layers_of(1), Some(PROJECTILES));
+    assert_eq!(hashgrid_2d.layers_of(2), Some(LayerMask::ALL));
+    assert_eq!(hashgrid_2d.layers_of(3), None);
+}
+
+#[test]
+fn difference_queries() {
+    use crate::linear::LinearIndex;
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [10.0, 10.0]),
+        Player2D::new(1, [14.0, 10.0]),
+        Player2D::new(2, [30.0, 10.0]),
+    ];
+
+    let mut hashgrid_2d = HashGrid::<f32, Player2D>::new([4, 4], 0, &bounds_2d, false);
+    hashgrid_2d.update(&players);
+
+    let mut index = LinearIndex::new();
+    players.iter().for_each(|p| index.insert(p));
+
+    // Donut shaped range around the first player
+    let outer = Geometry::radius((10.0, 10.0, 0.0), 10.0);
+    let inner = Geometry::radius((10.0, 10.0, 0.0), 2.0);
+
+    assert_eq!(
+        hashgrid_2d.query_difference(&outer, &inner),
+        vec![&players[1]]
+    );
+    assert_eq!(index.query_difference(&outer, &inner), vec![&players[1]]);
+}
+
+#[test]
+fn batched_ingest() {
+    use crate::hashgrid::IngestProgress;
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = (0..5)
+        .map(|i| Player2D::new(i, [15.0 * i as f32, 10.0]))
+        .collect::<Vec<_>>();
+
+    let mut hashgrid_2d = HashGrid::<f32, Player2D>::new([4, 4], 0, &bounds_2d, false);
+    let progress = hashgrid_2d.ingest(&players, 2).collect::<Vec<_>>();
+
+    // The last player lies outside the bounds and is rejected
+    assert_eq!(
+        progress,
+        vec![
+            IngestProgress {
+                inserted: 2,
+                rejected: 0
+            },
+            IngestProgress {
+                inserted: 4,
+                rejected: 0
+            },
+            IngestProgress {
+                inserted: 4,
+                rejected: 1
+            },
+        ]
+    );
+    assert_eq!(
+        hashgrid_2d
+            .query_geometry(&Geometry::rect_from_corners(
+                (0.0, 0.0, 0.0),
+                (50.0, 50.0, 0.0)
+            ))
+            .len(),
+        4
+    );
+}
+
+#[test]
+fn nearest_neighbour() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [-40.0, -40.0]),
+        Player2D::new(1, [20.0, 20.0]),
+        Player2D::new(2, [45.0, -10.0]),
+    ];
+
+    let mut hashgrid_2d = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    assert!(hashgrid_2d.nearest((0.0, 0.0, 0.0)).is_none());
+
+    hashgrid_2d.update(&players);
+
+    // The mirrored cell of the first player is closer to the point than its real cell
+    let (nearest, d) = hashgrid_2d.nearest((38.0, 38.0, 0.0)).unwrap();
+    assert_eq!(nearest, &players[1]);
+    assert!((d - 18.0 * 2_f32.sqrt()).abs() < 1e-4);
+
+    let (nearest, _) = hashgrid_2d.nearest((-45.0, -30.0, 0.0)).unwrap();
+    assert_eq!(nearest, &players[0]);
+
+    // Points outside the bounds are accepted as well
+    let (nearest, d) = hashgrid_2d.nearest((80.0, -10.0, 0.0)).unwrap();
+    assert_eq!(nearest, &players[2]);
+    assert_eq!(d, 35.0);
+}
+
+#[test]
+fn boundary_operations() {
+    let a = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+    let b = Bounds {
+        centre: [75_f32, 0_f32, 0_f32],
+        size: [100_f32, 50_f32, 0_f32],
+    };
+    let far = Bounds {
+        centre: [500_f32, 0_f32, 0_f32],
+        size: [10_f32, 10_f32, 0_f32],
+    };
+
+    assert!(a.intersects(&b));
+    assert!(!a.intersects(&far));
+    assert!(a.overlap(&far).is_none());
+
+    let overlap = a.overlap(&b).unwrap();
+    assert_eq!(overlap.centre(), [37.5, 0.0, 0.0]);
+    assert_eq!(overlap.size(), [25.0, 50.0, 0.0]);
+    assert_eq!(overlap.volume(), 1250.0);
+
+    let expanded = a.expand(10.0);
+    assert_eq!(expanded.size(), [120.0, 120.0, 0.0]);
+    assert!(expanded.is_inside((55.0, -55.0, 0.0)));
+    assert!(!a.is_inside((55.0, -55.0, 0.0)));
+
+    // Negative sizes are treated as their absolute value
+    let flipped = Bounds {
+        centre: [0_f32; 3],
+        size: [-100_f32, -100_f32, 0_f32],
+    };
+    assert!(flipped.is_inside((10.0, 10.0, 0.0)));
+}
+
+#[test]
+fn query_status() {
+    use crate::hashgrid::QueryStatus;
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [Player2D::new(0, [45.0, 45.0])];
+
+    let mut wrapped = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, true);
+    let mut bounded = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    wrapped.update(&players);
+    bounded.update(&players);
+
+    let inside = Query::from((45.0, 45.0, 0.0), QueryType::Relevant, 0.0);
+    assert_eq!(bounded.query(inside).status(), QueryStatus::InBounds);
+
+    // Outside of the world the wrapped grid answers from the nearest boundary cell
+    let outside = Query::from((300.0, 300.0, 0.0), QueryType::Relevant, 0.0);
+
+    let res = wrapped.query(outside);
+    assert_eq!(res.status(), QueryStatus::Clamped);
+    assert_eq!(res.data(), &[&players[0]]);
+
+    let res = bounded.query(outside);
+    assert_eq!(res.status(), QueryStatus::OutOfBounds);
+    assert!(res.data().is_empty());
+}
+
+#[test]
+fn rebuild_region() {
+    use core::cell::Cell;
+
+    #[derive(Debug, PartialEq)]
+    struct Debris {
+        id: u32,
+        position: Cell<[f32; 2]>,
+    }
+
+    impl Entity for Debris {
+        type ID = u32;
+        fn id(&self) -> Self::ID {
+            self.id
+        }
+    }
+
+    impl Coordinate for Debris {
+        type Item = f32;
+        fn x(&self) -> Self::Item {
+            self.position.get()[0]
+        }
+
+        fn y(&self) -> Self::Item {
+            self.position.get()[1]
+        }
+    }
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let debris: Vec<_> = [[5.0, 5.0], [7.0, 7.0], [35.0, 35.0], [45.0, 45.0]]
+        .into_iter()
+        .enumerate()
+        .map(|(id, p)| Debris {
+            id: id as u32,
+            position: Cell::new(p),
+        })
+        .collect();
+
+    let mut grid = HashGrid::<f32, Debris>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&debris);
+
+    // A blast moves the debris around the origin, and the one far away as well
+    debris[0].position.set([25.0, 25.0]);
+    debris[1].position.set([500.0, 500.0]);
+    debris[3].position.set([5.0, 5.0]);
+
+    let blast = Geometry::radius((5.0, 5.0, 0.0), 5.0);
+    assert_eq!(grid.rebuild_region(&blast), 1);
+
+    // Debris out of the bounds is dropped, the rest is found in its new cell
+    let around = |x: f32, y: f32| Geometry::rect((x, y, 0.0), (4.0, 4.0, 0.0));
+    assert_eq!(grid.query_geometry(&around(25.0, 25.0)), vec![&debris[0]]);
+    assert!(!grid.remove(1));
+
+    // Cells outside the region are untouched, so the far debris is still filed in its old cell
+    assert!(grid.query_geometry(&around(5.0, 5.0)).is_empty());
+    assert_eq!(grid.rebuild_region(&around(45.0, 45.0)), 1);
+    assert_eq!(grid.query_geometry(&around(5.0, 5.0)), vec![&debris[3]]);
+}
+
+#[test]
+fn ttl_eviction() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let events = [
+        Player2D::new(0, [5.0, 5.0]),
+        Player2D::new(1, [6.0, 6.0]),
+        Player2D::new(2, [25.0, 25.0]),
+    ];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.insert(&events[0]);
+    grid.insert_with_ttl(&events[1], 2);
+
+    assert_eq!(grid.expire(1), 0);
+
+    // TTLs count from the latest expired tick
+    grid.insert_with_ttl(&events[2], 2);
+
+    let everything = Geometry::rect((0.0, 0.0, 0.0), (100.0, 100.0, 0.0));
+    assert_eq!(grid.expire(2), 1);
+    assert_eq!(
+        grid.query_geometry(&everything),
+        vec![&events[0], &events[2]]
+    );
+
+    // TTLs are kept when the entities are filed again
+    let mut grid = grid.with_storage(crate::hashgrid::CellStorage::Dense);
+
+    // Entities without a TTL are never evicted, and the emptied cell is dropped
+    assert_eq!(grid.expire(100), 1);
+    assert_eq!(grid.query_geometry(&everything), vec![&events[0]]);
+    assert_eq!(grid.occupied_cells(0), 1);
+}
+
+#[test]
+fn extent_queries() {
+    use crate::hashgrid::ExtentEntity;
+
+    #[derive(Debug, PartialEq)]
+    struct Unit {
+        id: u32,
+        position: [f32; 2],
+        radius: f32,
+    }
+
+    impl Entity for Unit {
+        type ID = u32;
+        fn id(&self) -> Self::ID {
+            self.id
+        }
+    }
+
+    impl Coordinate for Unit {
+        type Item = f32;
+        fn x(&self) -> Self::Item {
+            self.position[0]
+        }
+
+        fn y(&self) -> Self::Item {
+            self.position[1]
+        }
+    }
+
+    impl ExtentEntity for Unit {
+        fn extent(&self) -> Geometry<f32> {
+            Geometry::radius((self.x(), self.y(), 0.0), self.radius)
+        }
+    }
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let units = [
+        Unit {
+            id: 0,
+            position: [12.0, 12.0],
+            radius: 1.0,
+        },
+        Unit {
+            id: 1,
+            position: [25.0, 12.0],
+            radius: 8.0,
+        },
+    ];
+
+    let mut grid = HashGrid::<f32, Unit>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&units);
+
+    // The centre of the large unit lies outside of the area, its extent does not
+    let area = Geometry::rect((15.0, 12.0, 0.0), (6.0, 6.0, 0.0));
+    assert_eq!(grid.query_geometry(&area), vec![&units[0]]);
+    assert_eq!(grid.query_extents(&area, 8.0), vec![&units[0], &units[1]]);
+
+    // Half of the small unit is exposed, and a sliver of the large one
+    assert_eq!(
+        grid.query_with_overlap(&area, 8.0),
+        vec![(&units[0], 0.5), (&units[1], 6.0 / 256.0)]
+    );
+
+    let mut res = grid.query(Query::from((12.0, 12.0, 0.0), QueryType::Relevant, 0.0));
+    assert_eq!(res.data().len(), 2);
+    res.retain_overlapping(&Geometry::point((10.0, 12.0, 0.0)));
+    assert!(res.data().is_empty());
+
+    // Relocated entities are clipped where they were moved to, not where they report to be
+    assert!(grid.relocate(0, (-30.0, -30.0, 0.0)));
+    let query = Query::from((-30.0, -30.0, 0.0), QueryType::Relevant, 0.0);
+
+    let mut res = grid.query(query);
+    assert_eq!(res.data(), [&units[0]]);
+    res.retain_overlapping(&Geometry::point((-30.5, -30.0, 0.0)));
+    assert_eq!(res.data(), [&units[0]]);
+
+    let mut res = grid.query(query);
+    res.retain_overlapping(&Geometry::point((12.0, 12.0, 0.0)));
+    assert!(res.data().is_empty());
+
+    assert!(area.intersects(&Geometry::radius((20.0, 12.0, 0.0), 2.0)));
+    assert!(!area.intersects(&Geometry::radius((21.0, 16.0, 0.0), 1.0)));
+    assert!(
+        Geometry::radius((0.0, 0.0, 0.0), 1.0).intersects(&Geometry::radius((3.0, 0.0, 0.0), 2.0))
+    );
+}
+
+#[test]
+fn backend_conversion() {
+    use crate::linear::LinearIndex;
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [25.0, 5.0]),
+        Player2D::new(1, [5.0, 5.0]),
+        Player2D::new(2, [80.0, 5.0]),
+    ];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    let index = LinearIndex::from_hashgrid(&grid);
+    assert_eq!(index.data(), &[&players[1], &players[0]]);
+
+    // Entities out of the bounds of the new grid are left out
+    let mut linear = LinearIndex::<f32, Player2D>::new();
+    players.iter().for_each(|p| linear.insert(p));
+
+    let grid = HashGrid::<f32, Player2D>::from_linear(&linear, [10, 10], 0, &bounds_2d, false);
+    let everything = Geometry::rect((0.0, 0.0, 0.0), (100.0, 100.0, 0.0));
+    assert_eq!(
+        grid.query_geometry(&everything),
+        vec![&players[1], &players[0]]
+    );
+}
+
+#[test]
+fn linear_relocation() {
+    use crate::linear::LinearIndex;
+    use crate::partition::SpatialUpdate;
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [Player2D::new(0, [25.0, 5.0]), Player2D::new(1, [5.0, 5.0])];
+
+    let mut linear = LinearIndex::<f32, Player2D>::new();
+    players.iter().for_each(|p| linear.insert(p));
+
+    let origin = Geometry::radius((0.0, 0.0, 0.0), 10.0);
+    let corner = Geometry::radius((-40.0, -40.0, 0.0), 5.0);
+
+    assert!(linear.relocate(0, (-40.0, -40.0, 0.0)));
+    assert!(SpatialUpdate::relocate(&mut linear, 1, (-38.0, -40.0, 0.0)));
+    assert!(!linear.relocate(7, (0.0, 0.0, 0.0)));
+    assert!(!linear.relocate(0, (f32::NAN, 0.0, 0.0)));
+
+    assert!(linear.query_geometry(&origin).is_empty());
+    assert_eq!(
+        linear.query_geometry(&corner),
+        vec![&players[0], &players[1]]
+    );
+    assert_eq!(
+        linear.query_difference(&corner, &Geometry::radius((-38.0, -40.0, 0.0), 1.0)),
+        vec![&players[0]]
+    );
+
+    // The relocated coordinates carry over to the grid and back
+    let grid = HashGrid::<f32, Player2D>::from_linear(&linear, [10, 10], 0, &bounds_2d, false);
+    assert_eq!(grid.query_geometry(&origin), Vec::<&Player2D>::new());
+    assert_eq!(grid.query_geometry(&corner).len(), 2);
+
+    let index = LinearIndex::from_hashgrid(&grid);
+    assert_eq!(index.query_geometry(&corner).len(), 2);
+    assert!(index.query_geometry(&origin).is_empty());
+
+    assert!(linear.remove(0));
+    assert_eq!(linear.query_geometry(&corner), vec![&players[1]]);
+}
+
+#[test]
+fn slab_queries() {
+    use crate::linear::LinearIndex;
+    use crate::shards::GridShards;
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [-45.0, 10.0]),
+        Player2D::new(1, [5.0, -30.0]),
+        Player2D::new(2, [40.0, 12.0]),
+    ];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    let mut linear = LinearIndex::<f32, Player2D>::new();
+    players.iter().for_each(|p| linear.insert(p));
+
+    let mut shards = GridShards::<f32, Player2D>::new([20.0, 20.0, 0.0], [4, 4], 0);
+    shards.update(&players);
+
+    let tide = Geometry::hslab(8.0, 12.0);
+    let front = Geometry::vslab(0.0, 50.0);
+
+    for results in [
+        grid.query_geometry(&tide),
+        linear.query_geometry(&tide),
+        shards.query_geometry(&tide),
+    ] {
+        assert_eq!(results.len(), 2);
+        assert!(results.contains(&&players[0]) && results.contains(&&players[2]));
+    }
+
+    for results in [
+        grid.query_geometry(&front),
+        linear.query_geometry(&front),
+        shards.query_geometry(&front),
+    ] {
+        assert_eq!(results.len(), 2);
+        assert!(results.contains(&&players[1]) && results.contains(&&players[2]));
+    }
+
+    assert!(tide.intersects(&front));
+    assert!(tide.intersects(&Geometry::radius((0.0, 14.0, 0.0), 2.0)));
+    assert!(!Geometry::<f32>::hslab(1.0, 0.0).is_valid());
+}
+
+#[test]
+fn distance_clusters() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    // A chain crossing the origin, a pair and a loner
+    let players = [
+        Player2D::new(4, [-12.0, 0.0]),
+        Player2D::new(0, [-4.0, 0.0]),
+        Player2D::new(3, [4.0, 0.0]),
+        Player2D::new(1, [30.0, 30.0]),
+        Player2D::new(5, [36.0, 34.0]),
+        Player2D::new(2, [-40.0, 40.0]),
+    ];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    assert_eq!(grid.clusters(8.0), vec![vec![0, 3, 4], vec![1, 5], vec![2]]);
+    assert_eq!(grid.clusters(1.0).len(), 6);
+}
+
+#[test]
+fn region_statistics() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [-4.0, 0.0]),
+        Player2D::new(1, [4.0, 0.0]),
+        Player2D::new(2, [0.0, 3.0]),
+        Player2D::new(3, [0.0, -3.0]),
+        Player2D::new(4, [40.0, 40.0]),
+    ];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    let camp = Geometry::radius((0.0, 0.0, 0.0), 10.0);
+    let stats = grid.region_stats(&camp).unwrap();
+    assert_eq!(stats.count, 4);
+    assert_eq!(stats.centroid, (0.0, 0.0, 0.0));
+    assert!((stats.spread - 12.5_f32.sqrt()).abs() < 1e-5);
+
+    // Only the units along the x axis weigh in
+    let stats = grid
+        .region_stats_by(&camp, |p| if p.position[1] == 0.0 { 1.0 } else { 0.0 })
+        .unwrap();
+    assert_eq!(stats.count, 4);
+    assert_eq!(stats.centroid, (0.0, 0.0, 0.0));
+    assert_eq!(stats.spread, 4.0);
+
+    let stats = grid
+        .region_stats_by(&camp, |p| if p.id == 1 { 3.0 } else { 1.0 })
+        .unwrap();
+    assert!((stats.centroid.0 - 4.0 / 3.0).abs() < 1e-5);
+
+    assert_eq!(
+        grid.region_stats(&Geometry::radius((-40.0, -40.0, 0.0), 5.0)),
+        None
+    );
+}
+
+#[test]
+fn path_queries() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [-20.0, 1.0]),
+        Player2D::new(1, [0.0, -1.5]),
+        Player2D::new(2, [19.0, 10.0]),
+        Player2D::new(3, [10.0, 10.0]),
+        Player2D::new(4, [22.0, 30.0]),
+    ];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    // An L shaped route, east along the x axis then north
+    let route = [(-30.0, 0.0), (20.0, 0.0), (20.0, 25.0)];
+
+    let mut hazards = grid.query_along_path(&route, 4.0);
+    hazards.sort_by_key(|p| p.id);
+    assert_eq!(hazards, vec![&players[0], &players[1], &players[2]]);
+
+    assert_eq!(
+        grid.query_along_path(&[(10.0, 12.0)], 4.0),
+        vec![&players[3]]
+    );
+    assert!(grid.query_along_path(&[], 4.0).is_empty());
+}
+
+#[test]
+fn per_floor_resolution() {
+    #[derive(Debug, PartialEq)]
+    struct Miner {
+        id: u32,
+        position: [f32; 3],
+    }
+
+    impl Entity for Miner {
+        type ID = u32;
+        fn id(&self) -> Self::ID {
+            self.id
+        }
+    }
+
+    impl Coordinate for Miner {
+        type Item = f32;
+        fn x(&self) -> Self::Item {
+            self.position[0]
+        }
+
+        fn y(&self) -> Self::Item {
+            self.position[1]
+        }
+
+        fn z(&self) -> Self::Item {
+            self.position[2]
+        }
+    }
+
+    let bounds_3d = Bounds {
+        centre: [0_f32, 0_f32, 10_f32],
+        size: [100_f32, 100_f32, 20_f32],
+    };
+
+    let miners = [
+        Miner {
+            id: 0,
+            position: [5.0, 5.0, 2.0],
+        },
+        Miner {
+            id: 1,
+            position: [40.0, 40.0, 2.0],
+        },
+        Miner {
+            id: 2,
+            position: [5.0, 5.0, 15.0],
+        },
+        Miner {
+            id: 3,
+            position: [20.0, 20.0, 15.0],
+        },
+    ];
+
+    let mut grid = HashGrid::<f32, Miner>::new([10, 10], 2, &bounds_3d, false);
+    grid.update(&miners);
+    assert_eq!(grid.occupied_cells(0), 2);
+
+    // A coarse underground, its entities being filed again into a single cell
+    let grid = grid.with_floor_cells(0, [2, 2]);
+    assert_eq!(grid.floor_cells(0), [2, 2]);
+    assert_eq!(grid.floor_cell_sizes(0), (50.0, 50.0));
+    assert_eq!(grid.floor_cells(1), [10, 10]);
+    assert_eq!(grid.occupied_cells(0), 1);
+    assert_eq!(grid.occupied_cells(1), 2);
+
+    let shaft = Geometry::rect((10.0, 10.0, 10.0), (16.0, 16.0, 20.0));
+    let mut found = grid.query_geometry(&shaft);
+    found.sort_by_key(|m| m.id);
+    assert_eq!(found, vec![&miners[0], &miners[2]]);
+
+    let (nearest, _) = grid.nearest((38.0, 38.0, 2.0)).unwrap();
+    assert_eq!(nearest, &miners[1]);
+
+    let res = grid.query(Query::from((5.0, 5.0, 2.0), QueryType::Find(1), 0.0));
+    assert_eq!(res.data(), &[&miners[1]]);
+}
+
+#[test]
+fn world_unit_query_radius() {
+    use crate::hashgrid::Relevance;
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [5.0, 5.0]),
+        Player2D::new(1, [15.0, 5.0]),
+        Player2D::new(2, [35.0, 5.0]),
+    ];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.set_deterministic(true);
+    grid.update(&players);
+
+    // The neighbouring cells are always visited
+    let near = Query::from((5.0, 5.0, 0.0), QueryType::Relevant, 0.0);
+    assert_eq!(grid.query(near).data(), &[&players[0], &players[1]]);
+
+    // A radius of 30 world units spans three cells of 10
+    let far = Query::from((5.0, 5.0, 0.0), QueryType::Relevant, 30.0);
+    assert_eq!(
+        grid.query(far).data(),
+        &players.iter().collect::<Vec<_>>()[..]
+    );
+
+    assert_eq!(Relevance(0.25).to_world_radius(&bounds_2d), 25.0);
+}
+
+#[test]
+fn coordinate_frames() {
+    use crate::hashgrid::{CoordinateFrame, Origin, YAxis};
+
+    let bounds_2d = Bounds {
+        centre: [105_f32, 105_f32, 0_f32],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [58.0, 58.0]),
+        Player2D::new(1, [62.0, 58.0]),
+    ];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    // Centred cells are counted from the world origin
+    assert_eq!(grid.get_cell_coordinates((58.0, 58.0, 0.0)), (5, 5, 0));
+    assert_eq!(grid.get_cell_coordinates((62.0, 58.0, 0.0)), (6, 5, 0));
+
+    let corner = CoordinateFrame {
+        origin: Origin::Corner,
+        y_axis: YAxis::Up,
+    };
+    let grid = grid.with_frame(corner);
+    assert_eq!(grid.frame(), corner);
+
+    // Cornered cells are counted from the minimum corner of the bounds, at 55
+    assert_eq!(grid.get_cell_coordinates((58.0, 58.0, 0.0)), (0, 0, 0));
+    assert_eq!(grid.get_cell_coordinates((62.0, 58.0, 0.0)), (0, 0, 0));
+    assert_eq!(grid.get_cell_coordinates((154.0, 66.0, 0.0)), (9, 1, 0));
+
+    let rect = Geometry::rect((60.0, 60.0, 0.0), (10.0, 10.0, 0.0));
+    let mut found: Vec<_> = grid.query_geometry(&rect).iter().map(|p| p.id).collect();
+    found.sort();
+    assert_eq!(found, [0, 1]);
+    assert_eq!(grid.nearest((70.0, 58.0, 0.0)).map(|(p, _)| p.id), Some(1));
+
+    let geojson = grid.to_geojson();
+    assert!(geojson.contains(r#"[[[55,55],[65,55],[65,65],[55,65],[55,55]]]"#));
+
+    // Y-down grids are flipped into the y-up GeoJSON coordinates
+    let grid = grid.with_frame(CoordinateFrame {
+        y_axis: YAxis::Down,
+        ..corner
+    });
+    let geojson = grid.to_geojson();
+    assert!(geojson.contains(r#"[[[55,-65],[65,-65],[65,-55],[55,-55],[55,-65]]]"#));
+}
+
+#[test]
+fn corner_frame_boundaries() {
+    use crate::hashgrid::{CoordinateFrame, Origin};
+
+    let bounds_3d = Bounds {
+        centre: [50_f32, 50_f32, 10_f32],
+        size: [100_f32, 100_f32, 20_f32],
+    };
+
+    // Entities on the faces of the bounds, the top one exactly at the maximum height
+    let players = [
+        Player3D::new(0, [0.0, 0.0, 0.0]),
+        Player3D::new(1, [100.0, 100.0, 20.0]),
+        Player3D::new(2, [50.0, 50.0, 10.0]),
+    ];
+
+    let mut grid = HashGrid::<f32, Player3D>::new([10, 10], 2, &bounds_3d, false).with_frame(
+        CoordinateFrame {
+            origin: Origin::Corner,
+            ..CoordinateFrame::default()
+        },
+    );
+    for player in players.iter() {
+        assert_eq!(grid.try_insert(player), Ok(()));
+    }
+
+    assert_eq!(grid.get_cell_coordinates((0.0, 0.0, 0.0)), (0, 0, 0));
+    assert_eq!(grid.get_cell_coordinates((100.0, 100.0, 20.0)), (10, 10, 1));
+    assert_eq!(grid.get_cell_coordinates((50.0, 50.0, 10.0)), (5, 5, 1));
+
+    let top = Geometry::radius((100.0, 100.0, 20.0), 1.0);
+    assert_eq!(grid.query_geometry(&top), vec![&players[1]]);
+    assert_eq!(grid.nearest((99.0, 99.0, 20.0)).map(|(p, _)| p.id), Some(1));
+
+    assert!(grid.remove(1));
+    assert_eq!(grid.iter_all().count(), 2);
+}
+
+#[test]
+fn bulk_export() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [5.0, 5.0]),
+        Player2D::new(1, [-15.0, 25.0]),
+        Player2D::new(2, [35.0, -45.0]),
+    ];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    assert_eq!(grid.iter_all().count(), 0);
+
+    grid.update(&players);
+    grid.remove(1);
+
+    let mut snapshot = grid.to_vec();
+    snapshot.sort_by_key(|(p, _)| p.id);
+    assert_eq!(
+        snapshot,
+        vec![
+            (&players[0], (5.0, 5.0, 0.0)),
+            (&players[2], (35.0, -45.0, 0.0)),
+        ]
+    );
+    assert_eq!(grid.iter_all().count(), 2);
+}
+
+#[test]
+fn budgeted_queries() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [Player2D::new(0, [5.0, 5.0]), Player2D::new(1, [5.0, 35.0])];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    // The rect spans the cells 0..=4 along both axis
+    let rect = Geometry::rect((20.0, 20.0, 0.0), (40.0, 40.0, 0.0));
+
+    let (found, complete) = grid.query_budgeted(&rect, 25);
+    assert!(complete);
+    assert_eq!(found.len(), 2);
+
+    let (found, complete) = grid.query_budgeted(&rect, 2);
+    assert!(!complete);
+    assert_eq!(found, vec![&players[0]]);
+
+    let (found, complete) = grid.query_budgeted(&rect, 0);
+    assert!(!complete);
+    assert!(found.is_empty());
+}
+
+#[test]
+fn snapshot_diff() {
+    use crate::hashgrid::GridDiff;
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let before = [
+        Player2D::new(0, [5.0, 5.0]),
+        Player2D::new(1, [-15.0, 25.0]),
+        Player2D::new(2, [35.0, -45.0]),
+    ];
+    let after = [
+        Player2D::new(0, [5.0, 5.0]),
+        Player2D::new(2, [36.0, -45.0]),
+        Player2D::new(3, [0.0, 0.0]),
+    ];
+
+    let mut older = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    older.update(&before);
+    let mut newer = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    newer.update(&after);
+
+    assert_eq!(
+        newer.diff(&older),
+        GridDiff {
+            added: vec![3],
+            removed: vec![1],
+            moved: vec![2],
+        }
+    );
+    assert!(older.diff(&older).is_empty());
+}
+
+#[test]
+fn cell_neighbourhoods() {
+    use crate::hashgrid::Neighbourhood;
+
+    let bounds_2d = Bounds {
+        centre: [50_f32, 50_f32, 0_f32],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [15.0, 15.0]),
+        Player2D::new(1, [5.0, 15.0]),
+        Player2D::new(2, [25.0, 25.0]),
+        Player2D::new(3, [35.0, 15.0]),
+    ];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    let cell = grid.get_cell_coordinates((15.0, 15.0, 0.0));
+    assert_eq!(cell, (1, 1, 0));
+
+    let cells = |pattern| {
+        let mut cells: Vec<_> = grid
+            .neighbours_of_cell(cell, pattern)
+            .iter()
+            .map(|c| (c.coordinates, c.data[0].id))
+            .collect();
+        cells.sort();
+        cells
+    };
+
+    assert_eq!(cells(Neighbourhood::VonNeumann), [([0, 1], 1)]);
+    assert_eq!(cells(Neighbourhood::Moore), [([0, 1], 1), ([2, 2], 2)]);
+
+    // Cells on the edge of the grid have fewer neighbours
+    let corner = grid.neighbours_of_cell((0, 0, 0), Neighbourhood::Moore);
+    assert_eq!(corner.len(), 2);
+
+    // Floors above and below are mapped through their own resolution
+    let bounds_3d = Bounds {
+        centre: [50_f32, 50_f32, 10_f32],
+        size: [100_f32, 100_f32, 20_f32],
+    };
+
+    let players = [
+        Player3D::new(0, [15.0, 15.0, 15.0]),
+        Player3D::new(1, [15.0, 15.0, 5.0]),
+        Player3D::new(2, [25.0, 15.0, 15.0]),
+        Player3D::new(3, [35.0, 15.0, 5.0]),
+    ];
+
+    let mut grid =
+        HashGrid::<f32, Player3D>::new([10, 10], 2, &bounds_3d, false).with_floor_cells(0, [5, 5]);
+    grid.update(&players);
+
+    assert_eq!(grid.get_cell_coordinates((15.0, 15.0, 15.0)), (1, 1, 1));
+    assert_eq!(grid.get_cell_coordinates((15.0, 15.0, 5.0)), (0, 0, 0));
+
+    let cells = |cell| {
+        let mut cells: Vec<_> = grid
+            .neighbours_of_cell(cell, Neighbourhood::VonNeumann)
+            .iter()
+            .map(|c| (c.floor, c.coordinates, c.data[0].id))
+            .collect();
+        cells.sort();
+        cells
+    };
+
+    assert_eq!(cells((1, 1, 1)), [(0, [0, 0], 1), (1, [2, 1], 2)]);
+    assert_eq!(cells((0, 0, 0)), [(0, [1, 0], 3), (1, [1, 1], 0)]);
+
+    let moore = grid.neighbours_of_cell((0, 0, 0), Neighbourhood::Moore);
+    assert_eq!(moore.len(), 3);
+}
+
+#[test]
+fn spanning_entities() {
+    #[derive(Debug, PartialEq)]
+    struct Lift {
+        id: u32,
+        position: [f32; 3],
+    }
+
+    impl Entity for Lift {
+        type ID = u32;
+        fn id(&self) -> Self::ID {
+            self.id
+        }
+    }
+
+    impl Coordinate for Lift {
+        type Item = f32;
+        fn x(&self) -> Self::Item {
+            self.position[0]
+        }
+
+        fn y(&self) -> Self::Item {
+            self.position[1]
+        }
+
+        fn z(&self) -> Self::Item {
+            self.position[2]
+        }
+    }
+
+    let bounds_3d = Bounds {
+        centre: [0_f32, 0_f32, 15_f32],
+        size: [100_f32, 100_f32, 30_f32],
+    };
+
+    let lifts = [
+        Lift {
+            id: 0,
+            position: [5.0, 5.0, 5.0],
+        },
+        Lift {
+            id: 1,
+            position: [25.0, 5.0, 5.0],
+        },
+    ];
+
+    let mut grid = HashGrid::<f32, Lift>::new([10, 10], 3, &bounds_3d, false);
+    grid.insert_spanning(&lifts[0], 0.0, 25.0);
+    grid.insert(&lifts[1]);
+    assert_eq!(
+        (0..grid.floors())
+            .map(|floor| grid.occupied_cells(floor))
+            .collect::<Vec<_>>(),
+        [2, 1, 1]
+    );
+
+    // The top floor sees the lift reaching into it, but not the single floor entity
+    let top = Geometry::rect((15.0, 5.0, 25.0), (30.0, 10.0, 10.0));
+    assert_eq!(grid.query_geometry(&top), vec![&lifts[0]]);
+
+    // Queries visiting every floor return the lift once
+    let tower = Geometry::rect((15.0, 5.0, 15.0), (30.0, 10.0, 30.0));
+    let mut found = grid.query_geometry(&tower);
+    found.sort_by_key(|l| l.id);
+    assert_eq!(found, vec![&lifts[0], &lifts[1]]);
+
+    let res = grid.query(Query::from((5.0, 5.0, 15.0), QueryType::Relevant, 10.0));
+    assert_eq!(res.data(), &[&lifts[0]]);
+    assert_eq!(grid.iter_all().count(), 2);
+
+    // Relocating keeps the span
+    assert!(grid.relocate(0, (45.0, 45.0, 5.0)));
+    let (cx, cy, _) = grid.get_cell_coordinates((45.0, 45.0, 5.0));
+    let key = grid.key(cx, cy).key();
+    assert!((0..grid.floors()).all(|floor| grid.cell_entities(floor, key) == [&lifts[0]]));
+    assert!(grid.query_geometry(&top).is_empty());
+    assert_eq!(
+        grid.query_geometry(&Geometry::radius((45.0, 45.0, 25.0), 3.0)),
+        vec![&lifts[0]]
+    );
+
+    // Removing takes the lift out of every floor
+    assert!(grid.remove(0));
+    assert_eq!(
+        (0..grid.floors())
+            .map(|floor| grid.occupied_cells(floor))
+            .collect::<Vec<_>>(),
+        [1, 0, 0]
+    );
+}
+
+#[test]
+fn spanning_entity_queries() {
+    use crate::hashgrid::ExtentEntity;
+
+    #[derive(Debug, PartialEq)]
+    struct Lift {
+        id: u32,
+        position: [f32; 3],
+    }
+
+    impl Entity for Lift {
+        type ID = u32;
+        fn id(&self) -> Self::ID {
+            self.id
+        }
+    }
+
+    impl Coordinate for Lift {
+        type Item = f32;
+        fn x(&self) -> Self::Item {
+            self.position[0]
+        }
+
+        fn y(&self) -> Self::Item {
+            self.position[1]
+        }
+
+        fn z(&self) -> Self::Item {
+            self.position[2]
+        }
+    }
+
+    impl ExtentEntity for Lift {
+        fn extent(&self) -> Geometry<f32> {
+            Geometry::rect((self.x(), self.y(), self.z()), (2.0, 2.0, 2.0))
+        }
+    }
+
+    let bounds_3d = Bounds {
+        centre: [0_f32, 0_f32, 15_f32],
+        size: [100_f32, 100_f32, 30_f32],
+    };
+
+    let lift = Lift {
+        id: 0,
+        position: [5.0, 5.0, 5.0],
+    };
+
+    let mut grid = HashGrid::<f32, Lift>::new([10, 10], 3, &bounds_3d, false);
+    grid.insert_spanning(&lift, 0.0, 25.0);
+
+    let tower = Geometry::rect((5.0, 5.0, 15.0), (10.0, 10.0, 30.0));
+    let top = Geometry::rect((5.0, 5.0, 25.0), (10.0, 10.0, 10.0));
+    let far = Geometry::radius((40.0, 40.0, 15.0), 1.0);
+
+    // Queries visiting every floor return the lift once
+    assert_eq!(grid.query_difference(&tower, &far), vec![&lift]);
+    assert_eq!(grid.query_budgeted(&tower, 100), (vec![&lift], true));
+    assert_eq!(grid.query_extents(&tower, 1.0), vec![&lift]);
+    assert_eq!(
+        grid.query_along_path(&[(0.0, 5.0), (10.0, 5.0)], 2.0),
+        vec![&lift]
+    );
+    assert_eq!(grid.region_stats(&tower).unwrap().count, 1);
+    assert_eq!(grid.clusters(1.0), vec![vec![0]]);
+
+    // Queries of the top floor only find the lift reaching into it
+    assert_eq!(grid.query_difference(&top, &far), vec![&lift]);
+    assert_eq!(grid.query_budgeted(&top, 100), (vec![&lift], true));
+    assert_eq!(grid.query_extents(&top, 1.0), vec![&lift]);
+    assert_eq!(grid.region_stats(&top).unwrap().count, 1);
+
+    // Excluding the top floor leaves the lift out of the difference
+    assert!(grid.query_difference(&tower, &top).is_empty());
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn geometry_sampling() {
+    use rand::rngs::mock::StepRng;
+
+    // Weyl sequence spreading the samples evenly
+    let mut rng = StepRng::new(0, 0x9E37_79B9_7F4A_7C15);
+
+    let rect = Geometry::rect((10.0_f32, -5.0, 0.0), (4.0, 2.0, 0.0));
+    let sphere = Geometry::radius((1.0_f32, 2.0, 3.0), 5.0);
+    let cone = Geometry::cone((1.0_f32, 2.0, 3.0), (1.0, 1.0, 1.0), 0.5, 5.0);
+
+    // Samples may land on the edge of the cone, within rounding errors
+    let widened = Geometry::cone((1.0_f32, 2.0, 3.0), (1.0, 1.0, 1.0), 0.501, 5.001);
+
+    for _ in 0..100 {
+        let p = rect.sample_point(&mut rng).unwrap();
+        assert!(rect.contains(p));
+        assert_eq!(p.2, 0.0);
+
+        let p = sphere.sample_point(&mut rng).unwrap();
+        assert!(sphere.contains(p));
+
+        let p = sphere.sample_point_planar(&mut rng).unwrap();
+        assert!(sphere.contains(p));
+        assert_eq!(p.2, 3.0);
+
+        assert!(widened.contains(cone.sample_point(&mut rng).unwrap()));
+    }
+
+    let flat = Geometry::cone((1.0_f32, 2.0, 0.0), (0.0, -3.0, 0.0), 0.5, 5.0);
+    let flat_widened = Geometry::cone((1.0_f32, 2.0, 0.0), (0.0, -3.0, 0.0), 0.501, 5.001);
+    for _ in 0..100 {
+        let p = flat.sample_point_planar(&mut rng).unwrap();
+        assert!(flat_widened.contains(p));
+        assert_eq!(p.2, 0.0);
+    }
+
+    let point = Geometry::point((1.0_f32, 1.0, 1.0));
+    assert_eq!(point.sample_point(&mut rng), Some((1.0, 1.0, 1.0)));
+    assert_eq!(Geometry::hslab(0.0_f32, 1.0).sample_point(&mut rng), None);
+    assert_eq!(
+        Geometry::Radius {
+            centre: (0.0_f32, 0.0, 0.0),
+            radius: -1.0
+        }
+        .sample_point(&mut rng),
+        None
+    );
+}
+
+#[test]
+fn query_around_entity() {
+    use crate::error::SpatialError;
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [5.0, 5.0]),
+        Player2D::new(1, [8.0, 9.0]),
+        Player2D::new(2, [-5.0, 5.0]),
+        Player2D::new(3, [30.0, 30.0]),
+    ];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    let mut around = grid.query_around(0, 10.0);
+    around.sort_by_key(|p| p.id);
+    assert_eq!(around, vec![&players[1], &players[2]]);
+
+    assert!(grid.query_around(3, 10.0).is_empty());
+    assert!(grid.query_around(7, 10.0).is_empty());
+    assert_eq!(
+        grid.try_query_around(7, 10.0),
+        Err(SpatialError::UnknownEntity(7))
+    );
+}
+
+#[test]
+fn density_retuning() {
+    let bounds_2d = Bounds {
+        centre: [50_f32, 50_f32, 0_f32],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    // Two players in every cell of a 10 x 10 grid
+    let players: Vec<_> = (0..200)
+        .map(|i| {
+            let (cx, cy) = ((i / 2) % 10, (i / 2) / 10);
+            let offset = (i % 2) as f32 * 2.0;
+            Player2D::new(i, [5.0 + cx as f32 * 10.0 + offset, 5.0 + cy as f32 * 10.0])
+        })
+        .collect();
+
+    let mut grid = HashGrid::<f32, Player2D>::new([2, 2], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    // Fifty players per cell down to two
+    assert_eq!(grid.retune(2.0), 1);
+    assert_eq!(grid.floor_cells(0), [10, 10]);
+    assert_eq!(grid.occupied(0).map(|(_, d)| d.len()).max(), Some(2));
+    assert_eq!(grid.iter_all().count(), players.len());
+
+    // Already on target
+    assert_eq!(grid.retune(2.0), 0);
+
+    let rect = Geometry::rect((6.0, 5.0, 0.0), (4.0, 2.0, 0.0));
+    assert_eq!(grid.query_geometry(&rect).len(), 2);
+
+    // Tiny targets are capped so that the hash indices of the floor still fit
+    let clustered: Vec<_> = (0..200)
+        .map(|i| Player2D::new(i, [50.0 + (i % 10) as f32 * 0.01, 50.0]))
+        .collect();
+
+    let mut grid = HashGrid::<f32, Player2D>::new([1000, 1000], 0, &bounds_2d, false);
+    grid.update(&clustered);
+    assert_eq!(grid.retune(1e-6), 1);
+    assert_eq!(grid.floor_cells(0), [32767, 32767]);
+    assert_eq!(grid.iter_all().count(), clustered.len());
+
+    let mut grid = HashGrid::<f32, Player2D, u16>::new([10, 10], 0, &bounds_2d, false)
+        .with_storage(crate::hashgrid::CellStorage::Dense);
+    grid.update(&clustered);
+    assert_eq!(grid.retune(1e-6), 1);
+    assert_eq!(grid.floor_cells(0), [180, 180]);
+    assert_eq!(
+        grid.query_geometry(&Geometry::radius((50.0, 50.0, 0.0), 1.0))
+            .len(),
+        clustered.len()
+    );
+}
+
+#[test]
+fn geometry_penetration() {
+    let rect = Geometry::rect((0.0_f32, 0.0, 0.0), (4.0, 4.0, 0.0));
+
+    // Overlapping by 1 along x and 3 along y, pushed out along x
+    let other = Geometry::rect((2.0_f32, 1.0, 0.0), (2.0, 4.0, 0.0));
+    assert_eq!(other.penetration(&rect), Some(((1.0, 0.0, 0.0), 1.0)));
+    assert_eq!(rect.penetration(&other), Some(((-1.0, 0.0, 0.0), 1.0)));
+
+    let far = Geometry::rect((10.0_f32, 0.0, 0.0), (2.0, 2.0, 0.0));
+    assert_eq!(far.penetration(&rect), None);
+
+    // Circles overlapping the rect from outside and from inside
+    let circle = Geometry::radius((0.0_f32, 3.0, 0.0), 2.0);
+    assert_eq!(circle.penetration(&rect), Some(((0.0, 1.0, 0.0), 1.0)));
+    assert_eq!(rect.penetration(&circle), Some(((0.0, -1.0, 0.0), 1.0)));
+
+    let inside = Geometry::radius((1.5_f32, 0.0, 0.0), 1.0);
+    assert_eq!(inside.penetration(&rect), Some(((1.0, 0.0, 0.0), 1.5)));
+
+    let a = Geometry::radius((0.0_f32, 0.0, 0.0), 2.0);
+    let b = Geometry::radius((0.0_f32, 3.0, 0.0), 2.0);
+    assert_eq!(b.penetration(&a), Some(((0.0, 1.0, 0.0), 1.0)));
+    assert_eq!(
+        Geometry::radius((0.0_f32, 5.0, 0.0), 1.0).penetration(&a),
+        None
+    );
+
+    assert_eq!(
+        Geometry::point((0.0_f32, 0.0, 0.0)).penetration(&rect),
+        None
+    );
+}
+
+#[test]
+fn paginated_queries() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players: Vec<_> = (0..7)
+        .map(|i| Player2D::new(i * 3 % 7, [i as f32 * 5.0 - 15.0, 0.0]))
+        .chain([Player2D::new(9, [45.0, 45.0])])
+        .collect();
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    let area = Geometry::rect((0.0, 0.0, 0.0), (40.0, 10.0, 0.0));
+
+    let mut cursor = None;
+    let mut pages = Vec::new();
+    loop {
+        let page = grid.query_page(&area, cursor, 3);
+        pages.push(page.items.iter().map(|p| p.id).collect::<Vec<_>>());
+
+        match page.next {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+
+    assert_eq!(pages, [vec![0, 1, 2], vec![3, 4, 5], vec![6]]);
+
+    // An exact multiple of the page size ends on a full page
+    let page = grid.query_page(&area, Some(0), 6);
+    assert_eq!(page.items.len(), 6);
+    assert_eq!(page.next, None);
+}
+
+#[test]
+fn line_of_sight() {
+    use crate::hashgrid::ExtentEntity;
+
+    #[derive(Debug, PartialEq)]
+    struct Prop {
+        id: u32,
+        centre: [f32; 2],
+        size: [f32; 2],
+        solid: bool,
+    }
+
+    impl Entity for Prop {
+        type ID = u32;
+        fn id(&self) -> Self::ID {
+            self.id
+        }
+    }
+
+    impl Coordinate for Prop {
+        type Item = f32;
+        fn x(&self) -> Self::Item {
+            self.centre[0]
+        }
+
+        fn y(&self) -> Self::Item {
+            self.centre[1]
+        }
+    }
+
+    impl ExtentEntity for Prop {
+        fn extent(&self) -> Geometry<f32> {
+            Geometry::rect((self.x(), self.y(), 0.0), (self.size[0], self.size[1], 0.0))
+        }
+    }
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let props = [
+        // A long wall filed in the cell of its centre, reaching far across x
+        Prop {
+            id: 0,
+            centre: [0.0, 20.0],
+            size: [30.0, 2.0],
+            solid: true,
+        },
+        // A bush which does not block the sight
+        Prop {
+            id: 1,
+            centre: [30.0, 0.0],
+            size: [4.0, 4.0],
+            solid: false,
+        },
+    ];
+
+    let mut grid = HashGrid::<f32, Prop>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&props);
+
+    let solid = |p: &Prop| p.solid;
+
+    assert!(!grid.visible((12.0, 10.0, 0.0), (12.0, 30.0, 0.0), 15.0, solid));
+    assert!(grid.visible((20.0, 10.0, 0.0), (20.0, 30.0, 0.0), 15.0, solid));
+    assert!(grid.visible((20.0, 0.0, 0.0), (40.0, 0.0, 0.0), 15.0, solid));
+    assert!(!grid.visible((20.0, 0.0, 0.0), (40.0, 0.0, 0.0), 15.0, |_| true));
+
+    // The segment tests of the shapes
+    let wall = props[0].extent();
+    assert!(wall.intersects_segment((-40.0, 0.0, 0.0), (40.0, 40.0, 0.0)));
+    assert!(!wall.intersects_segment((-40.0, 0.0, 0.0), (-20.0, 40.0, 0.0)));
+    let pillar = Geometry::radius((0.0_f32, 0.0, 0.0), 1.0);
+    assert!(pillar.intersects_segment((-5.0, 1.0, 0.0), (5.0, 1.0, 0.0)));
+    assert!(!pillar.intersects_segment((-5.0, 1.5, 0.0), (5.0, 1.5, 0.0)));
+}
+
+#[test]
+fn pathfinding() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    // A wall along the x = 15 column of cells, open above y = 25
+    let wall = (0..8)
+        .map(|i| Player2D::new(i, [15.0, -45.0 + 10.0 * i as f32]))
+        .collect::<Vec<_>>();
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&wall);
+
+    let occupied = |_: [i64; 2], entities: &[&Player2D]| !entities.is_empty();
+
+    let path = grid
+        .pathfind((-25.0, -25.0, 0.0), (35.0, -25.0, 0.0), occupied)
+        .unwrap();
+
+    assert_eq!(path.first(), Some(&[-3, -3]));
+    assert_eq!(path.last(), Some(&[3, -3]));
+
+    // Every step moves to a neighbouring cell, around the wall
+    assert!(path
+        .windows(2)
+        .all(|w| (w[0][0] - w[1][0]).abs() <= 1 && (w[0][1] - w[1][1]).abs() <= 1));
+    assert!(path.iter().all(|c| c[0] != 1 || c[1] >= 3));
+    assert!(path.iter().any(|c| c[1] >= 3));
+
+    // Mirrored cells on the other side of the origin are not blocked by the wall
+    let mirrored = grid
+        .pathfind((-25.0, -25.0, 0.0), (-5.0, -25.0, 0.0), occupied)
+        .unwrap();
+    assert_eq!(mirrored, vec![[-3, -3], [-2, -3], [-1, -3]]);
+
+    // Waypoints only keep the turns around the wall
+    let waypoints = grid.pull_string(&path, 0, occupied);
+    assert!(waypoints.len() >= 3 && waypoints.len() < path.len());
+    assert_eq!(waypoints.first(), Some(&(-25.0, -25.0)));
+    assert_eq!(waypoints.last(), Some(&(35.0, -25.0)));
+
+    // Unreachable goals, inside the wall or outside the bounds
+    assert!(grid
+        .pathfind((-25.0, -25.0, 0.0), (15.0, 0.0, 0.0), occupied)
+        .is_none());
+
+    let everything = |c: [i64; 2], _: &[&Player2D]| c[0] == 1;
+    assert!(grid
+        .pathfind((-25.0, -25.0, 0.0), (35.0, -25.0, 0.0), everything)
+        .is_none());
+}
+
+#[test]
+fn flow_fields() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    // A wall along the x = 15 column of cells, open above y = 25
+    let wall = (0..8)
+        .map(|i| Player2D::new(i, [15.0, -45.0 + 10.0 * i as f32]))
+        .collect::<Vec<_>>();
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&wall);
+
+    let field = grid.flow_field((35.0, -25.0, 0.0), |_, entities| {
+        entities.is_empty().then_some(1.0)
+    });
+
+    assert_eq!(field.goal(), [3, -3]);
+    assert_eq!(field.cost([3, -3]), Some(0.0));
+    assert_eq!(field.direction([3, -3]), None);
+    assert_eq!(field.direction([2, -3]), Some((1.0, 0.0)));
+
+    // Cells of the wall cannot be crossed, nor left
+    assert_eq!(field.cost([1, 0]), None);
+    assert_eq!(field.direction_at((15.0, 0.0)), None);
+
+    // Mirrored cells on the other side of the origin are not part of the wall
+    assert!(field.cost([-1, 0]).is_some());
+    assert_eq!(field.cell_of((150.0, 0.0)), None);
+
+    // Following the field from anywhere leads to the goal around the wall
+    let mut cell = field.cell_of((-25.0, -25.0)).unwrap();
+    let mut steps = 0;
+
+    while let Some((dx, dy)) = field.direction(cell) {
+        cell = [cell[0] + dx.round() as i64, cell[1] + dy.round() as i64];
+        assert!(cell[0] != 1 || cell[1] >= 3);
+        steps += 1;
+    }
+
+    assert_eq!(cell, field.goal());
+    assert!((12..20).contains(&steps));
+}
+
+#[test]
+fn cone_queries() {
+    use core::f32::consts::FRAC_PI_4;
+
+    let cone = Geometry::cone((0.0_f32, 0.0, 0.0), (2.0, 0.0, 0.0), FRAC_PI_4, 20.0);
+    assert!(cone.is_valid());
+    assert!(Geometry::try_cone((0.0_f32, 0.0, 0.0), (0.0, 0.0, 0.0), FRAC_PI_4, 20.0).is_err());
+
+    // Bounded by the full range ahead, and by the rim sideways
+    let (min, max) = cone.bounding_box();
+    assert_eq!((min.0, max.0), (0.0, 20.0));
+    assert!((max.1 - 20.0 * FRAC_PI_4.sin()).abs() < 1e-4);
+    assert_eq!(min.1, -max.1);
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [10.0, 0.0]),
+        Player2D::new(1, [10.0, 9.0]),
+        // Outside the angle, beyond the range, and behind the apex
+        Player2D::new(2, [10.0, 11.0]),
+        Player2D::new(3, [25.0, 0.0]),
+        Player2D::new(4, [-5.0, 0.0]),
+    ];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    let mut seen = grid
+        .query_geometry(&cone)
+        .into_iter()
+        .map(|p| p.id)
+        .collect::<Vec<_>>();
+    seen.sort();
+    assert_eq!(seen, vec![0, 1]);
+
+    // Circles touching the side of the cone, or only its bounding box
+    assert!(cone.intersects(&Geometry::radius((10.0, 11.0, 0.0), 1.5)));
+    assert!(!cone.intersects(&Geometry::radius((2.0, 8.0, 0.0), 1.0)));
+    assert!(Geometry::radius((22.0, 0.0, 0.0), 2.5).intersects(&cone));
+
+    // Rects crossed by the cone without any of their corners inside it
+    assert!(cone.intersects(&Geometry::rect((10.0, 0.0, 0.0), (1.0, 40.0, 0.0))));
+    assert!(cone.intersects(&Geometry::rect((0.0, 0.0, 0.0), (1.0, 1.0, 0.0))));
+    assert!(!cone.intersects(&Geometry::rect((2.0, 8.0, 0.0), (2.0, 2.0, 0.0))));
+    assert!(cone.intersects(&Geometry::vslab(5.0, 6.0)));
+    assert!(!cone.intersects(&Geometry::vslab(-6.0, -5.0)));
+
+    assert!(cone.intersects_segment((5.0, -10.0, 0.0), (5.0, 10.0, 0.0)));
+    assert!(!cone.intersects_segment((-5.0, -10.0, 0.0), (-5.0, 10.0, 0.0)));
+    assert!(!cone.intersects_segment((2.0, 8.0, 0.0), (4.0, 10.0, 0.0)));
+}
+
+#[test]
+fn distance_matrices() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [0.0, 0.0]),
+        Player2D::new(1, [3.0, 4.0]),
+        Player2D::new(2, [-12.0, 0.0]),
+        Player2D::new(3, [40.0, 40.0]),
+    ];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    // Two points sharing a cell, one on the mirrored side of the origin, one far away
+    let points = [
+        (1.0, 0.0, 0.0),
+        (2.0, 1.0, 0.0),
+        (-9.0, 0.0, 0.0),
+        (-40.0, -40.0, 0.0),
+    ];
+    let matrix = grid.distance_matrix(&points, 6.0);
+
+    assert_eq!(matrix.len(), 4);
+    assert_eq!(matrix.offsets.len(), 5);
+    assert_eq!(matrix.row(0).0, &[0, 1]);
+    assert_eq!(matrix.row(0).1[0], 1.0);
+    assert_eq!(matrix.row(1).0, &[0, 1]);
+    assert_eq!(matrix.row(2).0, &[2]);
+    assert_eq!(matrix.row(2).1, &[3.0]);
+    assert!(matrix.row(3).0.is_empty());
+
+    // Rows match the individual radius queries
+    for (i, &point) in points.iter().enumerate() {
+        let mut expected = grid
+            .query_geometry(&Geometry::radius(point, 6.0))
+            .into_iter()
+            .map(|p| p.id)
+            .collect::<Vec<_>>();
+        let mut row = matrix.row(i).0.to_vec();
+        expected.sort();
+        row.sort();
+        assert_eq!(row, expected);
+    }
+
+    assert!(grid.distance_matrix(&[], 6.0).is_empty());
+}
+
+#[cfg(all(feature = "tracing", feature = "std"))]
+#[test]
+fn traced_operations() {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// Collects the names of the spans opened, along with the fields recorded on them
+    #[derive(Default)]
+    struct Collector {
+        spans: Arc<Mutex<Vec<(&'static str, usize)>>>,
+    }
+
+    struct Counter<'s>(&'s mut usize);
+
+    impl tracing::field::Visit for Counter<'_> {
+        fn record_u64(&mut self, _: &tracing::field::Field, value: u64) {
+            *self.0 = value as usize;
+        }
+
+        fn record_debug(&mut self, _: &tracing::field::Field, _: &dyn std::fmt::Debug) {}
+    }
+
+    impl Subscriber for Collector {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut spans = self.spans.lock().unwrap();
+            let mut count = 0;
+            span.record(&mut Counter(&mut count));
+            spans.push((span.metadata().name(), count));
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            let mut spans = self.spans.lock().unwrap();
+            values.record(&mut Counter(&mut spans[span.into_u64() as usize - 1].1));
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, _: &Event<'_>) {}
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    let collector = Collector::default();
+    let spans = collector.spans.clone();
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+    let players = [Player2D::new(0, [1.0, 1.0]), Player2D::new(1, [2.0, 2.0])];
+
+    tracing::subscriber::with_default(collector, || {
+        let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+        grid.update(&players);
+        grid.query_geometry(&Geometry::radius((0.0, 0.0, 0.0), 5.0));
+        grid.retune(1.0);
+    });
+
+    let spans = spans.lock().unwrap();
+    assert_eq!(
+        *spans,
+        vec![
+            ("update", 2),
+            ("insert", 0),
+            ("insert", 0),
+            ("query", 2),
+            ("retune", 1),
+            // Refiling the floor keeps the filing of its entities, without inserting them again
+            ("rehash", 2),
+        ]
+    );
+}
+
+#[test]
+fn generation_counter() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+    let players = [Player2D::new(0, [1.0, 1.0]), Player2D::new(1, [20.0, 20.0])];
+    let outside = Player2D::new(2, [500.0, 0.0]);
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    assert_eq!(grid.generation(), 0);
+
+    grid.update(&players);
+    let cached = grid.query(Query::from((0.0, 0.0, 0.0), QueryType::Relevant, 0.0));
+    assert_eq!(cached.generation(), grid.generation());
+
+    // Queries and failed mutations leave the generation untouched
+    grid.query_geometry(&Geometry::radius((0.0, 0.0, 0.0), 50.0));
+    assert!(!grid.remove(7));
+    assert!(grid.try_insert(&outside).is_err());
+    assert_eq!(cached.generation(), grid.generation());
+
+    assert!(grid.relocate(1, (30.0, 30.0, 0.0)));
+    assert!(cached.generation() < grid.generation());
+
+    let generation = grid.generation();
+    assert!(grid.remove(0));
+    assert!(grid.generation() > generation);
+}
+
+#[test]
+fn changed_cells() {
+    let bounds_2d = Bounds {
+        centre: [50_f32, 50.0, 0.0],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+    let players = [
+        Player2D::new(0, [5.0, 5.0]),
+        Player2D::new(1, [25.0, 5.0]),
+        Player2D::new(2, [45.0, 45.0]),
+    ];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    let changed = grid.cells_changed_since(0);
+    assert_eq!(changed.len(), 3);
+    assert_eq!(changed[0].0.coordinates, [0, 0]);
+    assert_eq!(changed[0].0.data.len(), 1);
+
+    // Only the cells left and entered by the entity are dirty
+    let synced = grid.generation();
+    assert!(grid.cells_changed_since(synced).is_empty());
+    assert!(grid.relocate(1, (35.0, 5.0, 0.0)));
+
+    let changed = grid.cells_changed_since(synced);
+    let cells = changed
+        .iter()
+        .map(|(cell, _)| (cell.coordinates, cell.data.len()))
+        .collect::<Vec<_>>();
+    assert_eq!(cells, vec![([2, 0], 0), ([3, 0], 1)]);
+    assert!(changed.iter().all(|&(_, version)| version > synced));
+
+    let synced = grid.generation();
+    assert!(grid.remove(2));
+    let changed = grid.cells_changed_since(synced);
+    assert_eq!(changed.len(), 1);
+    assert_eq!(changed[0].0.coordinates, [4, 4]);
+    assert!(changed[0].0.data.is_empty());
+}
+
+#[test]
+fn entity_groups() {
+    use crate::error::SpatialError;
+    use crate::hashgrid::CellOrder;
+
+    let bounds_2d = Bounds {
+        centre: [50_f32, 50.0, 0.0],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+    let players = [
+        Player2D::new(0, [5.0, 5.0]),
+        Player2D::new(1, [8.0, 5.0]),
+        Player2D::new(2, [5.0, 8.0]),
+        Player2D::new(3, [15.0, 5.0]),
+    ];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    let cells = |grid: &HashGrid<f32, Player2D>| {
+        grid.cells_in_order(CellOrder::RowMajor)
+            .into_iter()
+            .map(|cell| {
+                let mut ids = cell.data.iter().map(|p| p.id).collect::<Vec<_>>();
+                ids.sort();
+                (cell.coordinates, ids)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut convoy = grid.create_group([2, 0, 1, 0]).unwrap();
+    assert_eq!(convoy.len(), 3);
+    assert_eq!(convoy.members()[2], (2, (5.0, 8.0, 0.0)));
+    assert_eq!(
+        grid.create_group([0, 9]),
+        Err(SpatialError::UnknownEntity(9))
+    );
+
+    // Successive moves add up from the positions the members are filed at
+    assert!(grid.relocate_group(&mut convoy, (20.0, 10.0, 0.0)));
+    assert!(grid.relocate_group(&mut convoy, (20.0, 0.0, 0.0)));
+    assert_eq!(convoy.members()[0], (0, (45.0, 15.0, 0.0)));
+    assert_eq!(
+        cells(&grid),
+        vec![([1, 0], vec![3]), ([4, 1], vec![0, 1, 2])]
+    );
+
+    // Moves leaving the bounds for a single member move nobody
+    let generation = grid.generation();
+    assert!(grid
+        .try_relocate_group(&mut convoy, (0.0, 85.0, 0.0))
+        .is_err());
+    assert_eq!(grid.generation(), generation);
+    assert_eq!(convoy.members()[0], (0, (45.0, 15.0, 0.0)));
+
+    assert!(grid.remove(1));
+    assert_eq!(
+        grid.try_relocate_group(&mut convoy, (1.0, 0.0, 0.0)),
+        Err(SpatialError::UnknownEntity(1))
+    );
+    assert_eq!(cells(&grid), vec![([1, 0], vec![3]), ([4, 1], vec![0, 2])]);
+
+    // Moved members are found at their new positions
+    let found = |grid: &HashGrid<f32, Player2D>, centre: (f32, f32)| {
+        let mut ids = grid
+            .query_geometry(&Geometry::radius((centre.0, centre.1, 0.0), 5.0))
+            .into_iter()
+            .map(|p| p.id)
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    };
+    assert_eq!(found(&grid, (45.0, 15.0)), vec![0, 2]);
+    assert!(found(&grid, (5.0, 5.0)).is_empty());
+}
+
+#[test]
+fn spanning_entity_groups() {
+    let bounds_3d = Bounds {
+        centre: [0_f32, 0_f32, 15_f32],
+        size: [100_f32, 100_f32, 30_f32],
+    };
+    let players = [
+        Player3D::new(0, [5.0, 5.0, 5.0]),
+        Player3D::new(1, [8.0, 5.0, 25.0]),
+    ];
+
+    let mut grid = HashGrid::<f32, Player3D>::new([10, 10], 3, &bounds_3d, false);
+    grid.insert_spanning(&players[0], 0.0, 25.0);
+    grid.insert(&players[1]);
+
+    let mut group = grid.create_group([0, 1]).unwrap();
+    let ids = |grid: &HashGrid<f32, Player3D>, centre: (f32, f32, f32)| {
+        grid.query_geometry(&Geometry::radius(centre, 2.0))
+            .into_iter()
+            .map(|p| p.id)
+            .collect::<Vec<_>>()
+    };
+
+    // The second member would leave the top floor, so the lift is not taken out either
+    let generation = grid.generation();
+    assert!(!grid.relocate_group(&mut group, (10.0, 0.0, 10.0)));
+    assert_eq!(grid.generation(), generation);
+    assert_eq!(ids(&grid, (5.0, 5.0, 25.0)), vec![0]);
+    assert_eq!(ids(&grid, (8.0, 5.0, 25.0)), vec![1]);
+
+    // The lift keeps its span, only the other member moving down
+    assert!(grid.relocate_group(&mut group, (10.0, 0.0, -10.0)));
+    assert_eq!(group.members()[0], (0, (15.0, 5.0, 5.0)));
+    assert_eq!(ids(&grid, (15.0, 5.0, 25.0)), vec![0]);
+    assert_eq!(ids(&grid, (18.0, 5.0, 15.0)), vec![1]);
+    assert!(ids(&grid, (5.0, 5.0, 5.0)).is_empty());
+}
+
+#[test]
+fn dense_storage() {
+    use crate::hashgrid::CellStorage;
+
+    let bounds_2d = Bounds {
+        centre: [50_f32, 50.0, 0.0],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+    let players = (0..100)
+        .map(|i| {
+            Player2D::new(
+                i,
+                [(i % 10) as f32 * 10.0 + 5.0, (i / 10) as f32 * 10.0 + 5.0],
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut sparse = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    sparse.update(&players);
+
+    // Switching the storage keeps the entities already filed
+    let mut dense = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    dense.update(&players[..50]);
+    let mut dense = dense.with_storage(CellStorage::Dense);
+    dense.update(&players[50..]);
+
+    assert_eq!(sparse.storage(), CellStorage::Sparse);
+    assert_eq!(dense.storage(), CellStorage::Dense);
+    assert_eq!(dense.occupied_cells(0), sparse.occupied_cells(0));
+
+    let ids = |grid: &HashGrid<f32, Player2D>| {
+        let mut ids = grid
+            .query_geometry(&Geometry::radius((50.0, 50.0, 0.0), 22.0))
+            .into_iter()
+            .map(|p| p.id)
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids
+    };
+    assert_eq!(ids(&dense), ids(&sparse));
+    assert_eq!(dense.nearest((71.0, 12.0, 0.0)).unwrap().0.id, 17);
+
+    // Emptied cells are dropped from the occupied ones
+    assert!(dense.remove(0));
+    assert!(dense.relocate(1, (5.0, 5.0, 0.0)));
+    assert_eq!(dense.occupied_cells(0), 99);
+    assert!(dense.cell_entities(0, dense.key(1, 0).key()).is_empty());
+    assert_eq!(dense.cell_entities(0, dense.key(0, 0).key()).len(), 1);
+}
+
+#[test]
+fn free_positions() {
+    let bounds_2d = Bounds {
+        centre: [50_f32, 50.0, 0.0],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+    let players = [Player2D::new(0, [15.0, 15.0])];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    let region = Geometry::rect_from_corners((0.0, 0.0, 0.0), (40.0, 40.0, 0.0));
+    let positions = grid.find_free_positions(&region, 100, 10.0);
+
+    // Every stratum but the one holding the entity gives a position
+    assert_eq!(positions.len(), 15);
+    assert!(!positions.contains(&(15.0, 15.0, 0.0)));
+    assert!(positions.iter().all(|&p| region.contains(p)));
+
+    assert_eq!(
+        grid.find_free_positions(&region, 3, 10.0),
+        vec![(5.0, 5.0, 0.0), (15.0, 5.0, 0.0), (25.0, 5.0, 0.0)]
+    );
+
+    // Regions beyond the bounds are only covered within them
+    let outside = Geometry::rect_from_corners((90.0, 90.0, 0.0), (130.0, 130.0, 0.0));
+    assert_eq!(
+        grid.find_free_positions(&outside, 10, 10.0),
+        vec![(95.0, 95.0, 0.0)]
+    );
+    assert!(grid.find_free_positions(&region, 10, 0.0).is_empty());
+
+    #[cfg(feature = "rand")]
+    {
+        use crate::math::distance;
+        use rand::rngs::mock::StepRng;
+
+        let mut rng = StepRng::new(0, 0x9E37_79B9_7F4A_7C15);
+        let positions = grid.find_free_positions_jittered(&region, 10, 10.0, &mut rng);
+
+        assert!(!positions.is_empty());
+        for (i, &p) in positions.iter().enumerate() {
+            assert!(region.contains(p));
+            assert!(distance(p, (15.0, 15.0, 0.0)) >= 10.0);
+            assert!(positions[..i].iter().all(|&q| distance(p, q) >= 10.0));
+        }
+    }
+}
+
+#[test]
+fn floor_ranges() {
+    #[derive(Debug, PartialEq)]
+    struct Listener {
+        id: u32,
+        position: [f32; 3],
+    }
+
+    impl Entity for Listener {
+        type ID = u32;
+        fn id(&self) -> Self::ID {
+            self.id
+        }
+    }
+
+    impl Coordinate for Listener {
+        type Item = f32;
+        fn x(&self) -> Self::Item {
+            self.position[0]
+        }
+
+        fn y(&self) -> Self::Item {
+            self.position[1]
+        }
+
+        fn z(&self) -> Self::Item {
+            self.position[2]
+        }
+    }
+
+    let bounds_3d = Bounds {
+        centre: [0_f32, 0_f32, 15_f32],
+        size: [100_f32, 100_f32, 30_f32],
+    };
+
+    let listeners = [
+        Listener {
+            id: 0,
+            position: [5.0, 5.0, 5.0],
+        },
+        Listener {
+            id: 1,
+            position: [5.0, 5.0, 15.0],
+        },
+        Listener {
+            id: 2,
+            position: [5.0, 5.0, 25.0],
+        },
+    ];
+
+    let mut grid = HashGrid::<f32, Listener>::new([10, 10], 3, &bounds_3d, false);
+    grid.update(&listeners);
+
+    let ids = |query: Query<f32, u32>, floors| {
+        let mut ids = grid
+            .query_on_floors(query, floors)
+            .data()
+            .iter()
+            .map(|l| l.id)
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids
+    };
+
+    // The radius reaches the floors above and below
+    let query = Query::from((5.0, 5.0, 15.0), QueryType::Relevant, 10.0);
+    assert_eq!(grid.query(query).data().len(), 3);
+    assert_eq!(ids(query, 0..=9), vec![0, 1, 2]);
+
+    // Sounds stay on the level they are made on
+    assert_eq!(ids(query, 1..=1), vec![1]);
+
+    assert_eq!(ids(query, 0..=1), vec![0, 1]);
+    assert!(ids(query, 5..=9).is_empty());
+}
+
+#[test]
+fn ranked_queries() {
+    let bounds_2d = Bounds {
+        centre: [0_f32, 0.0, 0.0],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+    let players = (0..20)
+        .map(|i| Player2D::new(i, [(i as f32 - 10.0) * 4.0, 2.0]))
+        .collect::<Vec<_>>();
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    let range = Geometry::radius((0.0, 0.0, 0.0), 20.0);
+    let ranked = grid.query_ranked(&range, |p| p.id as f64, 3);
+    let ids = ranked.iter().map(|(p, _)| p.id).collect::<Vec<_>>();
+    assert_eq!(ids, vec![14, 13, 12]);
+    assert_eq!(ranked[0].1, 14.0);
+
+    // Every hit is ranked when there are fewer than requested
+    let closest = |p: &Player2D| -(p.position[0].abs() as f64);
+    let ranked = grid.query_ranked(&range, closest, 100);
+    assert_eq!(ranked.len(), grid.query_geometry(&range).len());
+    assert_eq!(ranked[0].0.id, 10);
+    assert!(ranked.windows(2).all(|w| w[0].1 >= w[1].1));
+
+    // Unscored entities are left out
+    let left = |p: &Player2D| if p.id < 10 { 1.0 } else { f64::NAN };
+    assert!(grid
+        .query_ranked(&range, left, 100)
+        .iter()
+        .all(|(p, _)| p.id < 10));
+    assert!(grid.query_ranked(&range, closest, 0).is_empty());
+}
+
+#[test]
+fn cell_space() {
+    use crate::hashgrid::CellId;
+
+    let bounds_2d = Bounds {
+        centre: [0_f32, 0.0, 0.0],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+    let players = [Player2D::new(0, [-15.0, 25.0])];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    // Cells on both sides of the origin cover their own area
+    let bounds = grid.cell_bounds(-2, 2, 0);
+    assert_eq!(bounds.bounding_box().0, (-20.0, 20.0, 0.0));
+    assert_eq!(bounds.bounding_box().1, (-10.0, 30.0, 1.0));
+    assert!(bounds.contains((-15.0, 25.0, 0.0)));
+    assert!(!grid.cell_bounds(1, 2, 0).contains((-15.0, 25.0, 0.0)));
+
+    let area = Geometry::rect_from_corners((-15.0, 5.0, 0.0), (5.0, 25.0, 0.0));
+    let cells = grid.cells_overlapping(&area).collect::<Vec<_>>();
+    assert_eq!(cells.len(), 9);
+    assert_eq!(
+        cells[0],
+        CellId {
+            floor: 0,
+            x: -2,
+            y: 0
+        }
+    );
+    assert_eq!(
+        cells[8],
+        CellId {
+            floor: 0,
+            x: 0,
+            y: 2
+        }
+    );
+
+    // The cell of the entity holds it under its hash coordinates
+    let cell = cells[6];
+    assert_eq!(cell.hash_coordinates(), [2, 2]);
+    let [cx, cy] = cell.hash_coordinates();
+    assert_eq!(grid.cell_entities(0, grid.key(cx, cy).key()), [&players[0]]);
+
+    // Areas beyond the bounds are clamped into them
+    let outside = Geometry::rect_from_corners((45.0, 45.0, 0.0), (80.0, 80.0, 0.0));
+    assert_eq!(
+        grid.cells_overlapping(&outside).collect::<Vec<_>>(),
+        vec![
+            CellId {
+                floor: 0,
+                x: 4,
+                y: 4
+            },
+            CellId {
+                floor: 0,
+                x: 5,
+                y: 4
+            },
+            CellId {
+                floor: 0,
+                x: 4,
+                y: 5
+            },
+            CellId {
+                floor: 0,
+                x: 5,
+                y: 5
+            }
+        ]
+    );
+}
+
+#[test]
+fn blocked_cells() {
+    use crate::hashgrid::CellId;
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+    let players = [Player2D::new(0, [-25.0, -25.0])];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    // A river along the x = 15 column of cells, open above y = 25
+    let river = (-5..3).map(|y| CellId { floor: 0, x: 1, y });
+    grid.mark_blocked(river);
+
+    assert!(grid.is_blocked((15.0, 0.0, 0.0)));
+    assert!(grid.is_blocked((19.0, -45.0, 0.0)));
+    assert!(!grid.is_blocked((-15.0, 0.0, 0.0)));
+    assert!(!grid.is_blocked((15.0, 35.0, 0.0)));
+    assert!(grid.is_cell_blocked(CellId {
+        floor: 0,
+        x: 1,
+        y: 0
+    }));
+
+    // Paths go around the terrain without any entity in the way
+    let open = |_: [i64; 2], _: &[&Player2D]| false;
+    let path = grid
+        .pathfind((-25.0, -25.0, 0.0), (35.0, -25.0, 0.0), open)
+        .unwrap();
+    assert!(path.iter().all(|c| c[0] != 1 || c[1] >= 3));
+    assert!(grid
+        .pathfind((-25.0, -25.0, 0.0), (15.0, 0.0, 0.0), open)
+        .is_none());
+
+    let field = grid.flow_field((35.0, -25.0, 0.0), |_, _| Some(1.0));
+    assert_eq!(field.cost([1, 0]), None);
+
+    let bank = Geometry::rect_from_corners((10.0, -20.0, 0.0), (30.0, 0.0, 0.0));
+    let spawns = grid.find_free_positions(&bank, 10, 10.0);
+    assert_eq!(spawns, vec![(25.0, -15.0, 0.0), (25.0, -5.0, 0.0)]);
+
+    // The blockage follows the area when the resolution changes
+    let mut grid = grid.with_floor_cells(0, [20, 20]);
+    assert!(grid.is_blocked((12.0, 0.0, 0.0)));
+    assert!(grid.is_blocked((18.0, 0.0, 0.0)));
+    assert!(!grid.is_blocked((22.0, 0.0, 0.0)));
+    assert!(!grid.is_blocked((8.0, 0.0, 0.0)));
+
+    grid.unmark_blocked((-10..6).map(|y| CellId { floor: 0, x: 2, y }));
+    assert!(!grid.is_blocked((12.0, 0.0, 0.0)));
+    assert!(grid.is_blocked((18.0, 0.0, 0.0)));
+}
+
+#[test]
+fn hotspots() {
+    use crate::hashgrid::CellId;
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    // Crowds in mirrored cells sharing the same hash cell
+    let players = [
+        Player2D::new(0, [15.0, 15.0]),
+        Player2D::new(1, [16.0, 12.0]),
+        Player2D::new(2, [-15.0, 15.0]),
+        Player2D::new(3, [-16.0, 12.0]),
+        Player2D::new(4, [-14.0, 18.0]),
+        Player2D::new(5, [-35.0, -35.0]),
+    ];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+
+    let hotspots = grid.hotspots(2);
+    assert_eq!(hotspots.len(), 2);
+    assert_eq!(
+        hotspots[0].cell,
+        CellId {
+            floor: 0,
+            x: -2,
+            y: 1
+        }
+    );
+    assert_eq!(hotspots[0].count, 3);
+    assert_eq!(hotspots[0].bounds, grid.cell_bounds(-2, 1, 0));
+    assert_eq!(
+        hotspots[1].cell,
+        CellId {
+            floor: 0,
+            x: 1,
+            y: 1
+        }
+    );
+    assert_eq!(hotspots[1].count, 2);
+
+    let all = grid.hotspots(10);
+    assert_eq!(
+        all.iter().map(|h| h.count).collect::<Vec<_>>(),
+        vec![3, 2, 1]
+    );
+    assert!(grid.hotspots(0).is_empty());
+}
+
+#[test]
+fn weighted_queries() {
+    use crate::hashgrid::WeightedEntity;
+
+    #[derive(Debug, PartialEq)]
+    struct Replica {
+        id: u32,
+        position: [f32; 2],
+        bytes: f32,
+    }
+
+    impl Entity for Replica {
+        type ID = u32;
+        fn id(&self) -> Self::ID {
+            self.id
+        }
+    }
+
+    impl Coordinate for Replica {
+        type Item = f32;
+        fn x(&self) -> Self::Item {
+            self.position[0]
+        }
+
+        fn y(&self) -> Self::Item {
+            self.position[1]
+        }
+    }
+
+    impl WeightedEntity for Replica {
+        fn weight(&self) -> Self::Item {
+            self.bytes
+        }
+    }
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+    let replicas = [
+        Replica {
+            id: 0,
+            position: [30.0, 0.0],
+            bytes: 10.0,
+        },
+        Replica {
+            id: 1,
+            position: [5.0, 0.0],
+            bytes: 40.0,
+        },
+        Replica {
+            id: 2,
+            position: [-10.0, 0.0],
+            bytes: 50.0,
+        },
+        Replica {
+            id: 3,
+            position: [0.0, 20.0],
+            bytes: f32::NAN,
+        },
+    ];
+
+    let mut grid = HashGrid::<f32, Replica>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&replicas);
+
+    let ids = |budget: f32| {
+        grid.query_weighted(&Geometry::radius((0.0, 0.0, 0.0), 40.0), budget)
+            .iter()
+            .map(|r| r.id)
+            .collect::<Vec<_>>()
+    };
+
+    // Nearest first, stopping at the first entity over the budget
+    assert_eq!(ids(100.0), vec![1, 2, 3, 0]);
+    assert_eq!(ids(95.0), vec![1, 2, 3]);
+    assert_eq!(ids(60.0), vec![1]);
+    assert!(ids(20.0).is_empty());
+}
+
+#[test]
+fn geometry_groups() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [-30.0, 10.0]),
+        Player2D::new(1, [30.0, 10.0]),
+        Player2D::new(2, [32.0, 10.0]),
+        Player2D::new(3, [0.0, 10.0]),
+    ];
+
+    let mut hashgrid_2d = HashGrid::<f32, Player2D>::new([4, 4], 0, &bounds_2d, false);
+    hashgrid_2d.update(&players);
+
+    let zone_a = Geometry::radius((-30.0, 10.0, 0.0), 5.0);
+    let zone_b = Geometry::radius((30.0, 10.0, 0.0), 5.0);
+    let zone_c = Geometry::radius((32.0, 10.0, 0.0), 1.0);
+
+    // In zone A or zone B but not C
+    let group = zone_a.union(zone_b).difference(zone_c);
+    assert!(group.is_valid());
+
+    let mut found = hashgrid_2d.query_group(&group);
+    found.sort_by_key(|p| p.id());
+    assert_eq!(found, vec![&players[0], &players[1]]);
+
+    let band = Geometry::rect((0.0, 10.0, 0.0), (80.0, 2.0, 0.0));
+    let group = band.intersection(Geometry::radius((20.0, 10.0, 0.0), 11.0));
+    let mut found = hashgrid_2d.query_group(&group);
+    found.sort_by_key(|p| p.id());
+    assert_eq!(found, vec![&players[1]]);
+    assert_eq!(
+        group.bounding_box(),
+        Some(((9.0, 9.0, 0.0), (31.0, 11.0, 0.0)))
+    );
+}
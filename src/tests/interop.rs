@@ -0,0 +1,80 @@
+#[cfg(feature = "glam")]
+#[test]
+fn glam_vectors() {
+    use glam::{Vec2, Vec3};
+
+    use crate::geometry::Geometry;
+    use crate::linear::LinearIndex;
+
+    let bounds = (Vec3::ZERO, Vec3::new(100.0, 100.0, 0.0));
+    let mut grid = crate::hashgrid::HashGrid::<f32, Vec2>::new([10, 10], 0, &bounds, false);
+
+    let points = [Vec2::new(5.0, 5.0), Vec2::new(-30.0, 12.0)];
+    for point in points.iter() {
+        grid.insert(point);
+    }
+
+    let mut index = LinearIndex::<f32, Vec2>::new();
+    index.insert(&points[1]);
+
+    let circle = Geometry::radius(Vec3::new(-30.0, 10.0, 0.0).into(), 5.0);
+    assert_eq!(grid.query_geometry(&circle), vec![&points[1]]);
+    assert_eq!(
+        index.query_geometry(&Geometry::from(points[1])),
+        vec![&points[1]]
+    );
+}
+
+#[cfg(feature = "nalgebra")]
+#[test]
+fn nalgebra_points() {
+    use nalgebra::{Point3, Vector3};
+
+    use crate::geometry::Geometry;
+    use crate::hashgrid::HashGrid;
+
+    let bounds = (
+        Point3::new(50.0, 50.0, 50.0),
+        Vector3::new(100.0, 100.0, 100.0),
+    );
+    let mut grid = HashGrid::<f64, Point3<f64>>::new([10, 10], 4, &bounds, false);
+
+    let points = [Point3::new(10.0, 10.0, 10.0), Point3::new(80.0, 20.0, 60.0)];
+    grid.update(&points);
+
+    let sphere = Geometry::radius((80.0, 20.0, 55.0), 10.0);
+    assert_eq!(grid.query_geometry(&sphere), vec![&points[1]]);
+    assert_eq!(
+        grid.query_geometry(&Geometry::from(points[0])),
+        vec![&points[0]]
+    );
+}
+
+#[cfg(feature = "geo")]
+#[test]
+fn geo_polygons() {
+    use geo_types::{coord, point, polygon, Rect};
+
+    use crate::geometry::Geometry;
+    use crate::hashgrid::HashGrid;
+
+    let world = super::grid::Bounds {
+        centre: [0.0; 3],
+        size: [100.0, 100.0, 0.0],
+    };
+    let mut grid = HashGrid::<f32, geo_types::Point<f32>>::new([10, 10], 0, &world, false);
+
+    let points = [
+        point!(x: 5.0, y: 5.0),
+        point!(x: 25.0, y: 5.0),
+        point!(x: -20.0, y: -20.0),
+    ];
+    grid.update(&points);
+
+    // triangle covering the first point only
+    let triangle = polygon![(x: 0.0, y: 0.0), (x: 20.0, y: 0.0), (x: 0.0, y: 20.0)];
+    assert_eq!(grid.query_polygon(&triangle), vec![&points[0]]);
+
+    let rect = Rect::new(coord! { x: -30.0, y: -30.0 }, coord! { x: -10.0, y: -10.0 });
+    assert_eq!(grid.query_geometry(&Geometry::from(rect)), vec![&points[2]]);
+}
@@ -0,0 +1,262 @@
+use super::grid::{Bounds, Player2D};
+use super::prelude::*;
+use crate::geometry::Geometry;
+use crate::hashgrid::HashGrid;
+use crate::manager::{Backend, InterestEvent, InterestManager, Subscriber};
+
+#[test]
+fn observer_enter_exit() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let mut manager = InterestManager::<f32, u32>::new([10, 10], 0, &bounds_2d, true);
+
+    // player 0 is both an observer and an entity
+    manager.subscribe(0, (0.0, 0.0, 0.0), 15.0);
+
+    let mut players = vec![
+        Player2D::new(0, [0.0, 0.0]),
+        Player2D::new(1, [10.0, 0.0]),
+        Player2D::new(2, [30.0, 0.0]),
+    ];
+
+    manager.update(&players);
+
+    let set = manager.interest_set(0).unwrap();
+    assert_eq!(set.entered, &[1]);
+    assert!(set.exited.is_empty());
+    assert_eq!(set.relevant.iter().copied().collect::<Vec<_>>(), vec![1]);
+
+    // nothing changes when the world is the same
+    manager.update(&players);
+    let set = manager.interest_set(0).unwrap();
+    assert!(set.entered.is_empty() && set.exited.is_empty());
+
+    // player 1 walks away while player 2 walks in
+    players[1].position = [-40.0, 0.0];
+    players[2].position = [-5.0, 5.0];
+    manager.update(&players);
+
+    let set = manager.interest_set(0).unwrap();
+    assert_eq!(set.entered, &[2]);
+    assert_eq!(set.exited, &[1]);
+
+    assert!(manager.unsubscribe(0));
+    assert_eq!(manager.interest_sets().count(), 0);
+}
+
+#[test]
+fn event_stream_with_hysteresis() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let mut manager = InterestManager::<f32, u32>::new([10, 10], 0, &bounds_2d, true);
+    manager.set_hysteresis(2.0);
+    manager.subscribe(0, (0.0, 0.0, 0.0), 10.0);
+
+    let mut players = vec![Player2D::new(1, [9.5, 0.0])];
+
+    manager.update(&players);
+    assert_eq!(
+        manager.poll_events().collect::<Vec<_>>(),
+        vec![InterestEvent::Entered {
+            observer: 0,
+            entity: 1
+        }]
+    );
+
+    // oscillating just outside the radius but within the margin produces no events
+    for x in [10.5, 9.8, 11.5, 9.9] {
+        players[0].position = [x, 0.0];
+        manager.update(&players);
+    }
+    assert_eq!(manager.poll_events().count(), 0);
+
+    // leaving the margin finally exits the entity
+    players[0].position = [12.5, 0.0];
+    manager.update(&players);
+
+    // re-entering requires being within the radius again
+    players[0].position = [11.0, 0.0];
+    manager.update(&players);
+
+    assert_eq!(
+        manager.poll_events().collect::<Vec<_>>(),
+        vec![InterestEvent::Exited {
+            observer: 0,
+            entity: 1
+        }]
+    );
+}
+
+#[test]
+fn region_subscriptions() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let mut manager = InterestManager::<f32, u32>::new([10, 10], 0, &bounds_2d, true);
+
+    // region ids do not collide with the entity ids
+    manager.subscribe_region(0, Geometry::rect((20.0, 20.0, 0.0), (10.0, 10.0, 0.0)));
+
+    let mut players = vec![Player2D::new(0, [0.0, 0.0]), Player2D::new(1, [22.0, 18.0])];
+
+    manager.update(&players);
+    assert_eq!(manager.region_set(0).unwrap().entered, &[1]);
+
+    // an observer sharing the id of the region is told apart by its subscriber
+    manager.subscribe(0, (0.0, 0.0, 0.0), 5.0);
+    assert_eq!(
+        manager.region_set(0).unwrap().subscriber,
+        Subscriber::Region(0)
+    );
+    assert_eq!(
+        manager.interest_set(0).unwrap().subscriber,
+        Subscriber::Observer(0)
+    );
+    manager.unsubscribe(0);
+
+    players[0].position = [16.0, 24.0];
+    players[1].position = [40.0, 40.0];
+    manager.update(&players);
+
+    assert_eq!(
+        manager.poll_events().collect::<Vec<_>>(),
+        vec![
+            InterestEvent::RegionEntered {
+                region: 0,
+                entity: 1
+            },
+            InterestEvent::RegionExited {
+                region: 0,
+                entity: 1
+            },
+            InterestEvent::RegionEntered {
+                region: 0,
+                entity: 0
+            },
+        ]
+    );
+}
+
+#[test]
+fn backend_migration() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let mut manager = InterestManager::<f32, u32>::with_backend(Backend::Linear, &bounds_2d);
+    manager.subscribe(0, (0.0, 0.0, 0.0), 10.0);
+
+    let mut players = vec![Player2D::new(1, [5.0, 5.0]), Player2D::new(2, [20.0, 0.0])];
+    manager.update(&players);
+    assert_eq!(manager.poll_events().count(), 1);
+
+    // migrating to a grid backend does not produce any spurious events
+    let backend = Backend::HashGrid {
+        cells: [10, 10],
+        floors: 0,
+        wrap: true,
+    };
+    manager.set_backend(backend);
+    manager.update(&players);
+    assert_eq!(manager.backend(), backend);
+    assert_eq!(manager.poll_events().count(), 0);
+
+    // an externally maintained structure can drive the updates as well
+    players[1].position = [0.0, -8.0];
+    let mut grid = HashGrid::<f32, Player2D>::new([4, 4], 0, &bounds_2d, true);
+    grid.update(&players);
+    manager.update_with(&grid);
+
+    assert_eq!(
+        manager.poll_events().collect::<Vec<_>>(),
+        vec![InterestEvent::Entered {
+            observer: 0,
+            entity: 2
+        }]
+    );
+}
+
+#[test]
+fn replication_budget() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let mut manager = InterestManager::<f32, u32>::new([10, 10], 0, &bounds_2d, true);
+    manager.subscribe(0, (0.0, 0.0, 0.0), 20.0);
+
+    let players = vec![
+        Player2D::new(1, [15.0, 0.0]),
+        Player2D::new(2, [2.0, 0.0]),
+        Player2D::new(3, [0.0, 8.0]),
+    ];
+
+    manager.update(&players);
+
+    // the closest entities come first
+    assert_eq!(manager.replication_list(0, 2), vec![2, 3]);
+
+    // the entity left out ages and gets its turn on the next update
+    manager.update(&players);
+    assert_eq!(manager.replication_list(0, 1), vec![1]);
+
+    assert!(manager.replication_list(7, 4).is_empty());
+}
+
+#[test]
+fn relocated_entities() {
+    use crate::linear::LinearIndex;
+
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [Player2D::new(1, [40.0, 0.0]), Player2D::new(2, [5.0, 0.0])];
+
+    let mut grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    grid.update(&players);
+    let mut linear = LinearIndex::<f32, Player2D>::new();
+    players.iter().for_each(|p| linear.insert(p));
+
+    // Player 1 is moved next to the observer, player 2 away from it, neither of them
+    // reporting the new coordinates
+    assert!(grid.relocate(1, (5.0, 5.0, 0.0)));
+    assert!(grid.relocate(2, (-40.0, 0.0, 0.0)));
+    assert!(linear.relocate(1, (5.0, 5.0, 0.0)));
+    assert!(linear.relocate(2, (-40.0, 0.0, 0.0)));
+
+    let mut from_grid = InterestManager::<f32, u32>::new([10, 10], 0, &bounds_2d, false);
+    let mut from_linear = InterestManager::<f32, u32>::new([10, 10], 0, &bounds_2d, false);
+    for manager in [&mut from_grid, &mut from_linear] {
+        manager.subscribe(0, (0.0, 0.0, 0.0), 15.0);
+        manager.subscribe_region(7, Geometry::rect((-40.0, 0.0, 0.0), (4.0, 4.0, 0.0)));
+    }
+    from_grid.update_with(&grid);
+    from_linear.update_with(&linear);
+
+    for manager in [&from_grid, &from_linear] {
+        let set = manager.interest_set(0).unwrap();
+        assert_eq!(set.relevant.iter().copied().collect::<Vec<_>>(), vec![1]);
+        assert_eq!(
+            manager
+                .region_set(7)
+                .unwrap()
+                .relevant
+                .iter()
+                .copied()
+                .collect::<Vec<_>>(),
+            vec![2]
+        );
+    }
+}
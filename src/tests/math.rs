@@ -0,0 +1,52 @@
+use core::f64::consts::{FRAC_PI_2, PI};
+
+use crate::math::{
+    angle_between, angle_to_bearing, bearing, bearing_difference, bearing_to_angle,
+    signed_angle_between, wrap_angle, wrap_degrees,
+};
+
+const EPSILON: f64 = 1e-9;
+
+fn assert_close(a: f64, b: f64) {
+    assert!((a - b).abs() < EPSILON, "{a} != {b}");
+}
+
+#[test]
+fn angle_wrapping() {
+    assert_close(wrap_angle(3.0 * PI), PI);
+    assert_close(wrap_angle(-3.0 * PI), PI);
+    assert_close(wrap_angle(-FRAC_PI_2), -FRAC_PI_2);
+    assert_close(wrap_angle(2.0 * PI + 0.5), 0.5);
+
+    assert_close(wrap_degrees(370.0), 10.0);
+    assert_close(wrap_degrees(-90.0), 270.0);
+
+    // generic over the base float type as well
+    let wrapped = wrap_angle(4.5_f32 * core::f32::consts::PI);
+    assert!((wrapped - core::f32::consts::FRAC_PI_2).abs() < 1e-5);
+}
+
+#[test]
+fn angles_between_vectors() {
+    assert_close(angle_between((1.0, 0.0), (0.0, 1.0)), FRAC_PI_2);
+    assert_close(angle_between((0.0, 1.0), (1.0, 0.0)), FRAC_PI_2);
+    assert_close(angle_between((1.0, 0.0), (-1.0, 0.0)), PI);
+
+    assert_close(signed_angle_between((1.0, 0.0), (0.0, 1.0)), FRAC_PI_2);
+    assert_close(signed_angle_between((0.0, 1.0), (1.0, 0.0)), -FRAC_PI_2);
+}
+
+#[test]
+fn compass_bearings() {
+    let origin = (0.0, 0.0);
+
+    assert_close(bearing(origin, (0.0, 10.0)), 0.0);
+    assert_close(bearing(origin, (10.0, 0.0)), 90.0);
+    assert_close(bearing(origin, (0.0, -10.0)), 180.0);
+    assert_close(bearing(origin, (-10.0, 0.0)), 270.0);
+
+    assert_close(angle_to_bearing(bearing_to_angle(135.0)), 135.0);
+
+    assert_close(bearing_difference(350.0, 10.0), 20.0);
+    assert_close(bearing_difference(10.0, 350.0), -20.0);
+}
@@ -1,3 +1,25 @@
 #![cfg(test)]
 
+/// Items of the std prelude the tests rely on, taken from alloc so that the tests also build
+/// without the std feature
+mod prelude {
+    pub use alloc::boxed::Box;
+    pub use alloc::string::{String, ToString};
+    pub use alloc::{format, vec, vec::Vec};
+}
+
+mod aggregate;
+mod entity;
+mod field;
+mod geofence;
 mod grid;
+mod interop;
+mod manager;
+mod math;
+mod observer;
+mod relevance;
+#[cfg(feature = "serde")]
+mod serialization;
+mod shards;
+mod tiered;
+mod trajectory;
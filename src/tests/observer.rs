@@ -0,0 +1,61 @@
+use super::grid::{Bounds, Player2D};
+use super::prelude::*;
+use crate::hashgrid::HashGrid;
+use crate::observer::{Observed, Observer};
+use crate::partition::{SpatialInsertion, SpatialRemoval, SpatialUpdate};
+
+#[derive(Default)]
+struct Replication {
+    changes: Vec<String>,
+}
+
+impl Observer<&Player2D> for Replication {
+    type Id = u32;
+    type Position = (f32, f32, f32);
+
+    fn on_insert(&mut self, item: &&Player2D) {
+        self.changes.push(format!("insert {}", item.id));
+    }
+
+    fn on_remove(&mut self, id: &u32) {
+        self.changes.push(format!("remove {id}"));
+    }
+
+    fn on_relocate(&mut self, id: &u32, position: &(f32, f32, f32)) {
+        self.changes
+            .push(format!("relocate {id} to {:?}", position));
+    }
+}
+
+#[test]
+fn observed_mutations() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [Player2D::new(0, [5.0, 5.0]), Player2D::new(1, [25.0, 25.0])];
+
+    let grid = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, false);
+    let mut observed = Observed::new(grid, Replication::default());
+
+    observed.insert_all(&players);
+    assert!(observed.relocate(0, (15.0, 15.0, 0.0)));
+    assert!(observed.remove(1));
+
+    // Failed mutations are not reported
+    assert!(!observed.remove(1));
+    assert!(!observed.relocate(1, (15.0, 15.0, 0.0)));
+
+    let (grid, replication) = observed.into_inner();
+    assert_eq!(
+        replication.changes,
+        vec![
+            "insert 0",
+            "insert 1",
+            "relocate 0 to (15.0, 15.0, 0.0)",
+            "remove 1",
+        ]
+    );
+    assert_eq!(grid.occupied_cells(0), 1);
+}
@@ -0,0 +1,65 @@
+use super::grid::{Bounds, Player2D};
+use super::prelude::*;
+use crate::hashgrid::HashGrid;
+use crate::relevance::{Falloff, Priority, RelevanceProfile, Tiers};
+
+#[test]
+fn falloff_scores() {
+    let tiers = Tiers {
+        high: 10.0,
+        medium: 20.0,
+        low: 40.0,
+    };
+
+    let step = RelevanceProfile::new(tiers, Falloff::Step);
+    assert_eq!(step.score(39.0), 1.0);
+    assert_eq!(step.score(41.0), 0.0);
+
+    let linear = RelevanceProfile::new(tiers, Falloff::Linear);
+    assert_eq!(linear.score(0.0), 1.0);
+    assert_eq!(linear.score(20.0), 0.5);
+
+    let inverse = RelevanceProfile::new(tiers, Falloff::InverseSquare);
+    assert_eq!(inverse.score(5.0), 1.0);
+    assert_eq!(inverse.score(20.0), 0.25);
+
+    assert_eq!(tiers.priority(10.0), Some(Priority::High));
+    assert_eq!(tiers.priority(15.0), Some(Priority::Medium));
+    assert_eq!(tiers.priority(40.0), Some(Priority::Low));
+    assert_eq!(tiers.priority(40.5), None);
+}
+
+#[test]
+fn prioritized_grid_query() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let players = [
+        Player2D::new(0, [2.0, 0.0]),
+        Player2D::new(1, [15.0, 0.0]),
+        Player2D::new(2, [0.0, 30.0]),
+        Player2D::new(3, [45.0, 45.0]),
+    ];
+
+    let mut hashgrid_2d = HashGrid::<f32, Player2D>::new([10, 10], 0, &bounds_2d, true);
+    hashgrid_2d.update(&players);
+
+    let profile = RelevanceProfile::new(
+        Tiers {
+            high: 10.0,
+            medium: 20.0,
+            low: 40.0,
+        },
+        Falloff::Linear,
+    );
+
+    let res = hashgrid_2d.query_prioritized((0.0, 0.0, 0.0), &profile);
+
+    let ids = |p: Priority| res.bucket(p).iter().map(|(e, _)| e.id).collect::<Vec<_>>();
+    assert_eq!(ids(Priority::High), vec![0]);
+    assert_eq!(ids(Priority::Medium), vec![1]);
+    assert_eq!(ids(Priority::Low), vec![2]);
+    assert_eq!(res.len(), 3);
+}
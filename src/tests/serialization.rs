@@ -0,0 +1,48 @@
+use crate::geometry::Geometry;
+use crate::hashgrid::{CoordinateFrame, Origin, Query, QueryType, YAxis};
+use crate::manager::Backend;
+
+#[test]
+fn configuration_round_trip() {
+    let backend = Backend::HashGrid {
+        cells: [16, 8],
+        floors: 2,
+        wrap: true,
+    };
+    let json = serde_json::to_string(&backend).unwrap();
+    assert_eq!(serde_json::from_str::<Backend>(&json).unwrap(), backend);
+
+    let frame = CoordinateFrame {
+        origin: Origin::Corner,
+        y_axis: YAxis::Down,
+    };
+    let json = serde_json::to_string(&frame).unwrap();
+    assert_eq!(
+        serde_json::from_str::<CoordinateFrame>(&json).unwrap(),
+        frame
+    );
+}
+
+#[test]
+fn query_round_trip() {
+    let query = Query::<f32, u32>::from((10.0, -5.0, 2.5), QueryType::Find(7), 12.0);
+    let json = serde_json::to_string(&query).unwrap();
+    let decoded = serde_json::from_str::<Query<f32, u32>>(&json).unwrap();
+
+    assert_eq!(decoded.radius, query.radius);
+    assert_eq!(decoded.coordinates, query.coordinates);
+    assert!(matches!(decoded.ty, QueryType::Find(7)));
+
+    let geometries = [
+        Geometry::rect((0.0_f32, 0.0, 0.0), (10.0, 20.0, 0.0)),
+        Geometry::radius((1.0, 2.0, 3.0), 4.0),
+        Geometry::hslab(-1.0, 1.0),
+    ];
+    for geometry in geometries {
+        let json = serde_json::to_string(&geometry).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Geometry<f32>>(&json).unwrap(),
+            geometry
+        );
+    }
+}
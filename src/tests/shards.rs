@@ -0,0 +1,107 @@
+use super::grid::Player2D;
+use super::prelude::*;
+use crate::error::SpatialError;
+use crate::geometry::Geometry;
+use crate::partition::{SpatialQuery, SpatialUpdate};
+use crate::shards::GridShards;
+
+#[test]
+fn sharded_world() {
+    let players = [
+        Player2D::new(0, [10.0, 10.0]),
+        Player2D::new(1, [-10.0, 10.0]),
+        Player2D::new(2, [990.0, 10.0]),
+        Player2D::new(3, [1010.0, 10.0]),
+    ];
+    let moved = Player2D::new(0, [1990.0, 10.0]);
+
+    let mut shards = GridShards::<f32, Player2D>::new([1000.0, 1000.0, 0.0], [10, 10], 0);
+    assert!(shards.is_empty());
+
+    shards.update(&players);
+
+    // Shards are only created for the regions holding entities
+    assert_eq!(shards.len(), 3);
+    assert_eq!(
+        shards.shards().map(|(key, _)| key).collect::<Vec<_>>(),
+        vec![(-1, 0), (0, 0), (1, 0)]
+    );
+    assert!(shards.shard((5, 5)).is_none());
+
+    // Queries fan out across the borders of the shards
+    let border = Geometry::radius((1000.0, 10.0, 0.0), 20.0);
+    assert_eq!(
+        shards.query_geometry(&border),
+        vec![&players[2], &players[3]]
+    );
+
+    let origin = Geometry::rect((0.0, 10.0, 0.0), (40.0, 40.0, 0.0));
+    assert_eq!(
+        SpatialQuery::query(&shards, &origin).collect::<Vec<_>>(),
+        vec![&players[1], &players[0]]
+    );
+
+    // Ids are unique across the shards, whichever region the duplicate lands in
+    assert_eq!(shards.try_insert(&moved), Err(SpatialError::DuplicateId(0)));
+    assert_eq!(shards.len(), 3);
+
+    assert!(shards.remove(3));
+    assert_eq!(shards.query_geometry(&border), vec![&players[2]]);
+
+    // Relocated entities are handed over to the shard of their new region
+    assert!(shards.relocate(2, (2500.0, 10.0, 0.0)));
+    assert_eq!(shards.len(), 4);
+    assert!(shards.query_geometry(&border).is_empty());
+    assert_eq!(
+        shards.query_geometry(&Geometry::radius((2500.0, 10.0, 0.0), 5.0)),
+        vec![&players[2]]
+    );
+    assert!(SpatialUpdate::relocate(&mut shards, 2, (2510.0, 10.0, 0.0)));
+    assert_eq!(
+        shards
+            .shard((2, 0))
+            .unwrap()
+            .query_geometry(&Geometry::radius((2510.0, 10.0, 0.0), 1.0)),
+        vec![&players[2]]
+    );
+
+    // Failed relocations leave the entity and the shards untouched
+    assert_eq!(
+        shards.try_relocate(2, (5000.0, 10.0, 5.0)),
+        Err(SpatialError::OutOfBounds([5000.0, 10.0, 5.0]))
+    );
+    assert_eq!(
+        shards.try_relocate(9, (0.0, 0.0, 0.0)),
+        Err(SpatialError::UnknownEntity(9))
+    );
+    assert_eq!(shards.len(), 4);
+    assert!(shards.shard((5, 0)).is_none());
+    assert_eq!(
+        shards.query_geometry(&Geometry::radius((2510.0, 10.0, 0.0), 1.0)),
+        vec![&players[2]]
+    );
+}
+
+#[test]
+fn shards_far_from_origin() {
+    let far = Player2D::new(0, [1.0e6, 1.0e6]);
+
+    let mut shards = GridShards::<f32, Player2D>::new([100.0, 100.0, 0.0], [10, 10], 0);
+    assert_eq!(shards.try_insert(&far), Ok(()));
+    assert_eq!(shards.shard_key((1.0e6, 1.0e6, 0.0)), (10000, 10000));
+
+    // Cells are counted from the corner of the shard, not from the world origin
+    let shard = shards.shard((10000, 10000)).unwrap();
+    assert_eq!(shard.get_cell_coordinates((1.0e6, 1.0e6, 0.0)), (0, 0, 0));
+
+    let around = Geometry::radius((1.0e6, 1.0e6, 0.0), 5.0);
+    assert_eq!(shards.query_geometry(&around), vec![&far]);
+
+    assert_eq!(shards.try_relocate(0, (1.0e6 + 150.0, 1.0e6, 0.0)), Ok(()));
+    assert!(shards.query_geometry(&around).is_empty());
+    assert_eq!(
+        shards.query_geometry(&Geometry::radius((1.0e6 + 150.0, 1.0e6, 0.0), 5.0)),
+        vec![&far]
+    );
+    assert!(shards.shard((10001, 10000)).is_some());
+}
@@ -0,0 +1,46 @@
+use super::grid::{Bounds, Player2D};
+use super::prelude::*;
+use crate::geometry::Geometry;
+use crate::partition::SpatialQuery;
+use crate::tiered::TieredIndex;
+
+#[test]
+fn static_and_dynamic_tiers() {
+    let bounds_2d = Bounds {
+        centre: [0_f32; 3],
+        size: [100_f32, 100_f32, 0_f32],
+    };
+
+    let buildings = [
+        Player2D::new(0, [30.0, 0.0]),
+        Player2D::new(1, [-10.0, 5.0]),
+        Player2D::new(2, [5.0, 40.0]),
+        Player2D::new(3, [0.0, f32::NAN]),
+    ];
+    let units = [
+        Player2D::new(10, [-5.0, -5.0]),
+        Player2D::new(11, [45.0, 45.0]),
+    ];
+
+    let mut index = TieredIndex::new(&buildings, [10, 10], 0, &bounds_2d, false);
+    units.iter().for_each(|u| index.insert(u));
+
+    // The static layer is packed along the x axis, without the invalid entity
+    assert_eq!(
+        index.statics(),
+        &[&buildings[1], &buildings[2], &buildings[0]]
+    );
+
+    let area = Geometry::rect((0.0, 0.0, 0.0), (30.0, 30.0, 0.0));
+    assert_eq!(index.query_geometry(&area), vec![&buildings[1], &units[0]]);
+
+    // Only the dynamic entities can be moved or removed
+    assert!(index.relocate(11, (40.0, 40.0, 0.0)));
+    assert!(!index.relocate(1, (40.0, 40.0, 0.0)));
+    assert!(!index.remove(1));
+    assert!(index.remove(10));
+
+    let mut results = Vec::new();
+    SpatialQuery::query_into(&index, &area, &mut results);
+    assert_eq!(results, vec![&buildings[1]]);
+}
@@ -0,0 +1,36 @@
+use super::prelude::*;
+use crate::trajectory::TrajectoryIndex;
+
+#[test]
+fn lag_compensated_queries() {
+    let mut history = TrajectoryIndex::<f32, u32>::new(3);
+
+    // A runner moving along the x-axis and a player standing still
+    assert!(history.record(0, 0.0, (0.0, 0.0, 0.0)));
+    assert!(history.record(0, 1.0, (10.0, 0.0, 0.0)));
+    assert!(history.record(1, 0.0, (5.0, 5.0, 0.0)));
+
+    assert_eq!(history.position_at(0, 0.5), Some((5.0, 0.0, 0.0)));
+    assert_eq!(history.position_at(0, 2.0), None);
+    assert_eq!(history.position_at(2, 0.5), None);
+
+    // Who was near the origin half a second ago
+    assert_eq!(history.query_at((5.0, 0.0, 0.0), 1.0, 0.5), vec![0]);
+    assert_eq!(history.query_at((5.0, 2.0, 0.0), 3.0, 0.0), vec![1]);
+
+    // Samples out of order are rejected
+    assert!(!history.record(0, 0.5, (0.0, 0.0, 0.0)));
+
+    // The oldest sample is dropped once the ring buffer is full
+    assert!(history.record(0, 2.0, (20.0, 0.0, 0.0)));
+    assert!(history.record(0, 3.0, (30.0, 0.0, 0.0)));
+    assert_eq!(history.samples(0).count(), 3);
+    assert_eq!(history.position_at(0, 0.5), None);
+
+    history.prune_before(2.5);
+    assert_eq!(history.position_at(0, 2.5), Some((25.0, 0.0, 0.0)));
+    assert_eq!(history.samples(0).count(), 2);
+
+    assert!(history.remove(1));
+    assert_eq!(history.len(), 1);
+}
@@ -0,0 +1,216 @@
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use num_traits::{Float, FromPrimitive, ToPrimitive};
+
+use crate::error::SpatialError;
+use crate::geometry::Geometry;
+use crate::hashgrid::{Boundary, Coordinate, DataIndex, Entity, HashGrid};
+use crate::partition::{Positioned, SpatialInsertion, SpatialQuery, SpatialRemoval, SpatialUpdate};
+
+/// # Tiered Index
+///
+/// Splits the entities of a world into two tiers. Static entities, such as buildings or
+/// props which never move, are bulk built once into a packed immutable layer sorted along the
+/// x-axis. Dynamic entities live in a regular [`HashGrid`], where they can be inserted,
+/// relocated and removed. Queries merge both tiers, the static entities coming first.
+///
+/// The static layer never pays for relocations or cell bookkeeping, and its entities are
+/// stored contiguously, which keeps the queries cache friendly for the bulk of the world.
+///
+/// TieredIndex is parameterized over:
+///
+/// * `F (Float type):` Defines the base float type such as `f32` or `f64` for spatial components
+/// * `T (generic data type):` Defines the data type to insert into the index, data must live as long as the index lives
+#[derive(Debug)]
+pub struct TieredIndex<'a, F, T> {
+    /// x coordinates of the static entities, kept apart from the references so the range
+    /// search only walks a packed list of floats
+    static_x: Vec<F>,
+    statics: Vec<&'a T>,
+    dynamics: HashGrid<'a, F, T>,
+}
+
+impl<'a, F, T> TieredIndex<'a, F, T>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    T: Coordinate<Item = F>,
+{
+    /// Bulk builds the static layer out of the `statics`, along with an empty dynamic grid.
+    ///
+    /// See [`HashGrid::new`] for the description of the grid parameters. Static entities with
+    /// non-finite coordinates are left out.
+    pub fn new<B, I>(statics: I, cells: [u32; 2], floors: usize, bounds: &B, wrap: bool) -> Self
+    where
+        B: Boundary<Item = F>,
+        I: IntoIterator<Item = &'a T>,
+    {
+        let mut statics: Vec<&'a T> = statics
+            .into_iter()
+            .filter(|e| e.x().is_finite() && e.y().is_finite() && e.z().is_finite())
+            .collect();
+        statics.sort_by(|a, b| a.x().partial_cmp(&b.x()).unwrap_or(Ordering::Equal));
+
+        Self {
+            static_x: statics.iter().map(|e| e.x()).collect(),
+            statics,
+            dynamics: HashGrid::new(cells, floors, bounds, wrap),
+        }
+    }
+
+    /// Inserts the reference to a dynamic entity into the grid of the dynamic tier, see
+    /// [`HashGrid::insert`]
+    pub fn insert(&mut self, entity: &'a T) {
+        self.dynamics.insert(entity);
+    }
+
+    /// Same as [`TieredIndex::insert`], except that the reason for not inserting the entity
+    /// is reported as a [`SpatialError`], an id already in either tier included
+    pub fn try_insert<Id>(&mut self, entity: &'a T) -> Result<(), SpatialError<Id>>
+    where
+        Id: DataIndex,
+        T: Entity<ID = Id>,
+    {
+        if self.statics.iter().any(|e| e.id() == entity.id()) {
+            return Err(SpatialError::DuplicateId(entity.id()));
+        }
+        self.dynamics.try_insert(entity)
+    }
+
+    /// Removes the dynamic entity matching the `id`. Static entities are never removed.
+    ///
+    /// Returns `true` if the entity was found and removed.
+    pub fn remove<Id>(&mut self, id: Id) -> bool
+    where
+        Id: DataIndex,
+        T: Entity<ID = Id>,
+    {
+        self.dynamics.remove(id)
+    }
+
+    /// Moves the dynamic entity matching the `id`, see [`HashGrid::relocate`]. Static entities
+    /// are never relocated.
+    ///
+    /// Returns `true` if the entity was found and relocated.
+    pub fn relocate<Id>(&mut self, id: Id, coordinates: (F, F, F)) -> bool
+    where
+        Id: DataIndex,
+        T: Entity<ID = Id>,
+    {
+        self.dynamics.relocate(id, coordinates)
+    }
+
+    /// Returns every entity of both tiers located inside the [`Geometry`]
+    pub fn query_geometry(&self, geometry: &Geometry<F>) -> Vec<&'a T> {
+        let mut results = Vec::new();
+        self.query_into(geometry, &mut results);
+        results
+    }
+
+    /// Same as [`TieredIndex::query_geometry`], except that the entities are written into the
+    /// `results` buffer, which is cleared first
+    pub fn query_into(&self, geometry: &Geometry<F>, results: &mut Vec<&'a T>) {
+        let (min, max) = geometry.bounding_box();
+
+        // Static entities within the x extent of the geometry form a contiguous run
+        let start = self.static_x.partition_point(|&x| x < min.0);
+        let end = self.static_x.partition_point(|&x| x <= max.0);
+
+        self.dynamics.query_into(geometry, results);
+        let dynamics = results.len();
+
+        results.extend(
+            self.statics[start..end]
+                .iter()
+                .filter(|e| geometry.contains((e.x(), e.y(), e.z())))
+                .copied(),
+        );
+
+        // Moving the static entities in front without going through another buffer
+        results.rotate_left(dynamics);
+    }
+
+    /// Returns the static entities, sorted along the x-axis
+    pub fn statics(&self) -> &[&'a T] {
+        &self.statics
+    }
+
+    /// Returns the grid holding the dynamic entities
+    pub fn dynamics(&self) -> &HashGrid<'a, F, T> {
+        &self.dynamics
+    }
+}
+
+impl<'a, F, T> SpatialInsertion for TieredIndex<'a, F, T>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    T: Coordinate<Item = F>,
+{
+    type Item = &'a T;
+
+    fn insert(&mut self, item: Self::Item) {
+        TieredIndex::insert(self, item)
+    }
+}
+
+impl<'a, F, T> SpatialQuery for TieredIndex<'a, F, T>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    T: Coordinate<Item = F>,
+{
+    type Item = &'a T;
+    type Scalar = F;
+    type Results = alloc::vec::IntoIter<&'a T>;
+
+    fn query(&self, geometry: &Geometry<F>) -> Self::Results {
+        self.query_geometry(geometry).into_iter()
+    }
+
+    fn query_into(&self, geometry: &Geometry<F>, results: &mut Vec<Self::Item>) {
+        TieredIndex::query_into(self, geometry, results)
+    }
+
+    fn query_positioned_into(
+        &self,
+        geometry: &Geometry<F>,
+        results: &mut Vec<Positioned<Self::Item, F>>,
+    ) {
+        let (min, max) = geometry.bounding_box();
+        let start = self.static_x.partition_point(|&x| x < min.0);
+        let end = self.static_x.partition_point(|&x| x <= max.0);
+
+        results.clear();
+        results.extend(
+            self.statics[start..end]
+                .iter()
+                .map(|&e| (e, (e.x(), e.y(), e.z())))
+                .filter(|&(_, position)| geometry.contains(position)),
+        );
+        self.dynamics.query_positioned_into(geometry, results);
+    }
+}
+
+impl<F, T> SpatialRemoval for TieredIndex<'_, F, T>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    T: Coordinate<Item = F> + Entity,
+{
+    type Id = T::ID;
+
+    fn remove(&mut self, id: Self::Id) -> bool {
+        TieredIndex::remove(self, id)
+    }
+}
+
+impl<F, T> SpatialUpdate for TieredIndex<'_, F, T>
+where
+    F: Float + FromPrimitive + ToPrimitive,
+    T: Coordinate<Item = F> + Entity,
+{
+    type Id = T::ID;
+    type Position = (F, F, F);
+
+    fn relocate(&mut self, id: Self::Id, position: Self::Position) -> bool {
+        TieredIndex::relocate(self, id, position)
+    }
+}
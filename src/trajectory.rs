@@ -0,0 +1,156 @@
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec::Vec;
+
+use num_traits::Float;
+
+use crate::hashgrid::DataIndex;
+use crate::math::distance_sq;
+
+/// Timestamped positions of a single entity, from the oldest to the latest
+type Samples<F> = VecDeque<(F, (F, F, F))>;
+
+/// # Trajectory Index
+///
+/// Keeps the recent history of the positions of every entity, so the world can be queried
+/// as it was at some point in the past. Game servers use it for lag compensation, validating
+/// a hit against the positions the shooter saw on their screen rather than the current ones.
+///
+/// Every entity keeps up to `capacity` timestamped samples in a ring buffer, the oldest
+/// sample being dropped once the buffer is full. Positions between two samples are linearly
+/// interpolated.
+///
+/// TrajectoryIndex is parameterized over:
+///
+/// * `F (Float type):` Defines the base float type such as `f32` or `f64` for the positions and timestamps
+/// * `Id (DataIndex type):` Defines the type of the unique ids of the entities
+#[derive(Debug, Clone)]
+pub struct TrajectoryIndex<F, Id> {
+    capacity: usize,
+    trajectories: BTreeMap<Id, Samples<F>>,
+}
+
+impl<F, Id> TrajectoryIndex<F, Id>
+where
+    F: Float,
+    Id: DataIndex,
+{
+    /// Creates an empty [`TrajectoryIndex`] keeping up to `capacity` samples per entity,
+    /// with a minimum of one sample
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            trajectories: BTreeMap::new(),
+        }
+    }
+
+    /// Records the position of the entity at the `time`.
+    ///
+    /// Samples must be recorded in chronological order per entity. A sample older than the
+    /// latest one of the entity is rejected, while a sample at the same time replaces it.
+    ///
+    /// Returns `true` if the sample was recorded.
+    pub fn record(&mut self, id: Id, time: F, position: (F, F, F)) -> bool {
+        let samples = self
+            .trajectories
+            .entry(id)
+            .or_insert_with(|| VecDeque::with_capacity(self.capacity));
+
+        match samples.back_mut() {
+            Some(latest) if time < latest.0 => return false,
+            Some(latest) if time == latest.0 => {
+                latest.1 = position;
+                return true;
+            }
+            _ => {}
+        }
+
+        if samples.len() == self.capacity {
+            samples.pop_front();
+        }
+        samples.push_back((time, position));
+
+        true
+    }
+
+    /// Forgets the whole history of the entity.
+    ///
+    /// Returns `true` if the entity had any recorded sample.
+    pub fn remove(&mut self, id: Id) -> bool {
+        self.trajectories.remove(&id).is_some()
+    }
+
+    /// Drops the samples older than the `time` for every entity, keeping the latest sample
+    /// before it so positions at the `time` can still be interpolated
+    pub fn prune_before(&mut self, time: F) {
+        for samples in self.trajectories.values_mut() {
+            while samples.len() > 1 && samples[1].0 <= time {
+                samples.pop_front();
+            }
+        }
+    }
+
+    /// Position of the entity at the `time`, interpolated between the surrounding samples.
+    ///
+    /// Returns `None` if the entity is unknown or the `time` lies outside of its recorded
+    /// history.
+    pub fn position_at(&self, id: Id, time: F) -> Option<(F, F, F)> {
+        interpolate(self.trajectories.get(&id)?, time)
+    }
+
+    /// Returns the ids of the entities which were within the `radius` of the `point` at the
+    /// `time`, in ascending id order. Entities without a recorded position at the `time`
+    /// are ignored.
+    pub fn query_at(&self, point: (F, F, F), radius: F, time: F) -> Vec<Id> {
+        let radius_sq = radius * radius;
+
+        self.trajectories
+            .iter()
+            .filter_map(|(&id, samples)| Some((id, interpolate(samples, time)?)))
+            .filter(|&(_, position)| distance_sq(point, position) <= radius_sq)
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Returns the recorded samples of the entity, from the oldest to the latest
+    pub fn samples(&self, id: Id) -> impl Iterator<Item = (F, (F, F, F))> + '_ {
+        self.trajectories.get(&id).into_iter().flatten().copied()
+    }
+
+    /// Maximum number of samples kept per entity
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of entities with a recorded history
+    pub fn len(&self) -> usize {
+        self.trajectories.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trajectories.is_empty()
+    }
+}
+
+/// Linearly interpolates the position at the `time` between the surrounding samples
+fn interpolate<F: Float>(samples: &Samples<F>, time: F) -> Option<(F, F, F)> {
+    let (first, last) = (samples.front()?, samples.back()?);
+    if time < first.0 || time > last.0 {
+        return None;
+    }
+
+    // Index of the first sample at or after the time
+    let next = samples.partition_point(|&(t, _)| t < time);
+    let (t1, p1) = samples[next];
+    if next == 0 || t1 == time {
+        return Some(p1);
+    }
+
+    let (t0, p0) = samples[next - 1];
+    let ratio = (time - t0) / (t1 - t0);
+
+    Some((
+        p0.0 + (p1.0 - p0.0) * ratio,
+        p0.1 + (p1.1 - p0.1) * ratio,
+        p0.2 + (p1.2 - p0.2) * ratio,
+    ))
+}